@@ -1,3 +1,738 @@
+use std::io::{self, BufRead, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+
+use rust_chess_engine::book::Book;
+use rust_chess_engine::evaluation::EvalEngine;
+use rust_chess_engine::movegen::{Move, generate_legal_moves_in_place};
+use rust_chess_engine::position::{Color, Position};
+use rust_chess_engine::search::time::MOVE_OVERHEAD_MS;
+use rust_chess_engine::search::{Clock, IterationInfo, MATE, SearchLimits, SearchResult, Searcher};
+use rust_chess_engine::tablebase::Tablebase;
+
+const ENGINE_NAME: &str = "rust_chess_engine";
+const ENGINE_AUTHOR: &str = "rust_chess_engine developers";
+
+const MAX_THREADS: usize = 256;
+const MAX_MULTI_PV: usize = 256;
+const DEFAULT_BOOK_DEPTH: u32 = 20;
+const MAX_BOOK_DEPTH: u32 = 1000;
+
 fn main() {
-    println!("Engine binary place-holder");
+    let mut engine = UciEngine::new();
+    let stdin = io::stdin();
+
+    for line in stdin.lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let input = line.trim();
+        if input.is_empty() {
+            continue;
+        }
+        if engine.handle_line(input) {
+            break;
+        }
+    }
+
+    //stdin closed or "quit": don't leave a search thread running
+    engine.stop_search();
+}
+
+//everything "go" can carry, parsed but not yet turned into SearchLimits
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct GoParams {
+    depth: Option<u8>,
+    nodes: Option<u64>,
+    movetime: Option<u64>,
+    wtime: Option<u64>,
+    btime: Option<u64>,
+    winc: Option<u64>,
+    binc: Option<u64>,
+    movestogo: Option<u64>,
+    infinite: bool,
+    ponder: bool,
+}
+
+struct UciEngine {
+    position: Position,
+    //keys of the game positions before position, for repetition detection
+    history: Vec<u64>,
+    //None while a search thread owns the searcher
+    searcher: Option<Searcher<EvalEngine>>,
+    search_thread: Option<JoinHandle<Searcher<EvalEngine>>>,
+    stop: Arc<AtomicBool>,
+    //set for "go infinite" and "go ponder", cleared by "ponderhit"
+    infinite: Arc<AtomicBool>,
+    threads: usize,
+    multi_pv: usize,
+    //kept here so a fresh searcher gets them too
+    tablebase: Option<Arc<Tablebase>>,
+    eval: EvalEngine,
+    book: Option<Book>,
+    own_book: bool,
+    //in plies, also applied to a book loaded later
+    book_depth: u32,
+}
+
+impl UciEngine {
+    fn new() -> Self {
+        let searcher = new_searcher(1, None, EvalEngine::classical());
+        let stop = searcher.stop_flag();
+        let infinite = searcher.infinite_flag();
+        Self {
+            position: Position::starting_position(),
+            history: Vec::new(),
+            searcher: Some(searcher),
+            search_thread: None,
+            stop,
+            infinite,
+            threads: 1,
+            multi_pv: 1,
+            tablebase: None,
+            eval: EvalEngine::classical(),
+            book: None,
+            own_book: false,
+            book_depth: DEFAULT_BOOK_DEPTH,
+        }
+    }
+
+    //fresh searcher = fresh TT
+    fn reset_searcher(&mut self) {
+        let searcher = new_searcher(self.threads, self.tablebase.clone(), self.eval.clone());
+        self.stop = searcher.stop_flag();
+        self.infinite = searcher.infinite_flag();
+        self.searcher = Some(searcher);
+    }
+
+    //returns true if the engine should quit
+    fn handle_line(&mut self, input: &str) -> bool {
+        let parts: Vec<&str> = input.split_whitespace().collect();
+
+        match parts[0] {
+            "uci" => {
+                send(&format!("id name {ENGINE_NAME}"));
+                send(&format!("id author {ENGINE_AUTHOR}"));
+                send(&format!(
+                    "option name Threads type spin default 1 min 1 max {MAX_THREADS}"
+                ));
+                send(&format!(
+                    "option name MultiPV type spin default 1 min 1 max {MAX_MULTI_PV}"
+                ));
+                send("option name Ponder type check default false");
+                send("option name SyzygyPath type string default <empty>");
+                send("option name EvalFile type string default <empty>");
+                send("option name OwnBook type check default false");
+                send("option name BookFile type string default <empty>");
+                send(&format!(
+                    "option name BookDepth type spin default {DEFAULT_BOOK_DEPTH} min 0 max {MAX_BOOK_DEPTH}"
+                ));
+                send("uciok");
+            }
+            "isready" => send("readyok"),
+            "ucinewgame" => {
+                self.wait_for_search();
+                self.reset_searcher();
+                self.position = Position::starting_position();
+                self.history.clear();
+            }
+            "setoption" => {
+                self.wait_for_search();
+                self.set_option(&parts[1..]);
+            }
+            "position" => {
+                self.wait_for_search();
+                match parse_position(&parts[1..]) {
+                    Some((pos, history)) => {
+                        self.position = pos;
+                        self.history = history;
+                    }
+                    None => send(&format!("info string invalid position command: {input}")),
+                }
+            }
+            "go" => {
+                self.wait_for_search();
+                let params = parse_go(&parts[1..]);
+                //infinite and ponder searches must wait for "stop", so they skip the book
+                if !params.infinite
+                    && !params.ponder
+                    && let Some(mv) = self.book_move()
+                {
+                    send(&format!("bestmove {}", mv.to_uci()));
+                    return false;
+                }
+                let mut limits = limits_from_go(&params, self.position.player_to_move);
+                limits.multi_pv = self.multi_pv;
+                self.start_search(limits, params.infinite || params.ponder);
+            }
+            //the opponent played the expected move: the ponder search keeps going on its clock
+            "ponderhit" => self.infinite.store(false, Ordering::Relaxed),
+            "stop" => self.stop_search(),
+            "quit" => return true,
+            _ => send(&format!("info string unknown command: {input}")),
+        }
+        false
+    }
+
+    fn book_move(&mut self) -> Option<Move> {
+        if !self.own_book {
+            return None;
+        }
+        self.book.as_mut()?.pick(&self.position)
+    }
+
+    fn start_search(&mut self, limits: SearchLimits, infinite: bool) {
+        let Some(mut searcher) = self.searcher.take() else {
+            return;
+        };
+        self.stop.store(false, Ordering::Relaxed);
+        self.infinite.store(infinite, Ordering::Relaxed);
+
+        let mut pos = self.position.clone();
+        let history = self.history.clone();
+        self.search_thread = Some(thread::spawn(move || {
+            //"info" lines are streamed by the iteration callback
+            let result = searcher.search_smp_with_history(&mut pos, limits, &history);
+            send(&format_bestmove(&result, &mut pos));
+            searcher
+        }));
+    }
+
+    //"setoption name <id> [value <x>]"
+    fn set_option(&mut self, tokens: &[&str]) {
+        let Some((name, value)) = parse_setoption(tokens) else {
+            send("info string invalid setoption command");
+            return;
+        };
+        match name.to_ascii_lowercase().as_str() {
+            "threads" => match value.and_then(|v| v.parse::<usize>().ok()) {
+                Some(n) if (1..=MAX_THREADS).contains(&n) => {
+                    self.threads = n;
+                    if let Some(searcher) = self.searcher.as_mut() {
+                        searcher.set_threads(n);
+                    }
+                }
+                _ => send("info string invalid Threads value"),
+            },
+            //pondering is driven by "go ponder", nothing to configure
+            "ponder" => {}
+            "multipv" => match value.and_then(|v| v.parse::<usize>().ok()) {
+                Some(n) if (1..=MAX_MULTI_PV).contains(&n) => self.multi_pv = n,
+                _ => send("info string invalid MultiPV value"),
+            },
+            "syzygypath" => {
+                let paths = value.unwrap_or_default();
+                self.tablebase = if paths.is_empty() || paths == "<empty>" {
+                    None
+                } else {
+                    match Tablebase::open(&paths) {
+                        Ok(tablebase) => {
+                            send(&format!(
+                                "info string found {} tablebases with up to {} pieces",
+                                tablebase.len(),
+                                tablebase.max_pieces()
+                            ));
+                            Some(Arc::new(tablebase))
+                        }
+                        Err(err) => {
+                            send(&format!("info string invalid SyzygyPath: {err}"));
+                            None
+                        }
+                    }
+                };
+                if let Some(searcher) = self.searcher.as_mut() {
+                    searcher.set_tablebase(self.tablebase.clone());
+                }
+            }
+            //quantized network from export_nn, empty for the classical eval
+            "evalfile" => {
+                let path = value.unwrap_or_default();
+                self.eval = if path.is_empty() || path == "<empty>" {
+                    EvalEngine::classical()
+                } else {
+                    match EvalEngine::quantized(&path) {
+                        Ok(eval) => {
+                            send(&format!("info string using network {path}"));
+                            eval
+                        }
+                        Err(err) => {
+                            send(&format!("info string invalid EvalFile: {err}"));
+                            EvalEngine::classical()
+                        }
+                    }
+                };
+                //stored scores came from the other eval
+                self.reset_searcher();
+            }
+            "ownbook" => match value.as_deref() {
+                Some("true") => self.own_book = true,
+                Some("false") => self.own_book = false,
+                _ => send("info string invalid OwnBook value"),
+            },
+            "bookfile" => {
+                let path = value.unwrap_or_default();
+                self.book = if path.is_empty() || path == "<empty>" {
+                    None
+                } else {
+                    match Book::open(&path) {
+                        Ok(mut book) => {
+                            send(&format!("info string book with {} entries", book.len()));
+                            book.set_max_ply(self.book_depth);
+                            Some(book)
+                        }
+                        Err(err) => {
+                            send(&format!("info string invalid BookFile: {err}"));
+                            None
+                        }
+                    }
+                };
+            }
+            "bookdepth" => match value.and_then(|v| v.parse::<u32>().ok()) {
+                Some(n) if n <= MAX_BOOK_DEPTH => {
+                    self.book_depth = n;
+                    if let Some(book) = self.book.as_mut() {
+                        book.set_max_ply(n);
+                    }
+                }
+                _ => send("info string invalid BookDepth value"),
+            },
+            _ => send(&format!("info string unknown option: {name}")),
+        }
+    }
+
+    //signals the running search (if any) and waits for its bestmove
+    fn stop_search(&mut self) {
+        if self.search_thread.is_some() {
+            self.stop.store(true, Ordering::Relaxed);
+        }
+        self.wait_for_search();
+    }
+
+    //commands that change the position must not race with a running search,
+    //so they wait for it to finish on its own limits (an infinite or ponder search
+    //only finishes on "stop" or "ponderhit")
+    fn wait_for_search(&mut self) {
+        let Some(handle) = self.search_thread.take() else {
+            return;
+        };
+        match handle.join() {
+            Ok(searcher) => self.searcher = Some(searcher),
+            //search thread panicked, continue with a fresh one
+            Err(_) => self.reset_searcher(),
+        }
+    }
+}
+
+fn send(line: &str) {
+    let mut out = io::stdout().lock();
+    let _ = writeln!(out, "{line}");
+    let _ = out.flush();
+}
+
+//option names and values may contain spaces, so both are joined back together
+fn parse_setoption(tokens: &[&str]) -> Option<(String, Option<String>)> {
+    if tokens.first() != Some(&"name") {
+        return None;
+    }
+    let rest = &tokens[1..];
+    let value_at = rest.iter().position(|&t| t == "value");
+    let (name, value) = match value_at {
+        Some(i) => (&rest[..i], Some(rest[i + 1..].join(" "))),
+        None => (rest, None),
+    };
+    if name.is_empty() {
+        return None;
+    }
+    Some((name.join(" "), value))
+}
+
+fn new_searcher(threads: usize, tablebase: Option<Arc<Tablebase>>, eval: EvalEngine) -> Searcher<EvalEngine> {
+    let mut searcher = Searcher::new(eval);
+    searcher.set_threads(threads);
+    searcher.set_tablebase(tablebase);
+    searcher.set_on_iteration(Box::new(|info| send(&format_info(info))));
+    searcher
+}
+
+//"position [startpos | fen <6 fields>] [moves m1 m2 ...]"
+//the position and the keys of the positions the moves went through before it
+fn parse_position(tokens: &[&str]) -> Option<(Position, Vec<u64>)> {
+    let (mut pos, rest) = match tokens.first() {
+        Some(&"startpos") => (Position::starting_position(), &tokens[1..]),
+        Some(&"fen") => {
+            let fen_end = tokens
+                .iter()
+                .position(|&t| t == "moves")
+                .unwrap_or(tokens.len());
+            let fen = tokens[1..fen_end].join(" ");
+            (Position::from_fen(&fen).ok()?, &tokens[fen_end..])
+        }
+        _ => return None,
+    };
+
+    let mut history = Vec::new();
+    if rest.first() == Some(&"moves") {
+        let mut legal = Vec::new();
+        for uci in &rest[1..] {
+            generate_legal_moves_in_place(&mut pos, &mut legal);
+            let mv = find_legal_move_from_uci(uci, &legal)?;
+            history.push(pos.zobrist);
+            pos.make_move(mv);
+        }
+    }
+    Some((pos, history))
+}
+
+//Move::from_uci doesn't know about castling/ep/double push flags, so take the legal one
+fn find_legal_move_from_uci(input: &str, legal: &[Move]) -> Option<Move> {
+    let key = Move::from_uci(input)?;
+    legal.iter().copied().find(|m| {
+        m.from == key.from && m.to == key.to && m.promotion_piece() == key.promotion_piece()
+    })
+}
+
+fn parse_go(tokens: &[&str]) -> GoParams {
+    let mut params = GoParams::default();
+    let mut i = 0;
+
+    while i < tokens.len() {
+        let value = tokens.get(i + 1).and_then(|v| v.parse::<u64>().ok());
+        let consumed = match tokens[i] {
+            "infinite" => {
+                params.infinite = true;
+                1
+            }
+            "ponder" => {
+                params.ponder = true;
+                1
+            }
+            "depth" => {
+                params.depth = value.map(|d| d.clamp(1, u8::MAX as u64) as u8);
+                2
+            }
+            "nodes" => {
+                params.nodes = value;
+                2
+            }
+            "movetime" => {
+                params.movetime = value;
+                2
+            }
+            "wtime" => {
+                params.wtime = value;
+                2
+            }
+            "btime" => {
+                params.btime = value;
+                2
+            }
+            "winc" => {
+                params.winc = value;
+                2
+            }
+            "binc" => {
+                params.binc = value;
+                2
+            }
+            "movestogo" => {
+                params.movestogo = value;
+                2
+            }
+            //unknown token
+            _ => 1,
+        };
+        i += consumed;
+    }
+    params
+}
+
+fn limits_from_go(params: &GoParams, side_to_move: Color) -> SearchLimits {
+    let mut limits = SearchLimits {
+        max_depth: params.depth.unwrap_or(u8::MAX),
+        max_nodes: params.nodes,
+        max_time_ms: None,
+        multi_pv: 1,
+        clock: None,
+    };
+
+    if params.infinite {
+        return limits;
+    }
+
+    if let Some(movetime) = params.movetime {
+        limits.max_time_ms = Some(movetime.saturating_sub(MOVE_OVERHEAD_MS).max(1));
+        return limits;
+    }
+
+    let (time_left, increment) = match side_to_move {
+        Color::White => (params.wtime, params.winc.unwrap_or(0)),
+        Color::Black => (params.btime, params.binc.unwrap_or(0)),
+    };
+
+    //budgets are up to the searcher's time manager
+    limits.clock = time_left.map(|time_left_ms| Clock {
+        time_left_ms,
+        increment_ms: increment,
+        moves_to_go: params.movestogo,
+    });
+
+    limits
+}
+
+fn format_score(score_cp: i32) -> String {
+    if score_cp.abs() >= MATE - 1000 {
+        //plies to mate -> moves to mate, negative if we get mated
+        let plies = MATE - score_cp.abs();
+        let moves = (plies + 1) / 2;
+        if score_cp > 0 {
+            format!("mate {moves}")
+        } else {
+            format!("mate -{moves}")
+        }
+    } else {
+        format!("cp {score_cp}")
+    }
+}
+
+fn format_info(info: &IterationInfo) -> String {
+    let nps = info.nodes * 1000 / info.elapsed_ms.max(1);
+    let mut line = format!(
+        "info depth {} multipv {} score {} nodes {} nps {} time {}",
+        info.depth,
+        info.multipv,
+        format_score(info.score_cp),
+        info.nodes,
+        nps,
+        info.elapsed_ms
+    );
+    if !info.pv.is_empty() {
+        let pv: Vec<String> = info.pv.iter().map(|mv| mv.to_uci()).collect();
+        line.push_str(&format!(" pv {}", pv.join(" ")));
+    }
+    line
+}
+
+//the pv reply is the move the GUI ponders on ("go ponder" after "bestmove X ponder Y")
+fn format_bestmove(result: &SearchResult, pos: &mut Position) -> String {
+    if result.best_move.is_null() {
+        return format!("bestmove {}", fallback_move(pos).to_uci());
+    }
+    match result.pv.as_slice() {
+        [best, reply, ..] if *best == result.best_move => {
+            format!("bestmove {} ponder {}", best.to_uci(), reply.to_uci())
+        }
+        _ => format!("bestmove {}", result.best_move.to_uci()),
+    }
+}
+
+//used if the search was stopped before the first iteration finished
+fn fallback_move(pos: &mut Position) -> Move {
+    let mut legal = Vec::new();
+    generate_legal_moves_in_place(pos, &mut legal);
+    legal.first().copied().unwrap_or(Move::NULL)
+}
+
+#[cfg(test)]
+mod uci_parse_tests {
+    use super::*;
+
+    #[test]
+    fn book_move_needs_own_book_and_a_book_entry() {
+        use rust_chess_engine::book::BookEntry;
+        use rust_chess_engine::position::polyglot_key;
+
+        let mut engine = UciEngine::new();
+        let key = polyglot_key(&engine.position);
+        //e2e4
+        engine.book = Some(Book::from_entries(vec![BookEntry { key, mv: 12 << 6 | 28, weight: 1, learn: 0 }]));
+        assert_eq!(engine.book_move(), None);
+
+        engine.set_option(&["name", "OwnBook", "value", "true"]);
+        assert_eq!(engine.book_move().map(|mv| mv.to_uci()), Some("e2e4".to_string()));
+
+        engine.set_option(&["name", "BookDepth", "value", "0"]);
+        assert_eq!(engine.book_move(), None);
+    }
+
+    #[test]
+    fn eval_file_switches_to_the_quantized_network() {
+        use rust_chess_engine::evaluation::accumulator::{Dense, FEATURES, Network};
+        use rust_chess_engine::evaluation::quantized::QuantizedNet;
+
+        use rust_chess_engine::nn_model::format::save_network;
+
+        let first = Dense::new(FEATURES, 2, vec![0.01; 2 * FEATURES], vec![0.0; 2]).unwrap();
+        let out = Dense::new(2, 1, vec![0.5, -0.5], vec![0.0]).unwrap();
+        let float = Network::new(first, vec![out]).unwrap();
+        let dir = std::env::temp_dir();
+        let quantized = dir.join(format!("engine-evalfile-{}.nnq", std::process::id()));
+        let binary = dir.join(format!("engine-evalfile-{}.nn", std::process::id()));
+        QuantizedNet::quantize(&float).unwrap().save(&quantized).unwrap();
+        save_network(&binary, &float).unwrap();
+
+        let mut engine = UciEngine::new();
+        for path in [&quantized, &binary] {
+            engine.set_option(&["name", "EvalFile", "value", path.to_str().unwrap()]);
+            assert!(matches!(engine.eval, EvalEngine::Quantized(_)));
+            std::fs::remove_file(path).unwrap();
+        }
+        engine.set_option(&["name", "EvalFile", "value", "missing.nnq"]);
+        assert!(matches!(engine.eval, EvalEngine::Classical(_)));
+    }
+
+    #[test]
+    fn position_startpos_with_moves_applies_them() {
+        let (pos, history) = parse_position(&["startpos", "moves", "e2e4", "e7e5", "g1f3"]).unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0], Position::starting_position().zobrist);
+        assert_eq!(
+            pos.to_fen(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+    }
+
+    #[test]
+    fn position_fen_with_castling_move_uses_legal_move_flags() {
+        let tokens: Vec<&str> = "fen r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1 moves e1g1"
+            .split_whitespace()
+            .collect();
+        let (pos, _) = parse_position(&tokens).unwrap();
+        assert_eq!(pos.to_fen(), "r3k2r/8/8/8/8/8/8/R4RK1 b kq - 1 1");
+    }
+
+    #[test]
+    fn position_with_illegal_move_is_rejected() {
+        assert!(parse_position(&["startpos", "moves", "e2e5"]).is_none());
+        assert!(parse_position(&["nonsense"]).is_none());
+    }
+
+    #[test]
+    fn setoption_splits_name_and_value() {
+        assert_eq!(
+            parse_setoption(&["name", "Threads", "value", "8"]),
+            Some(("Threads".to_string(), Some("8".to_string())))
+        );
+        assert_eq!(
+            parse_setoption(&["name", "Clear", "Hash"]),
+            Some(("Clear Hash".to_string(), None))
+        );
+        assert_eq!(parse_setoption(&["value", "3"]), None);
+    }
+
+    #[test]
+    fn go_parses_all_fields_and_ignores_unknown_tokens() {
+        let tokens: Vec<&str> =
+            "wtime 60000 btime 50000 winc 1000 binc 500 movestogo 20 depth 12 nodes 1000 foo"
+                .split_whitespace()
+                .collect();
+        let params = parse_go(&tokens);
+        assert_eq!(params.wtime, Some(60000));
+        assert_eq!(params.btime, Some(50000));
+        assert_eq!(params.winc, Some(1000));
+        assert_eq!(params.binc, Some(500));
+        assert_eq!(params.movestogo, Some(20));
+        assert_eq!(params.depth, Some(12));
+        assert_eq!(params.nodes, Some(1000));
+        assert!(!params.infinite);
+        assert!(!params.ponder);
+    }
+
+    #[test]
+    fn go_ponder_keeps_the_clock_limits() {
+        let params = parse_go(&["ponder", "wtime", "60000", "btime", "60000", "movestogo", "1"]);
+        assert!(params.ponder);
+
+        //the searcher suspends them until ponderhit
+        let limits = limits_from_go(&params, Color::White);
+        assert_eq!(limits.clock.map(|c| c.time_left_ms), Some(60000));
+    }
+
+    #[test]
+    fn clock_uses_side_to_move() {
+        let params = parse_go(&["wtime", "60000", "btime", "1000", "binc", "100", "movestogo", "5"]);
+
+        let white = limits_from_go(&params, Color::White);
+        assert_eq!(
+            white.clock,
+            Some(Clock { time_left_ms: 60000, increment_ms: 0, moves_to_go: Some(5) })
+        );
+        assert_eq!(white.max_time_ms, None);
+
+        let black = limits_from_go(&params, Color::Black);
+        assert_eq!(
+            black.clock,
+            Some(Clock { time_left_ms: 1000, increment_ms: 100, moves_to_go: Some(5) })
+        );
+    }
+
+    #[test]
+    fn infinite_and_movetime_limits() {
+        let infinite = limits_from_go(&parse_go(&["infinite"]), Color::White);
+        assert_eq!(infinite.max_time_ms, None);
+        assert_eq!(infinite.max_depth, u8::MAX);
+
+        let fixed = limits_from_go(&parse_go(&["movetime", "500"]), Color::White);
+        assert_eq!(fixed.max_time_ms, Some(500 - MOVE_OVERHEAD_MS));
+    }
+
+    #[test]
+    fn bestmove_names_the_pv_reply_as_ponder_move() {
+        let (mut pos, _) = parse_position(&["startpos"]).unwrap();
+        let (after, _) = parse_position(&["startpos", "moves", "e2e4"]).unwrap();
+        let uci = |pos: &mut Position, text: &str| {
+            let mut legal = Vec::new();
+            generate_legal_moves_in_place(pos, &mut legal);
+            legal.into_iter().find(|mv| mv.to_uci() == text).unwrap()
+        };
+        let e2e4 = uci(&mut pos, "e2e4");
+        let e7e5 = uci(&mut after.clone(), "e7e5");
+        let mut result = SearchResult {
+            best_move: e2e4,
+            score_cp: 20,
+            depth: 6,
+            nodes: 1000,
+            pv: vec![e2e4, e7e5],
+            lines: Vec::new(),
+        };
+        assert_eq!(format_bestmove(&result, &mut pos), "bestmove e2e4 ponder e7e5");
+
+        result.pv.truncate(1);
+        assert_eq!(format_bestmove(&result, &mut pos), "bestmove e2e4");
+
+        //stopped before the first iteration: a legal move, nothing to ponder on
+        result.best_move = Move::NULL;
+        result.pv.clear();
+        let line = format_bestmove(&result, &mut pos);
+        assert!(line.starts_with("bestmove ") && !line.contains("ponder"), "{line}");
+    }
+
+    #[test]
+    fn mate_scores_are_reported_in_moves() {
+        assert_eq!(format_score(35), "cp 35");
+        assert_eq!(format_score(MATE - 1), "mate 1");
+        assert_eq!(format_score(MATE - 3), "mate 2");
+        assert_eq!(format_score(-MATE + 2), "mate -1");
+    }
+
+    #[test]
+    fn info_line_contains_whole_pv() {
+        let pv = vec![
+            Move::from_uci("e2e4").unwrap(),
+            Move::from_uci("e7e5").unwrap(),
+            Move::from_uci("g1f3").unwrap(),
+        ];
+        let info = IterationInfo {
+            depth: 3,
+            multipv: 2,
+            score_cp: 20,
+            nodes: 500,
+            pv: &pv,
+            elapsed_ms: 100,
+        };
+        assert_eq!(
+            format_info(&info),
+            "info depth 3 multipv 2 score cp 20 nodes 500 nps 5000 time 100 pv e2e4 e7e5 g1f3"
+        );
+    }
 }
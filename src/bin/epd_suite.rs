@@ -0,0 +1,223 @@
+//runs a tactical test suite (wac, sts, ecm, ...): every epd position is searched with the
+//given limits and the best move is checked against its bm/am (or dm) operations
+//epd_suite [-time MS] [-depth N] [-nodes N] [-threads N] <suite.epd>
+
+use std::fs;
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+
+use rust_chess_engine::evaluation::ClassicalEval;
+use rust_chess_engine::movegen::Move;
+use rust_chess_engine::position::{Epd, Position};
+use rust_chess_engine::search::{MATE, SearchLimits, Searcher};
+
+const DEFAULT_TIME_MS: u64 = 1000;
+const MAX_DEPTH: u8 = 64;
+
+struct Options {
+    time_ms: Option<u64>,
+    depth: Option<u8>,
+    nodes: Option<u64>,
+    threads: usize,
+    path: String,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Outcome {
+    //time to solution in ms: from the iteration the solution was found and kept
+    Solved(u64),
+    Failed,
+    //no bm, am or dm to check against
+    Unchecked,
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some(opts) = parse_args(&args) else {
+        eprintln!("usage: epd_suite [-time MS] [-depth N] [-nodes N] [-threads N] <suite.epd>");
+        return ExitCode::FAILURE;
+    };
+    let text = match fs::read_to_string(&opts.path) {
+        Ok(text) => text,
+        Err(err) => {
+            eprintln!("cannot read {}: {err}", opts.path);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let (mut solved, mut checked, mut total_ms) = (0, 0, 0);
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let epd = match Position::from_epd(line) {
+            Ok(epd) => epd,
+            Err(err) => {
+                println!("line {}: invalid epd: {err:?}", i + 1);
+                continue;
+            }
+        };
+        let name = epd.id.clone().unwrap_or_else(|| format!("line {}", i + 1));
+
+        let (outcome, best, score, depth) = run(&epd, &opts);
+        let expected = expected_text(&epd);
+        let status = match outcome {
+            Outcome::Solved(ms) => {
+                solved += 1;
+                checked += 1;
+                total_ms += ms;
+                format!("pass {ms}ms")
+            }
+            Outcome::Failed => {
+                checked += 1;
+                "FAIL".to_string()
+            }
+            Outcome::Unchecked => "unchecked".to_string(),
+        };
+        println!(
+            "{name}: {status} | played {} ({expected}) | score {score} | depth {depth}",
+            best.to_san(&epd.position)
+        );
+    }
+
+    let percent = if checked > 0 { 100.0 * solved as f64 / checked as f64 } else { 0.0 };
+    let mean_ms = total_ms.checked_div(solved).unwrap_or(0);
+    println!("solved {solved}/{checked} ({percent:.1}%) | time to solution: total {total_ms}ms, mean {mean_ms}ms");
+    ExitCode::SUCCESS
+}
+
+fn run(epd: &Epd, opts: &Options) -> (Outcome, Move, i32, u8) {
+    let mut searcher = Searcher::new(ClassicalEval::new());
+    searcher.set_threads(opts.threads);
+    //best move, score and time of every finished iteration
+    let iterations: Arc<Mutex<Vec<(Move, i32, u64)>>> = Arc::default();
+    let record = Arc::clone(&iterations);
+    searcher.set_on_iteration(Box::new(move |info| {
+        if info.multipv == 1
+            && let Some(&mv) = info.pv.first()
+        {
+            record.lock().unwrap().push((mv, info.score_cp, info.elapsed_ms));
+        }
+    }));
+
+    //acd of the record is the depth unless the command line sets one
+    let depth = opts.depth.or(epd.depth.map(|d| d.min(MAX_DEPTH as u32) as u8));
+    let limits = SearchLimits {
+        max_depth: depth.unwrap_or(MAX_DEPTH),
+        max_nodes: opts.nodes,
+        max_time_ms: opts.time_ms.or((depth.is_none() && opts.nodes.is_none()).then_some(DEFAULT_TIME_MS)),
+        multi_pv: 1,
+        clock: None,
+    };
+    let mut pos = epd.position.clone();
+    let result = searcher.search_smp(&mut pos, limits);
+
+    let iterations = iterations.lock().unwrap();
+    let outcome = judge(epd, result.best_move, result.score_cp, &iterations);
+    (outcome, result.best_move, result.score_cp, result.depth)
+}
+
+fn judge(epd: &Epd, best: Move, score: i32, iterations: &[(Move, i32, u64)]) -> Outcome {
+    let by_move = !epd.best_moves.is_empty() || !epd.avoid_moves.is_empty();
+    if !by_move && epd.direct_mate.is_none() {
+        return Outcome::Unchecked;
+    }
+    let solves = |mv: Move, score: i32| {
+        if by_move {
+            (epd.best_moves.is_empty() || epd.best_moves.contains(&mv)) && !epd.avoid_moves.contains(&mv)
+        } else {
+            mate_in(score).is_some_and(|moves| Some(moves) <= epd.direct_mate)
+        }
+    };
+    if !solves(best, score) {
+        return Outcome::Failed;
+    }
+    //first iteration of the final run of solving ones
+    let first = iterations.iter().rev().take_while(|&&(mv, score, _)| solves(mv, score)).last();
+    Outcome::Solved(first.map_or(0, |&(_, _, ms)| ms))
+}
+
+//moves to mate for a winning mate score
+fn mate_in(score: i32) -> Option<u32> {
+    (score >= MATE - 1000).then(|| ((MATE - score + 1) / 2) as u32)
+}
+
+fn expected_text(epd: &Epd) -> String {
+    let san = |moves: &[Move]| moves.iter().map(|m| m.to_san(&epd.position)).collect::<Vec<_>>().join(" ");
+    let mut parts = Vec::new();
+    if !epd.best_moves.is_empty() {
+        parts.push(format!("bm {}", san(&epd.best_moves)));
+    }
+    if !epd.avoid_moves.is_empty() {
+        parts.push(format!("am {}", san(&epd.avoid_moves)));
+    }
+    if let Some(dm) = epd.direct_mate {
+        parts.push(format!("dm {dm}"));
+    }
+    parts.join(", ")
+}
+
+fn parse_args(args: &[String]) -> Option<Options> {
+    let mut opts = Options { time_ms: None, depth: None, nodes: None, threads: 1, path: String::new() };
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "-time" => opts.time_ms = Some(it.next()?.parse().ok()?),
+            "-depth" => opts.depth = Some(it.next()?.parse().ok()?),
+            "-nodes" => opts.nodes = Some(it.next()?.parse().ok()?),
+            "-threads" => opts.threads = it.next()?.parse().ok().filter(|&n| n > 0)?,
+            path if opts.path.is_empty() && !path.starts_with('-') => opts.path = path.to_string(),
+            _ => return None,
+        }
+    }
+    (!opts.path.is_empty()).then_some(opts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn epd(line: &str) -> Epd {
+        Position::from_epd(line).unwrap()
+    }
+
+    fn mv(epd: &Epd, san: &str) -> Move {
+        Move::from_san(&epd.position, san).unwrap()
+    }
+
+    #[test]
+    fn judges_best_and_avoid_moves() {
+        let e = epd("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKBNR w KQkq - bm Bb5; am Qe2;");
+        let (bb5, qe2, bc4) = (mv(&e, "Bb5"), mv(&e, "Qe2"), mv(&e, "Bc4"));
+        //found at 20ms, lost and found again at 50ms
+        let iterations = [(bb5, 0, 20), (bc4, 0, 30), (bb5, 0, 50), (bb5, 0, 90)];
+        assert_eq!(judge(&e, bb5, 30, &iterations), Outcome::Solved(50));
+        assert_eq!(judge(&e, bc4, 30, &iterations), Outcome::Failed);
+        assert_eq!(judge(&e, qe2, 30, &[]), Outcome::Failed);
+
+        let avoid = epd("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKBNR w KQkq - am Qe2;");
+        assert_eq!(judge(&avoid, bc4, 30, &[(bc4, 0, 10)]), Outcome::Solved(10));
+    }
+
+    #[test]
+    fn judges_direct_mates() {
+        let e = epd("6k1/5ppp/8/8/8/8/8/R5K1 w - - dm 1;");
+        let ra8 = mv(&e, "Ra8");
+        //mate seen from the second iteration on
+        let iterations = [(ra8, 50, 5), (ra8, MATE - 1, 8)];
+        assert_eq!(judge(&e, ra8, MATE - 1, &iterations), Outcome::Solved(8));
+        assert_eq!(judge(&e, ra8, MATE - 3, &iterations), Outcome::Failed);
+        assert_eq!(judge(&epd("6k1/5ppp/8/8/8/8/8/R5K1 w - -"), ra8, 0, &[]), Outcome::Unchecked);
+        assert_eq!(mate_in(MATE - 5), Some(3));
+        assert_eq!(mate_in(100), None);
+    }
+
+    #[test]
+    fn parses_options() {
+        let args: Vec<String> = ["-time", "500", "-threads", "2", "wac.epd"].iter().map(|s| s.to_string()).collect();
+        let opts = parse_args(&args).unwrap();
+        assert_eq!((opts.time_ms, opts.depth, opts.threads, opts.path.as_str()), (Some(500), None, 2, "wac.epd"));
+        assert!(parse_args(&["-depth".to_string(), "9".to_string()]).is_none());
+    }
+}
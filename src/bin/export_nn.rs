@@ -0,0 +1,53 @@
+//exports a trained model (json record or binary network file) as a quantized network
+//for the default build:
+//export_nn <model.json or model.nn> <out.nnq>
+
+use std::process::ExitCode;
+use std::sync::Arc;
+
+use rust_chess_engine::evaluation::Evaluator;
+use rust_chess_engine::evaluation::neural::NeuralEval;
+use rust_chess_engine::evaluation::quantized::{QuantizedEval, QuantizedNet};
+use rust_chess_engine::position::Position;
+
+//printed side by side so a bad export is seen right away
+const CHECK_FENS: [&str; 3] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/pn1b1p1p/6p1/1Pp1P3/6n1/P4N2/2P2PPP/R3KB1R w KQkq - 0 16",
+    "rnb1k2r/pp3pbp/1N1ppnp1/2pP4/4P3/3Q1N2/PPP2PPP/R1B1KB1R b KQkq - 0 8",
+];
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let [model, out] = args.as_slice() else {
+        eprintln!("usage: export_nn <model.json or model.nn> <out.nnq>");
+        return ExitCode::FAILURE;
+    };
+
+    let mut float = match NeuralEval::load(model) {
+        Ok(eval) => eval,
+        Err(err) => {
+            eprintln!("cannot load {model}: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let net = match QuantizedNet::quantize(float.network()) {
+        Ok(net) => Arc::new(net),
+        Err(err) => {
+            eprintln!("cannot quantize {model}: {err:?}");
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Err(err) = net.save(out) {
+        eprintln!("cannot write {out}: {err}");
+        return ExitCode::FAILURE;
+    }
+
+    let mut quantized = QuantizedEval::new(net);
+    for fen in CHECK_FENS {
+        let pos = Position::from_fen(fen).expect("valid check fen");
+        println!("{fen}: float {} quantized {}", float.evaluate(&pos), quantized.evaluate(&pos));
+    }
+    println!("written to {out}");
+    ExitCode::SUCCESS
+}
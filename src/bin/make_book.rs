@@ -0,0 +1,86 @@
+//builds a polyglot opening book from pgn files:
+//make_book [-depth N] <out.bin> <games.pgn>...
+
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::process::ExitCode;
+
+use rust_chess_engine::book::{BookBuilder, write_book};
+use rust_chess_engine::position::pgn::read_pgn;
+
+const DEFAULT_DEPTH: u32 = 20;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some((depth, out, inputs)) = parse_args(&args) else {
+        eprintln!("usage: make_book [-depth N] <out.bin> <games.pgn>...");
+        return ExitCode::FAILURE;
+    };
+
+    let mut builder = BookBuilder::new();
+    builder.set_max_ply(depth);
+    let mut games = 0;
+    for path in inputs {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) => {
+                eprintln!("cannot read {path}: {err}");
+                return ExitCode::FAILURE;
+            }
+        };
+        match read_pgn(&text) {
+            Ok(pgn_games) => {
+                for pgn in &pgn_games {
+                    builder.add_game(&pgn.game, pgn.result());
+                }
+                games += pgn_games.len();
+            }
+            Err(err) => {
+                eprintln!("{path}: {err:?}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let entries = builder.finish();
+    let written = File::create(out).and_then(|file| write_book(&mut BufWriter::new(file), &entries));
+    if let Err(err) = written {
+        eprintln!("cannot write {out}: {err}");
+        return ExitCode::FAILURE;
+    }
+    println!("{games} games, {} entries written to {out}", entries.len());
+    ExitCode::SUCCESS
+}
+
+fn parse_args(args: &[String]) -> Option<(u32, &str, &[String])> {
+    let (depth, rest) = match args {
+        [flag, n, rest @ ..] if flag == "-depth" => (n.parse().ok()?, rest),
+        rest => (DEFAULT_DEPTH, rest),
+    };
+    match rest {
+        [out, inputs @ ..] if !inputs.is_empty() => Some((depth, out.as_str(), inputs)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_depth_output_and_inputs() {
+        let a = args(&["-depth", "12", "book.bin", "a.pgn", "b.pgn"]);
+        let (depth, out, inputs) = parse_args(&a).unwrap();
+        assert_eq!((depth, out, inputs.len()), (12, "book.bin", 2));
+
+        let a = args(&["book.bin", "a.pgn"]);
+        assert_eq!(parse_args(&a).unwrap().0, DEFAULT_DEPTH);
+
+        assert!(parse_args(&args(&["book.bin"])).is_none());
+        assert!(parse_args(&args(&["-depth", "x", "book.bin", "a.pgn"])).is_none());
+    }
+}
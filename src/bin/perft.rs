@@ -0,0 +1,216 @@
+//perft suite: runs every position of an epd file ("<fen> ;D1 n ;D2 n ...") up to a depth
+//limit and reports mismatches and nodes per second, meant to be run after movegen changes
+//perft [-depth N] [-threads N] [-hash MB] [-divide] [suite.epd]
+
+use std::fs;
+use std::process::ExitCode;
+use std::time::Instant;
+
+use rust_chess_engine::movegen::{perft_divide, perft_parallel};
+use rust_chess_engine::position::Position;
+
+//standard positions and the usual edge cases (illegal en passant, castling and
+//promotions giving check, stalemates), counts from the chessprogramming wiki
+const DEFAULT_SUITE: &str = "\
+rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 ;D1 20 ;D2 400 ;D3 8902 ;D4 197281 ;D5 4865609 ;D6 119060324
+r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1 ;D1 48 ;D2 2039 ;D3 97862 ;D4 4085603 ;D5 193690690
+8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1 ;D1 14 ;D2 191 ;D3 2812 ;D4 43238 ;D5 674624 ;D6 11030083
+r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1 ;D1 6 ;D2 264 ;D3 9467 ;D4 422333 ;D5 15833292
+r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1 ;D1 6 ;D2 264 ;D3 9467 ;D4 422333 ;D5 15833292
+rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8 ;D1 44 ;D2 1486 ;D3 62379 ;D4 2103487 ;D5 89941194
+r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10 ;D1 46 ;D2 2079 ;D3 89890 ;D4 3894594 ;D5 164075551
+3k4/3p4/8/K1P4r/8/8/8/8 b - - 0 1 ;D6 1134888
+8/8/4k3/8/2p5/8/B2P2K1/8 w - - 0 1 ;D6 1015133
+8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1 ;D6 1440467
+5k2/8/8/8/8/8/8/4K2R w K - 0 1 ;D6 661072
+3k4/8/8/8/8/8/8/R3K3 w Q - 0 1 ;D6 803711
+r3k2r/1b4bq/8/8/8/8/7B/R3K2R w KQkq - 0 1 ;D4 1274206
+r3k2r/8/3Q4/8/8/5q2/8/R3K2R b KQkq - 0 1 ;D4 1720476
+2K2r2/4P3/8/8/8/8/8/3k4 w - - 0 1 ;D6 3821001
+8/8/1P2K3/8/2n5/1q6/8/5k2 b - - 0 1 ;D5 1004658
+4k3/1P6/8/8/8/8/K7/8 w - - 0 1 ;D6 217342
+8/P1k5/K7/8/8/8/8/8 w - - 0 1 ;D6 92683
+K1k5/8/P7/8/8/8/8/8 w - - 0 1 ;D6 2217
+8/k1P5/8/1K6/8/8/8/8 w - - 0 1 ;D1 10 ;D2 25 ;D3 268 ;D4 926 ;D5 10857 ;D6 43261 ;D7 567584
+8/8/2k5/5q2/5n2/8/5K2/8 b - - 0 1 ;D4 23527
+";
+
+const DEFAULT_MAX_DEPTH: u32 = 6;
+const DEFAULT_HASH_MB: usize = 64;
+
+#[derive(Debug, PartialEq, Eq)]
+struct SuiteEntry {
+    fen: String,
+    //(depth, expected nodes)
+    counts: Vec<(u32, u64)>,
+}
+
+struct Options {
+    max_depth: u32,
+    threads: usize,
+    hash_mb: usize,
+    divide: bool,
+    path: Option<String>,
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some(opts) = parse_args(&args) else {
+        eprintln!("usage: perft [-depth N] [-threads N] [-hash MB] [-divide] [suite.epd]");
+        return ExitCode::FAILURE;
+    };
+
+    let text = match &opts.path {
+        Some(path) => match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) => {
+                eprintln!("cannot read {path}: {err}");
+                return ExitCode::FAILURE;
+            }
+        },
+        None => DEFAULT_SUITE.to_string(),
+    };
+    let suite = match parse_suite(&text) {
+        Ok(suite) => suite,
+        Err(line) => {
+            eprintln!("invalid epd line {line}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut mismatches = 0;
+    let mut skipped = 0;
+    let mut total_nodes = 0;
+    let t0 = Instant::now();
+    for entry in &suite {
+        let Ok(pos) = Position::from_fen(&entry.fen) else {
+            println!("invalid fen: {}", entry.fen);
+            mismatches += 1;
+            continue;
+        };
+        println!("{}", entry.fen);
+        if entry.counts.iter().all(|&(d, _)| d > opts.max_depth) {
+            println!("  skipped, no count up to depth {}", opts.max_depth);
+            skipped += 1;
+            continue;
+        }
+        for &(depth, expected) in entry.counts.iter().filter(|&&(d, _)| d <= opts.max_depth) {
+            let t = Instant::now();
+            let nodes = perft_parallel(&pos, depth, opts.threads, opts.hash_mb);
+            let elapsed = t.elapsed().as_secs_f64();
+            total_nodes += nodes;
+            let status = if nodes == expected { "ok" } else { "MISMATCH" };
+            println!(
+                "  depth {depth}: {nodes} (expected {expected}) {status} | {:.3}s | {} nps",
+                elapsed,
+                nps(nodes, elapsed)
+            );
+            if nodes != expected {
+                mismatches += 1;
+                if opts.divide {
+                    for (mv, n) in perft_divide(&pos, depth) {
+                        println!("    {}: {n}", mv.to_uci());
+                    }
+                }
+            }
+        }
+    }
+
+    let elapsed = t0.elapsed().as_secs_f64();
+    println!(
+        "{} positions ({skipped} skipped), {mismatches} mismatches, {total_nodes} nodes in {elapsed:.3}s ({} nps)",
+        suite.len(),
+        nps(total_nodes, elapsed)
+    );
+    if mismatches == 0 { ExitCode::SUCCESS } else { ExitCode::FAILURE }
+}
+
+fn nps(nodes: u64, secs: f64) -> u64 {
+    if secs > 0.0 { (nodes as f64 / secs) as u64 } else { 0 }
+}
+
+fn parse_args(args: &[String]) -> Option<Options> {
+    let mut opts = Options {
+        max_depth: DEFAULT_MAX_DEPTH,
+        threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+        hash_mb: DEFAULT_HASH_MB,
+        divide: false,
+        path: None,
+    };
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "-depth" => opts.max_depth = it.next()?.parse().ok()?,
+            "-threads" => opts.threads = it.next()?.parse().ok().filter(|&n| n > 0)?,
+            "-hash" => opts.hash_mb = it.next()?.parse().ok()?,
+            "-divide" => opts.divide = true,
+            path if opts.path.is_none() && !path.starts_with('-') => opts.path = Some(path.to_string()),
+            _ => return None,
+        }
+    }
+    Some(opts)
+}
+
+//Err(line number) of the first malformed line; empty lines and # comments are skipped
+fn parse_suite(text: &str) -> Result<Vec<SuiteEntry>, usize> {
+    let mut suite = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split(';');
+        let fen = fields.next().unwrap_or_default().trim().to_string();
+        let mut counts = Vec::new();
+        for field in fields {
+            let (depth, nodes) = field.trim().split_once(' ').ok_or(i + 1)?;
+            let depth = depth.strip_prefix('D').and_then(|d| d.parse().ok()).ok_or(i + 1)?;
+            let nodes = nodes.trim().parse().map_err(|_| i + 1)?;
+            counts.push((depth, nodes));
+        }
+        if fen.is_empty() || counts.is_empty() {
+            return Err(i + 1);
+        }
+        suite.push(SuiteEntry { fen, counts });
+    }
+    Ok(suite)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_chess_engine::movegen::perft;
+
+    #[test]
+    fn parses_epd_lines() {
+        let suite = parse_suite("# comment\n\n8/8/8/8/8/8/8/K1k5 w - - 0 1 ;D1 3 ;D2 9\n").unwrap();
+        assert_eq!(
+            suite,
+            vec![SuiteEntry { fen: "8/8/8/8/8/8/8/K1k5 w - - 0 1".to_string(), counts: vec![(1, 3), (2, 9)] }]
+        );
+        assert_eq!(parse_suite("a\nfen ;D1 x"), Err(1));
+        assert_eq!(parse_suite("fen ;D1 20\nfen ;X1 20"), Err(2));
+    }
+
+    #[test]
+    fn default_suite_is_valid_and_shallow_counts_match() {
+        let suite = parse_suite(DEFAULT_SUITE).unwrap();
+        assert_eq!(suite.len(), 21);
+        for entry in &suite {
+            let pos = Position::from_fen(&entry.fen).unwrap();
+            for &(depth, expected) in entry.counts.iter().filter(|&&(d, _)| d <= 2) {
+                assert_eq!(perft(&pos, depth), expected, "{} depth {depth}", entry.fen);
+            }
+        }
+    }
+
+    #[test]
+    fn parses_options() {
+        let args: Vec<String> =
+            ["-depth", "3", "-threads", "2", "-hash", "0", "-divide", "x.epd"].iter().map(|s| s.to_string()).collect();
+        let opts = parse_args(&args).unwrap();
+        assert_eq!((opts.max_depth, opts.threads, opts.hash_mb, opts.divide), (3, 2, 0, true));
+        assert_eq!(opts.path.as_deref(), Some("x.epd"));
+        assert!(parse_args(&["-threads".to_string(), "0".to_string()]).is_none());
+    }
+}
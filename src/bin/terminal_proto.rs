@@ -1,6 +1,7 @@
 use std::io::{self, Write};
 use std::time::Instant;
 
+use rust_chess_engine::book::Book;
 use rust_chess_engine::board::mailbox120::{QUEEN_DIRECTIONS, square120_from_file_rank};
 use rust_chess_engine::movegen::{Move, generate_legal_moves_in_place};
 use rust_chess_engine::position::{Cell, Color, PieceKind, Position, Game, GameStatus};
@@ -10,7 +11,7 @@ use rust_chess_engine::search::{SearchLimits, Searcher};
 fn main() {
     let  mut cli = EngineCli::new();
 
-    println!("terminal_promo — commands: help | eval | go [depth N|time MS|nodes N] | undo | undo2 | new | engine on/off | quit");
+    println!("terminal_promo — commands: help | eval | go [depth N|time MS|nodes N|multipv N] | undo | undo2 | new | engine on/off | book <file> | quit");
     loop {
         cli.print_position();
 
@@ -110,6 +111,7 @@ pub struct EngineCli {
     legal_buf: Vec<Move>,
     engine_enabled: bool,
     default_limits: SearchLimits,
+    book: Option<Book>,
 }
 
 impl EngineCli {
@@ -125,7 +127,10 @@ impl EngineCli {
                 max_depth: 7,
                 max_nodes: None,
                 max_time_ms: Some(2000),
+                multi_pv: 1,
+                clock: None,
             },
+            book: None,
         }
     }
 
@@ -149,14 +154,21 @@ impl EngineCli {
     fn play_engine_move(&mut self, limits: SearchLimits) {
         let root_side_to_move = self.game.position().player_to_move;
 
+        if let Some(mv) = self.book.as_mut().and_then(|book| book.pick(self.game.position())) {
+            println!("Engine({:?}): book move {} ({})", root_side_to_move, mv.to_uci(), mv.to_san(self.game.position()));
+            self.game.try_play_move(mv);
+            return;
+        }
+
         let requested_depth = limits.max_depth;
         let requested_time_ms = limits.max_time_ms;
         let requested_nodes = limits.max_nodes;
 
         let t0 = Instant::now();
         let result = {
+            let prior_keys = self.game.prior_keys();
             let (searcher, game) = (&mut self.searcher, &mut self.game);
-            searcher.search(game.position_mut(), limits)
+            searcher.search_with_history(game.position_mut(), limits, &prior_keys)
         };
 
         let score_side_to_move_cp = result.score_cp;
@@ -185,9 +197,10 @@ impl EngineCli {
         }
 
         println!(
-            "Engine({:?}): bestmove {} | score(stm)={}cp | score(white)={}cp | depth={}/{} | nodes={} | elapsed={}ms | stop={} | limits(time={:?}ms, nodes={:?})",
+            "Engine({:?}): bestmove {} ({}) | score(stm)={}cp | score(white)={}cp | depth={}/{} | nodes={} | elapsed={}ms | stop={} | limits(time={:?}ms, nodes={:?})",
             root_side_to_move,
             result.best_move.to_uci(),
+            result.best_move.to_san(self.game.position()),
             score_side_to_move_cp,
             score_white_cp,
             reached_depth,
@@ -198,6 +211,13 @@ impl EngineCli {
             requested_time_ms,
             requested_nodes,
         );
+        if result.lines.len() > 1 {
+            for (i, line) in result.lines.iter().enumerate() {
+                println!("line {}: {}cp | {}", i + 1, line.score_cp, pv_to_san(self.game.position(), &line.pv));
+            }
+        } else {
+            println!("pv: {}", pv_to_san(self.game.position(), &result.pv));
+        }
 
         self.game.try_play_move(result.best_move);
     }
@@ -218,8 +238,10 @@ impl EngineCli {
                 println!("  undo                           (undo 1 ply");
                 println!("  undo2                          (undo 2 plies");
                 println!("  eval                           (classical eval, from White perspective");
-                println!("  go [depth N| time MS|noes N]   (engine plays one move noew)");
+                println!("  go [depth N| time MS|noes N|multipv N] (engine plays one move noew)");
+                println!("  <move>                         (your move in UCI or SAN, e.g. e2e4 / Nf3)");
                 println!("  engine on/off                  (toggle auto-engine reply after your move");
+                println!("  book <file> | off | depth N    (polyglot opening book, used for the first N plies");
                 return false;
             }
 
@@ -255,6 +277,29 @@ impl EngineCli {
                 return false;
             }
 
+            "book" => {
+                match parts.get(1).copied() {
+                    None => match &self.book {
+                        Some(book) => println!("book: {} entries, depth {}", book.len(), book.max_ply()),
+                        None => println!("no book loaded"),
+                    },
+                    Some("off") => self.book = None,
+                    Some("depth") => match (parts.get(2).and_then(|n| n.parse::<u32>().ok()), self.book.as_mut()) {
+                        (Some(n), Some(book)) => book.set_max_ply(n),
+                        (Some(_), None) => println!("no book loaded"),
+                        (None, _) => println!("usage: book depth N"),
+                    },
+                    Some(path) => match Book::open(path) {
+                        Ok(book) => {
+                            println!("book loaded: {} entries", book.len());
+                            self.book = Some(book);
+                        }
+                        Err(err) => println!("cannot load book {path}: {err}"),
+                    },
+                }
+                return false;
+            }
+
             "eval" => {
                 let score = self.eval_view.evaluate(self.game.position());
                 println!("Eval (White+) {} cp", score);
@@ -289,8 +334,10 @@ impl EngineCli {
             return false;
         }
 
-        //uci -> legal move
-        let user_mv = match find_legal_move_from_uci(input, &self.legal_buf) {
+        //uci or SAN -> legal move
+        let user_mv = match find_legal_move_from_uci(input, &self.legal_buf)
+            .or_else(|| Move::from_san(self.game.position(), input))
+        {
             Some(mv) => mv,
             None => {
                 println!("Illegal: {input}");
@@ -352,6 +399,13 @@ fn format_status(status: GameStatus) -> String {
                     }
                     token_index += 2;
                 }
+                "multipv" if token_index + 1 < go_tokens.len() => {
+                    let multipv_token = go_tokens[token_index + 1];
+                    if let Ok(parsed_multipv) = multipv_token.parse::<usize>() {
+                        effective_limits.multi_pv = parsed_multipv.max(1);
+                    }
+                    token_index += 2;
+                }
                 //unknown token:
                 _ => {
                     token_index += 1;
@@ -405,6 +459,17 @@ fn find_legal_move_from_uci(input: &str, legal: &[Move]) -> Option<Move> {
     })
 }
 
+//SAN line, each move formatted from the position it is played in
+fn pv_to_san(pos: &Position, pv: &[Move]) -> String {
+    let mut scratch = pos.clone();
+    let mut line = Vec::with_capacity(pv.len());
+    for &mv in pv {
+        line.push(mv.to_san(&scratch));
+        scratch.make_move(mv);
+    }
+    line.join(" ")
+}
+
 #[cfg(test)]
 mod terminal_promo_cli_tests {
     use super::parse_go_limits;
@@ -415,6 +480,8 @@ mod terminal_promo_cli_tests {
             max_depth: 5,
             max_nodes: None,
             max_time_ms: None,
+            multi_pv: 1,
+            clock: None,
         }
     }
     #[test]
@@ -458,6 +525,14 @@ mod terminal_promo_cli_tests {
         assert_eq!(limits.max_time_ms, Some(2000));
         assert_eq!(limits.max_nodes, None);
     }
+
+    #[test]
+    fn multipv_is_parsed_and_at_least_one() {
+        let limits = parse_go_limits(&["multipv", "3"], default_limits());
+        assert_eq!(limits.multi_pv, 3);
+        let limits = parse_go_limits(&["multipv", "0"], default_limits());
+        assert_eq!(limits.multi_pv, 1);
+    }
 }
 
 #[cfg(test)]
@@ -505,6 +580,18 @@ mod terminal_promo_handle_line_tests {
         assert_ne!(side_after, side_before);
     }
 
+    #[test]
+    fn san_user_move_is_accepted() {
+        let mut cli = EngineCli::new();
+        cli.handle_line("engine off");
+
+        assert!(!cli.handle_line("Nf3"));
+        assert_eq!(
+            cli.game.position().to_fen(),
+            "rnbqkbnr/pppppppp/8/8/8/5N2/PPPPPPPP/RNBQKB1R b KQkq - 1 1"
+        );
+    }
+
     #[test]
     fn illegal_user_move_does_not_change_position() {
         let mut cli = EngineCli::new();
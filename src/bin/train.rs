@@ -0,0 +1,24 @@
+//trains the evaluation network on lichess eval jsonl files:
+//train --dataset lichess_db_eval.jsonl [--config run.json] [flags, see trainer_rust::config::USAGE]
+
+use std::process::ExitCode;
+
+use rust_chess_engine::trainer_rust::config::{TrainConfig, USAGE};
+use rust_chess_engine::trainer_rust::trainer_main;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let config = match TrainConfig::from_args(&args) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{err:#}");
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Err(err) = trainer_main::run(&config) {
+        eprintln!("training failed: {err:#}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,438 @@
+//bitboard layer next to the mailbox board: one u64 per piece and per color,
+//bit i is square64 i (a1 = 0, h8 = 63)
+//sliding attacks use magic bitboards with precomputed magics, the attack tables are
+//filled once at startup
+
+use std::sync::LazyLock;
+
+use crate::position::{Color, Piece, PieceKind};
+
+pub type Bitboard = u64;
+
+pub const FILE_A: Bitboard = 0x0101_0101_0101_0101;
+pub const FILE_H: Bitboard = FILE_A << 7;
+pub const RANK_1: Bitboard = 0xff;
+pub const RANK_2: Bitboard = RANK_1 << 8;
+pub const RANK_7: Bitboard = RANK_1 << 48;
+pub const RANK_8: Bitboard = RANK_1 << 56;
+
+//same order as piece_counter: WP, WN, WB, WR, WQ, WK, BP, BN, BB, BR, BQ, BK
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Bitboards {
+    pub pieces: [Bitboard; 12],
+    pub colors: [Bitboard; 2],
+}
+
+impl Bitboards {
+    //adds the piece if the square is empty for it, removes it otherwise
+    #[inline]
+    pub fn toggle(&mut self, piece: Piece, sq64: usize) {
+        let bit = square_bb(sq64);
+        self.pieces[piece.kind.idx() + piece.color.idx() * 6] ^= bit;
+        self.colors[piece.color.idx()] ^= bit;
+    }
+
+    #[inline]
+    pub fn piece(&self, color: Color, kind: PieceKind) -> Bitboard {
+        self.pieces[kind.idx() + color.idx() * 6]
+    }
+
+    #[inline]
+    pub fn color(&self, color: Color) -> Bitboard {
+        self.colors[color.idx()]
+    }
+
+    #[inline]
+    pub fn occupied(&self) -> Bitboard {
+        self.colors[0] | self.colors[1]
+    }
+
+    //both colors
+    #[inline]
+    pub fn kind(&self, kind: PieceKind) -> Bitboard {
+        self.pieces[kind.idx()] | self.pieces[kind.idx() + 6]
+    }
+}
+
+#[inline]
+pub const fn square_bb(sq64: usize) -> Bitboard {
+    1u64 << sq64
+}
+
+//index of the lowest set bit, which is cleared; bb must not be empty
+#[inline]
+pub fn pop_lsb(bb: &mut Bitboard) -> usize {
+    debug_assert!(*bb != 0, "pop_lsb on empty bitboard");
+    let sq = bb.trailing_zeros() as usize;
+    *bb &= *bb - 1;
+    sq
+}
+
+//iterates the squares of a bitboard, lowest first
+pub struct Squares(Bitboard);
+
+impl Iterator for Squares {
+    type Item = usize;
+
+    #[inline]
+    fn next(&mut self) -> Option<usize> {
+        if self.0 == 0 {
+            None
+        } else {
+            Some(pop_lsb(&mut self.0))
+        }
+    }
+}
+
+#[inline]
+pub fn squares(bb: Bitboard) -> Squares {
+    Squares(bb)
+}
+
+//(file, rank) deltas
+const KNIGHT_DELTAS: [(i32, i32); 8] = [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+const KING_DELTAS: [(i32, i32); 8] = [(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1)];
+const ROOK_DELTAS: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+const BISHOP_DELTAS: [(i32, i32); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+//square reached from sq64 by (df, dr), if it is on the board
+fn offset(sq64: usize, df: i32, dr: i32) -> Option<usize> {
+    let file = (sq64 % 8) as i32 + df;
+    let rank = (sq64 / 8) as i32 + dr;
+    if (0..8).contains(&file) && (0..8).contains(&rank) {
+        Some((rank * 8 + file) as usize)
+    } else {
+        None
+    }
+}
+
+fn leaper_table(deltas: &[(i32, i32)]) -> [Bitboard; 64] {
+    let mut table = [0; 64];
+    for (sq64, attacks) in table.iter_mut().enumerate() {
+        for &(df, dr) in deltas {
+            if let Some(to) = offset(sq64, df, dr) {
+                *attacks |= square_bb(to);
+            }
+        }
+    }
+    table
+}
+
+static KNIGHT_ATTACKS: LazyLock<[Bitboard; 64]> = LazyLock::new(|| leaper_table(&KNIGHT_DELTAS));
+static KING_ATTACKS: LazyLock<[Bitboard; 64]> = LazyLock::new(|| leaper_table(&KING_DELTAS));
+//[color][square]: squares a pawn of that color attacks
+static PAWN_ATTACKS: LazyLock<[[Bitboard; 64]; 2]> =
+    LazyLock::new(|| [leaper_table(&[(-1, 1), (1, 1)]), leaper_table(&[(-1, -1), (1, -1)])]);
+
+//slow reference: walks every ray until it leaves the board or hits a blocker (included)
+fn sliding_attacks_slow(sq64: usize, occupied: Bitboard, deltas: &[(i32, i32)]) -> Bitboard {
+    let mut attacks = 0;
+    for &(df, dr) in deltas {
+        let mut current = sq64;
+        while let Some(next) = offset(current, df, dr) {
+            attacks |= square_bb(next);
+            if occupied & square_bb(next) != 0 {
+                break;
+            }
+            current = next;
+        }
+    }
+    attacks
+}
+
+//squares whose occupancy matters for a slider on sq64: the rays without the last square
+fn relevant_mask(sq64: usize, deltas: &[(i32, i32)]) -> Bitboard {
+    let mut mask = 0;
+    for &(df, dr) in deltas {
+        let mut current = sq64;
+        while let Some(next) = offset(current, df, dr) {
+            if offset(next, df, dr).is_none() {
+                break;
+            }
+            mask |= square_bb(next);
+            current = next;
+        }
+    }
+    mask
+}
+
+#[derive(Clone, Copy, Default)]
+struct Magic {
+    mask: Bitboard,
+    magic: u64,
+    shift: u32,
+    offset: usize,
+}
+
+impl Magic {
+    #[inline]
+    fn index(&self, occupied: Bitboard) -> usize {
+        self.offset + ((occupied & self.mask).wrapping_mul(self.magic) >> self.shift) as usize
+    }
+}
+
+struct SliderTable {
+    magics: [Magic; 64],
+    attacks: Vec<Bitboard>,
+}
+
+impl SliderTable {
+    fn new(deltas: &[(i32, i32)], magic_numbers: &[u64; 64]) -> Self {
+        let mut magics = [Magic::default(); 64];
+        let mut attacks = Vec::new();
+
+        for (sq64, magic) in magics.iter_mut().enumerate() {
+            let mask = relevant_mask(sq64, deltas);
+            let bits = mask.count_ones();
+            *magic = Magic {
+                mask,
+                magic: magic_numbers[sq64],
+                shift: 64 - bits,
+                offset: attacks.len(),
+            };
+            attacks.resize(magic.offset + (1usize << bits), 0);
+
+            //all subsets of the mask (carry-rippler)
+            let mut subset: Bitboard = 0;
+            loop {
+                let idx = magic.index(subset);
+                let reference = sliding_attacks_slow(sq64, subset, deltas);
+                debug_assert!(
+                    attacks[idx] == 0 || attacks[idx] == reference,
+                    "bad magic for square {sq64}"
+                );
+                attacks[idx] = reference;
+                subset = subset.wrapping_sub(mask) & mask;
+                if subset == 0 {
+                    break;
+                }
+            }
+        }
+
+        Self { magics, attacks }
+    }
+
+    #[inline]
+    fn attacks(&self, sq64: usize, occupied: Bitboard) -> Bitboard {
+        self.attacks[self.magics[sq64].index(occupied)]
+    }
+}
+
+//found with a seeded random search over sparse candidates (a & b & c), for index
+//sizes of exactly the number of relevant occupancy bits
+const ROOK_MAGICS: [u64; 64] = [
+    0x80800510e0400082, 0x8540011002412000, 0x1080081000802002, 0x5180080005801000,
+    0x0280080004008002, 0x2200100200080401, 0x2280030001800a00, 0x0180008000a24900,
+    0x8c12802080024001, 0x8200400040201003, 0x0000802000801000, 0x0021001000082101,
+    0x1040800400800800, 0x2302808014000200, 0x6003000403000600, 0x4822800080004100,
+    0x0680004020004001, 0x009000c000402000, 0x0020008010002080, 0x08500a0040102200,
+    0x4140050028005100, 0x07008801044010a0, 0x0c00c4000210c801, 0x4004020001208844,
+    0x0000800080204000, 0x4100500140012002, 0x0840100080200080, 0x20044202000a1020,
+    0x0444080100050010, 0xe205020080040080, 0x2004082400d00201, 0x082280008030cb00,
+    0x000040088a800020, 0x0000802102004200, 0x0140104101002002, 0x0105100084800802,
+    0x2043080101000410, 0x2102008002800400, 0x0410020804000110, 0xc400040042000081,
+    0x4000800040008024, 0x0081044200860020, 0x00a0100020008080, 0x0434120020420008,
+    0x0838000402004040, 0x0044001008020200, 0x0808083042040081, 0x0201004100820024,
+    0x5000208000410100, 0x8010401000200840, 0x4001084020001100, 0x8208080010008080,
+    0x8021004800100500, 0x0004020004008080, 0x0000100208010400, 0x0002800100004080,
+    0x0900108001002841, 0x0802010010284082, 0x0208100841002001, 0x2041000408201001,
+    0x0123000800100285, 0x4092008b10084402, 0x0020100102182084, 0x6000102084004502,
+];
+
+const BISHOP_MAGICS: [u64; 64] = [
+    0x4044810811050200, 0x1020880089004120, 0x040800a40c810222, 0x4804104200282082,
+    0x0001104000800000, 0x0420903420002200, 0x040080841040000a, 0x0809002910080400,
+    0xe002292004009600, 0x780004040404002a, 0x0000080081060400, 0x7980020a02002000,
+    0xa010820210010002, 0x4011020842080602, 0x4062a52402200518, 0x4000102108083421,
+    0x89d200200202081c, 0x0045008248020400, 0x802480880a001200, 0x00a800222023c000,
+    0x0281008820080000, 0x8802000504808400, 0x6084021080880803, 0x0500848426080284,
+    0x8020840020082200, 0x0004228004104424, 0x048034000800c400, 0x020200220a008200,
+    0x0001010040104000, 0x0022020008480640, 0x0004004000825000, 0x00020180605c0082,
+    0x0091041210401028, 0x8808041000430200, 0x4028109000180040, 0x800c208020e80200,
+    0x044c110011640040, 0x2022104900021000, 0x0222020042241420, 0x088c004144060320,
+    0x0088280210080a40, 0x0000980802100900, 0x0288101808009400, 0x2001484010400200,
+    0x8402482008202101, 0x0030008901000810, 0x0008900102050042, 0x0911180600888044,
+    0x0000880410040001, 0x821200820842004a, 0x0814248410881004, 0xa18002004202000c,
+    0x0060001042020002, 0x0801040408020404, 0x0020080181040804, 0x0010500244404403,
+    0x00001a0801080801, 0x1000008445082080, 0x0000400114010400, 0x8680080444420880,
+    0xb088831031020602, 0xa784400808380820, 0x040022022c090400, 0x0008101002002020,
+];
+
+static ROOK_TABLE: LazyLock<SliderTable> =
+    LazyLock::new(|| SliderTable::new(&ROOK_DELTAS, &ROOK_MAGICS));
+static BISHOP_TABLE: LazyLock<SliderTable> =
+    LazyLock::new(|| SliderTable::new(&BISHOP_DELTAS, &BISHOP_MAGICS));
+
+#[inline]
+pub fn knight_attacks(sq64: usize) -> Bitboard {
+    KNIGHT_ATTACKS[sq64]
+}
+
+#[inline]
+pub fn king_attacks(sq64: usize) -> Bitboard {
+    KING_ATTACKS[sq64]
+}
+
+//squares attacked by a pawn of the given color standing on sq64
+#[inline]
+pub fn pawn_attacks(color: Color, sq64: usize) -> Bitboard {
+    PAWN_ATTACKS[color.idx()][sq64]
+}
+
+#[inline]
+pub fn bishop_attacks(sq64: usize, occupied: Bitboard) -> Bitboard {
+    BISHOP_TABLE.attacks(sq64, occupied)
+}
+
+#[inline]
+pub fn rook_attacks(sq64: usize, occupied: Bitboard) -> Bitboard {
+    ROOK_TABLE.attacks(sq64, occupied)
+}
+
+#[inline]
+pub fn queen_attacks(sq64: usize, occupied: Bitboard) -> Bitboard {
+    bishop_attacks(sq64, occupied) | rook_attacks(sq64, occupied)
+}
+
+//[a][b]: squares strictly between a and b if they share a rank, file or diagonal
+static BETWEEN: LazyLock<Box<[[Bitboard; 64]; 64]>> = LazyLock::new(|| {
+    let mut table = Box::new([[0; 64]; 64]);
+    for a in 0..64 {
+        for b in 0..64 {
+            let target = square_bb(b);
+            if rook_attacks(a, 0) & target != 0 {
+                table[a][b] = rook_attacks(a, target) & rook_attacks(b, square_bb(a));
+            } else if bishop_attacks(a, 0) & target != 0 {
+                table[a][b] = bishop_attacks(a, target) & bishop_attacks(b, square_bb(a));
+            }
+        }
+    }
+    table
+});
+
+//[a][b]: the whole line through a and b (both included), empty if not aligned
+static LINE: LazyLock<Box<[[Bitboard; 64]; 64]>> = LazyLock::new(|| {
+    let mut table = Box::new([[0; 64]; 64]);
+    for a in 0..64 {
+        for b in 0..64 {
+            let ends = square_bb(a) | square_bb(b);
+            if a != b && rook_attacks(a, 0) & square_bb(b) != 0 {
+                table[a][b] = (rook_attacks(a, 0) & rook_attacks(b, 0)) | ends;
+            } else if a != b && bishop_attacks(a, 0) & square_bb(b) != 0 {
+                table[a][b] = (bishop_attacks(a, 0) & bishop_attacks(b, 0)) | ends;
+            }
+        }
+    }
+    table
+});
+
+#[inline]
+pub fn between(a: usize, b: usize) -> Bitboard {
+    BETWEEN[a][b]
+}
+
+#[inline]
+pub fn line(a: usize, b: usize) -> Bitboard {
+    LINE[a][b]
+}
+
+//attacks of a non-pawn piece
+#[inline]
+pub fn piece_attacks(kind: PieceKind, sq64: usize, occupied: Bitboard) -> Bitboard {
+    match kind {
+        PieceKind::Knight => knight_attacks(sq64),
+        PieceKind::Bishop => bishop_attacks(sq64, occupied),
+        PieceKind::Rook => rook_attacks(sq64, occupied),
+        PieceKind::Queen => queen_attacks(sq64, occupied),
+        PieceKind::King => king_attacks(sq64),
+        PieceKind::Pawn => {
+            debug_assert!(false, "piece_attacks: pawn attacks depend on the color");
+            0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    //a1 = 0, e4 = 28, h8 = 63
+    const A1: usize = 0;
+    const E4: usize = 28;
+    const H8: usize = 63;
+
+    #[test]
+    fn leaper_tables_respect_board_edges() {
+        assert_eq!(knight_attacks(A1).count_ones(), 2);
+        assert_eq!(knight_attacks(E4).count_ones(), 8);
+        assert_eq!(king_attacks(A1).count_ones(), 3);
+        assert_eq!(king_attacks(E4).count_ones(), 8);
+        //no wrap from the a-file to the h-file
+        assert_eq!(pawn_attacks(Color::White, 8), square_bb(17));
+        assert_eq!(pawn_attacks(Color::Black, 55), square_bb(46));
+        assert_eq!(pawn_attacks(Color::White, H8), 0);
+    }
+
+    #[test]
+    fn magic_lookups_match_ray_walking() {
+        //pseudo random occupancies, including the edge squares the masks leave out
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..200 {
+            let occupied: Bitboard = rng.r#gen::<u64>() & rng.r#gen::<u64>();
+            for sq64 in 0..64 {
+                assert_eq!(
+                    rook_attacks(sq64, occupied),
+                    sliding_attacks_slow(sq64, occupied, &ROOK_DELTAS)
+                );
+                assert_eq!(
+                    bishop_attacks(sq64, occupied),
+                    sliding_attacks_slow(sq64, occupied, &BISHOP_DELTAS)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn empty_board_slider_attacks() {
+        assert_eq!(rook_attacks(A1, 0), (FILE_A | RANK_1) & !square_bb(A1));
+        assert_eq!(bishop_attacks(A1, 0).count_ones(), 7);
+        assert_eq!(queen_attacks(E4, 0).count_ones(), 27);
+    }
+
+    #[test]
+    fn between_and_line_tables() {
+        //a1-h8 diagonal
+        assert_eq!(between(A1, H8).count_ones(), 6);
+        assert_eq!(line(A1, 27).count_ones(), 8);
+        assert_eq!(between(A1, 1), 0);
+        assert_eq!(between(E4, E4), 0);
+        //not aligned: a1-b3 is a knight jump
+        assert_eq!(between(A1, 17), 0);
+        assert_eq!(line(A1, 17), 0);
+        //e4-e8 file
+        assert_eq!(between(E4, 60), square_bb(36) | square_bb(44) | square_bb(52));
+        assert_eq!(line(E4, 60), FILE_A << 4);
+    }
+
+    #[test]
+    fn toggle_keeps_piece_and_color_sets_in_sync() {
+        let mut bbs = Bitboards::default();
+        let knight = Piece { color: Color::Black, kind: PieceKind::Knight };
+
+        bbs.toggle(knight, E4);
+        assert_eq!(bbs.piece(Color::Black, PieceKind::Knight), square_bb(E4));
+        assert_eq!(bbs.color(Color::Black), square_bb(E4));
+        assert_eq!(bbs.occupied(), square_bb(E4));
+
+        bbs.toggle(knight, E4);
+        assert_eq!(bbs, Bitboards::default());
+    }
+
+    #[test]
+    fn squares_iterates_lowest_first() {
+        let bb = square_bb(3) | square_bb(40) | square_bb(63);
+        assert_eq!(squares(bb).collect::<Vec<_>>(), vec![3, 40, 63]);
+    }
+}
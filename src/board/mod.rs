@@ -1,3 +1,4 @@
+pub mod bitboard;
 pub mod conversion;
 pub mod debug;
 pub mod mailbox120;
@@ -0,0 +1,298 @@
+//polyglot opening books (.bin): 16 byte big endian entries (key, move, weight, learn)
+//sorted by key, the key is position::polyglot_key
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::board::mailbox120::SQUARE120_TO_SQUARE64;
+use crate::movegen::{Move, PromotionPiece, generate_legal_moves};
+use crate::position::{Color, Game, Position, polyglot_key};
+
+const ENTRY_SIZE: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BookEntry {
+    pub key: u64,
+    pub mv: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+impl BookEntry {
+    fn from_bytes(b: &[u8]) -> Self {
+        Self {
+            key: u64::from_be_bytes(b[0..8].try_into().unwrap()),
+            mv: u16::from_be_bytes(b[8..10].try_into().unwrap()),
+            weight: u16::from_be_bytes(b[10..12].try_into().unwrap()),
+            learn: u32::from_be_bytes(b[12..16].try_into().unwrap()),
+        }
+    }
+
+    fn to_bytes(self) -> [u8; ENTRY_SIZE] {
+        let mut b = [0; ENTRY_SIZE];
+        b[0..8].copy_from_slice(&self.key.to_be_bytes());
+        b[8..10].copy_from_slice(&self.mv.to_be_bytes());
+        b[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        b[12..16].copy_from_slice(&self.learn.to_be_bytes());
+        b
+    }
+}
+
+//polyglot move: to file/rank in bits 0-5, from file/rank in bits 6-11, promotion in 12-14,
+//castling is written as the king taking its own rook
+pub fn encode_move(mv: Move) -> u16 {
+    let from = SQUARE120_TO_SQUARE64[mv.from_sq()] as u16;
+    let mut to = SQUARE120_TO_SQUARE64[mv.to_sq()] as u16;
+    if mv.is_castling() {
+        to = if to > from { to | 7 } else { to & !7 };
+    }
+    let promotion = match mv.promotion_piece() {
+        None => 0,
+        Some(PromotionPiece::Knight) => 1,
+        Some(PromotionPiece::Bishop) => 2,
+        Some(PromotionPiece::Rook) => 3,
+        Some(PromotionPiece::Queen) => 4,
+    };
+    promotion << 12 | from << 6 | to
+}
+
+//the legal move of pos a book move stands for
+pub fn decode_move(pos: &Position, raw: u16) -> Option<Move> {
+    let mut legal = Vec::new();
+    generate_legal_moves(pos, &mut legal);
+    legal.into_iter().find(|&mv| encode_move(mv) == raw)
+}
+
+pub struct Book {
+    entries: Vec<BookEntry>,
+    //no book moves once the game is this many plies old
+    max_ply: u32,
+    rng: StdRng,
+}
+
+impl Book {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let data = fs::read(path)?;
+        if data.len() % ENTRY_SIZE != 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "book size is not a multiple of 16"));
+        }
+        Ok(Self::from_entries(data.chunks_exact(ENTRY_SIZE).map(BookEntry::from_bytes).collect()))
+    }
+
+    pub fn from_entries(mut entries: Vec<BookEntry>) -> Self {
+        //lookups rely on the order, files from other tools are not trusted with it
+        entries.sort_by_key(|e| e.key);
+        Self { entries, max_ply: u32::MAX, rng: StdRng::from_entropy() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn max_ply(&self) -> u32 {
+        self.max_ply
+    }
+
+    pub fn set_max_ply(&mut self, max_ply: u32) {
+        self.max_ply = max_ply;
+    }
+
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    fn entries_for(&self, key: u64) -> &[BookEntry] {
+        let start = self.entries.partition_point(|e| e.key < key);
+        let end = start + self.entries[start..].partition_point(|e| e.key == key);
+        &self.entries[start..end]
+    }
+
+    //legal book moves of pos with their weights; entries not legal here are hash collisions
+    pub fn moves(&self, pos: &Position) -> Vec<(Move, u16)> {
+        let mut legal = Vec::new();
+        generate_legal_moves(pos, &mut legal);
+        self.entries_for(polyglot_key(pos))
+            .iter()
+            .filter_map(|e| legal.iter().find(|&&mv| encode_move(mv) == e.mv).map(|&mv| (mv, e.weight)))
+            .collect()
+    }
+
+    //random book move, each one chosen with probability proportional to its weight
+    pub fn pick(&mut self, pos: &Position) -> Option<Move> {
+        if game_ply(pos) >= self.max_ply {
+            return None;
+        }
+        let moves = self.moves(pos);
+        let total: u32 = moves.iter().map(|&(_, w)| u32::from(w)).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut r = self.rng.gen_range(0..total);
+        for (mv, w) in moves {
+            if r < u32::from(w) {
+                return Some(mv);
+            }
+            r -= u32::from(w);
+        }
+        None
+    }
+}
+
+//plies played since the standard start, from the fullmove number
+fn game_ply(pos: &Position) -> u32 {
+    let black = u32::from(pos.player_to_move == Color::Black);
+    2 * u32::from(pos.move_counter.saturating_sub(1)) + black
+}
+
+//collects the moves of games into book entries: a move scores 2 for a win of its side and
+//1 for a draw, as in polyglot make-book
+#[derive(Default)]
+pub struct BookBuilder {
+    scores: HashMap<(u64, u16), u64>,
+    max_ply: Option<u32>,
+}
+
+impl BookBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    //only moves played before this ply go into the book
+    pub fn set_max_ply(&mut self, max_ply: u32) {
+        self.max_ply = Some(max_ply);
+    }
+
+    //result as in pgn: "1-0", "0-1", "1/2-1/2", anything else counts like a loss for both
+    pub fn add_game(&mut self, game: &Game, result: &str) {
+        let mut pos = game.start_position();
+        for (ply, mv) in game.moves().enumerate() {
+            if self.max_ply.is_some_and(|max| ply as u32 >= max) {
+                break;
+            }
+            let white = pos.player_to_move == Color::White;
+            let score = match (result, white) {
+                ("1-0", true) | ("0-1", false) => 2,
+                ("1/2-1/2", _) => 1,
+                _ => 0,
+            };
+            *self.scores.entry((polyglot_key(&pos), encode_move(mv))).or_default() += score;
+            pos.make_move(mv);
+        }
+    }
+
+    //entries sorted by key, weights scaled down to fit 16 bits; moves that never scored
+    //are left out
+    pub fn finish(self) -> Vec<BookEntry> {
+        let max = self.scores.values().copied().max().unwrap_or(0);
+        let scale = max.div_ceil(u64::from(u16::MAX)).max(1);
+        let mut entries: Vec<BookEntry> = self
+            .scores
+            .into_iter()
+            .filter(|&(_, score)| score > 0)
+            .map(|((key, mv), score)| BookEntry {
+                key,
+                mv,
+                weight: (score / scale).max(1) as u16,
+                learn: 0,
+            })
+            .collect();
+        entries.sort_by_key(|e| (e.key, std::cmp::Reverse(e.weight), e.mv));
+        entries
+    }
+}
+
+pub fn write_book<W: Write>(out: &mut W, entries: &[BookEntry]) -> io::Result<()> {
+    for e in entries {
+        out.write_all(&e.to_bytes())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn legal_move(pos: &Position, uci: &str) -> Move {
+        let mut legal = Vec::new();
+        generate_legal_moves(pos, &mut legal);
+        legal.into_iter().find(|mv| mv.to_uci() == uci).unwrap()
+    }
+
+    #[test]
+    fn encodes_moves_like_polyglot() {
+        let pos = Position::starting_position();
+        //e2e4: from 12, to 28
+        assert_eq!(encode_move(decode_move(&pos, 12 << 6 | 28).unwrap()), 12 << 6 | 28);
+        assert_eq!(decode_move(&pos, 12 << 6 | 36), None);
+
+        let castle = Position::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let short = decode_move(&castle, 4 << 6 | 7).unwrap();
+        assert!(short.is_castling());
+        assert_eq!(short.to_uci(), "e1g1");
+        assert_eq!(decode_move(&castle, 4 << 6).unwrap().to_uci(), "e1c1");
+
+        let promo = Position::from_fen("8/P6k/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        assert_eq!(decode_move(&promo, 4 << 12 | 48 << 6 | 56).unwrap().to_uci(), "a7a8q");
+    }
+
+    #[test]
+    fn built_book_round_trips_and_weights_moves() {
+        let mut builder = BookBuilder::new();
+        for (first, result) in [("e2e4", "1-0"), ("e2e4", "1/2-1/2"), ("d2d4", "1-0"), ("c2c4", "0-1")] {
+            let mut game = Game::new();
+            game.try_play_move(legal_move(game.position(), first));
+            builder.add_game(&game, result);
+        }
+        let entries = builder.finish();
+        let mut bytes = Vec::new();
+        write_book(&mut bytes, &entries).unwrap();
+        assert_eq!(bytes.len(), 2 * ENTRY_SIZE);
+
+        let book = Book::from_entries(bytes.chunks_exact(ENTRY_SIZE).map(BookEntry::from_bytes).collect());
+        let moves: Vec<(String, u16)> =
+            book.moves(&Position::starting_position()).into_iter().map(|(m, w)| (m.to_uci(), w)).collect();
+        //the lost c4 game never scored
+        assert_eq!(moves, vec![("e2e4".to_string(), 3), ("d2d4".to_string(), 2)]);
+    }
+
+    #[test]
+    fn pick_follows_weights_and_depth() {
+        let mut pos = Position::starting_position();
+        let start = polyglot_key(&pos);
+        let e4 = legal_move(&pos, "e2e4");
+        pos.make_move(e4);
+        let after_e4 = polyglot_key(&pos);
+        let mut book = Book::from_entries(vec![
+            BookEntry { key: start, mv: 12 << 6 | 28, weight: 3, learn: 0 },
+            BookEntry { key: start, mv: 11 << 6 | 27, weight: 1, learn: 0 },
+            BookEntry { key: start, mv: 10 << 6 | 26, weight: 0, learn: 0 },
+            BookEntry { key: after_e4, mv: 50 << 6 | 34, weight: 1, learn: 0 },
+        ]);
+        book.set_seed(7);
+
+        let start_pos = Position::starting_position();
+        let mut e4 = 0;
+        for _ in 0..1000 {
+            match book.pick(&start_pos).unwrap().to_uci().as_str() {
+                "e2e4" => e4 += 1,
+                "d2d4" => {}
+                other => panic!("unexpected book move {other}"),
+            }
+        }
+        assert!((650..850).contains(&e4), "e4 picked {e4} times");
+
+        assert_eq!(book.pick(&pos).unwrap().to_uci(), "c7c5");
+        book.set_max_ply(1);
+        assert_eq!(book.pick(&pos), None);
+        assert!(book.pick(&start_pos).is_some());
+    }
+}
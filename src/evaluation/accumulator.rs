@@ -0,0 +1,549 @@
+//plain rust forward pass of the evaluation mlp with an incrementally updated first layer
+//(nnue style): a move only turns a few input features on or off, so the hidden values of
+//the first layer change by a few weight rows. The position carries the accumulator and
+//make_move_with_undo/undo_move push and pop its values.
+
+use std::any::Any;
+use std::fmt;
+use std::ops::{AddAssign, SubAssign};
+use std::sync::Arc;
+
+use crate::board::mailbox120::SQUARE120_TO_SQUARE64;
+use crate::position::{Cell, Color, Piece, Position, Square, Undo};
+
+//input layout of decode_pos_nn: 12 piece planes (rank 8 first), white to move, castling
+//rights (black 0-0, black 0-0-0, white 0-0, white 0-0-0), en passant file
+pub const FEATURES: usize = 781;
+//id of this layout in network files
+pub const FEATURE_SET_ID: u32 = 1;
+//centipawns of a tanh output, the scale the trainer fits with
+pub const DEFAULT_OUTPUT_SCALE: f32 = 600.0;
+const WHITE_TO_MOVE: usize = 768;
+const CASTLING: usize = 769;
+const EN_PASSANT: usize = 773;
+//castling_rights bits in feature order
+const CASTLING_BITS: [u8; 4] = [0b0100, 0b1000, 0b0001, 0b0010];
+
+//a network whose first layer is accumulated: its bias plus one weight row per active feature
+pub trait AccumulatedNet: Send + Sync + 'static {
+    type Value: Copy + AddAssign + SubAssign + Send + Sync + 'static;
+
+    fn hidden(&self) -> usize;
+    fn bias(&self) -> &[Self::Value];
+    fn row(&self, feature: usize) -> &[Self::Value];
+    //raw network output (white's view) for the first layer values
+    fn forward(&self, accumulated: &[Self::Value]) -> f32;
+    //centipawns per atanh of the output, see score_to_cp
+    fn output_scale(&self) -> f32;
+}
+
+//fully connected layer, weights input-major ([input][output], the layout of burn's Linear)
+#[derive(Clone, Debug)]
+pub struct Dense {
+    inputs: usize,
+    outputs: usize,
+    weights: Vec<f32>,
+    bias: Vec<f32>,
+}
+
+impl Dense {
+    pub fn new(inputs: usize, outputs: usize, weights: Vec<f32>, bias: Vec<f32>) -> Option<Self> {
+        (weights.len() == inputs * outputs && bias.len() == outputs).then_some(Self { inputs, outputs, weights, bias })
+    }
+
+    pub fn inputs(&self) -> usize {
+        self.inputs
+    }
+
+    pub fn outputs(&self) -> usize {
+        self.outputs
+    }
+
+    pub fn bias(&self) -> &[f32] {
+        &self.bias
+    }
+
+    pub fn weights(&self) -> &[f32] {
+        &self.weights
+    }
+
+    //weights from one input to all outputs
+    pub fn row(&self, input: usize) -> &[f32] {
+        &self.weights[input * self.outputs..(input + 1) * self.outputs]
+    }
+
+    fn forward(&self, input: &[f32]) -> Vec<f32> {
+        let mut out = self.bias.clone();
+        for (i, &x) in input.iter().enumerate().filter(|&(_, &x)| x != 0.0) {
+            for (o, &w) in out.iter_mut().zip(self.row(i)) {
+                *o += x * w;
+            }
+        }
+        out
+    }
+}
+
+//activation of the hidden layers (MLP::sc_relu)
+#[inline]
+fn sc_relu(x: f32) -> f32 {
+    let c = x.clamp(0.0, 1.0);
+    c * c
+}
+
+//first layer (accumulated) followed by the small layers, sc_relu between all of them
+#[derive(Debug)]
+pub struct Network {
+    first: Dense,
+    rest: Vec<Dense>,
+    output_scale: f32,
+}
+
+impl Network {
+    //None if the layer sizes don't chain up to a single output
+    pub fn new(first: Dense, rest: Vec<Dense>) -> Option<Self> {
+        let mut width = first.outputs;
+        if first.inputs != FEATURES {
+            return None;
+        }
+        for layer in &rest {
+            if layer.inputs != width {
+                return None;
+            }
+            width = layer.outputs;
+        }
+        (width == 1).then_some(Self { first, rest, output_scale: DEFAULT_OUTPUT_SCALE })
+    }
+
+    pub fn with_output_scale(mut self, output_scale: f32) -> Self {
+        self.output_scale = output_scale;
+        self
+    }
+
+    //all layers, the accumulated one first
+    pub fn layers(&self) -> impl Iterator<Item = &Dense> {
+        std::iter::once(&self.first).chain(&self.rest)
+    }
+}
+
+impl AccumulatedNet for Network {
+    type Value = f32;
+
+    fn hidden(&self) -> usize {
+        self.first.outputs
+    }
+
+    fn bias(&self) -> &[f32] {
+        &self.first.bias
+    }
+
+    fn row(&self, feature: usize) -> &[f32] {
+        self.first.row(feature)
+    }
+
+    fn forward(&self, accumulated: &[f32]) -> f32 {
+        let mut x: Vec<f32> = accumulated.iter().map(|&v| sc_relu(v)).collect();
+        for (i, layer) in self.rest.iter().enumerate() {
+            x = layer.forward(&x);
+            if i + 1 < self.rest.len() {
+                x.iter_mut().for_each(|v| *v = sc_relu(*v));
+            }
+        }
+        x[0]
+    }
+
+    fn output_scale(&self) -> f32 {
+        self.output_scale
+    }
+}
+
+//network output (tanh-like) to centipawns, saturating to +-30000 from 1.2 on
+pub fn score_to_cp(score: f32, scale: f32) -> i32 {
+    if score >= 1.2 {
+        return 30_000;
+    }
+    if score <= -1.2 {
+        return -30_000;
+    }
+    let cp = scale * score.clamp(-1.0, 1.0).atanh();
+    cp.clamp(-30_000.0, 30_000.0) as i32
+}
+
+fn piece_feature(piece: Piece, sq120: usize) -> usize {
+    let sq64 = SQUARE120_TO_SQUARE64[sq120] as usize;
+    let flipped = (7 - sq64 / 8) * 8 + sq64 % 8;
+    let plane = piece.kind.idx() + if piece.color == Color::White { 0 } else { 6 };
+    plane * 64 + flipped
+}
+
+fn en_passant_feature(ep: Square) -> usize {
+    EN_PASSANT + SQUARE120_TO_SQUARE64[ep.as_usize()] as usize % 8
+}
+
+//indices of the inputs that are 1 in decode_pos_nn(pos)
+pub fn active_features(pos: &Position) -> Vec<usize> {
+    let mut features = Vec::with_capacity(40);
+    for (sq120, cell) in pos.board.iter().enumerate() {
+        if let Cell::Piece(piece) = *cell {
+            features.push(piece_feature(piece, sq120));
+        }
+    }
+    if pos.player_to_move == Color::White {
+        features.push(WHITE_TO_MOVE);
+    }
+    for (i, bit) in CASTLING_BITS.iter().enumerate() {
+        if pos.castling_rights & bit != 0 {
+            features.push(CASTLING + i);
+        }
+    }
+    if let Some(ep) = pos.en_passant_square {
+        features.push(en_passant_feature(ep));
+    }
+    features
+}
+
+//what Position::accumulator holds: any accumulator, the evaluator that attached it
+//gets its own type back with as_any
+pub trait Incremental: Any + Send + Sync {
+    //recomputes the current values from scratch
+    fn refresh(&mut self, pos: &Position);
+    //pos is the position after the move undo belongs to
+    fn push_move(&mut self, undo: &Undo, pos: &Position);
+    //pos is the position after the null move
+    fn push_null(&mut self, prev_ep: Option<Square>, pos: &Position);
+    fn pop(&mut self);
+    fn box_clone(&self) -> Box<dyn Incremental>;
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl Clone for Box<dyn Incremental> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+impl fmt::Debug for dyn Incremental {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Accumulator")
+    }
+}
+
+//first layer values of the current position and of every position before it in the
+//line, so undo is a pop
+pub struct Accumulator<N: AccumulatedNet> {
+    network: Arc<N>,
+    //stack[..=top] in use, the rest are buffers kept for reuse
+    stack: Vec<Vec<N::Value>>,
+    top: usize,
+}
+
+impl<N: AccumulatedNet> Accumulator<N> {
+    pub fn new(network: Arc<N>, pos: &Position) -> Self {
+        let mut acc = Self { stack: vec![Vec::new()], top: 0, network };
+        acc.refresh_values(pos);
+        acc
+    }
+
+    //the accumulator of network attached to pos, if there is one
+    pub fn attached<'a>(pos: &'a Position, network: &Arc<N>) -> Option<&'a Self> {
+        let acc = pos.accumulator.as_deref()?.as_any().downcast_ref::<Self>()?;
+        Arc::ptr_eq(&acc.network, network).then_some(acc)
+    }
+
+    //raw network output for pos, from scratch if no accumulator of network is attached
+    pub fn evaluate_position(network: &Arc<N>, pos: &Position) -> f32 {
+        match Self::attached(pos, network) {
+            Some(acc) => acc.evaluate(),
+            None => Self::new(Arc::clone(network), pos).evaluate(),
+        }
+    }
+
+    pub fn network(&self) -> &Arc<N> {
+        &self.network
+    }
+
+    pub fn values(&self) -> &[N::Value] {
+        &self.stack[self.top]
+    }
+
+    //raw network output of the current position
+    pub fn evaluate(&self) -> f32 {
+        self.network.forward(self.values())
+    }
+
+    fn refresh_values(&mut self, pos: &Position) {
+        let values = &mut self.stack[self.top];
+        values.clear();
+        values.extend_from_slice(self.network.bias());
+        for feature in active_features(pos) {
+            add_row(values, self.network.row(feature));
+        }
+    }
+
+    fn push(&mut self) {
+        self.top += 1;
+        if self.stack.len() == self.top {
+            self.stack.push(Vec::with_capacity(self.network.hidden()));
+        }
+        let (done, next) = self.stack.split_at_mut(self.top);
+        next[0].clear();
+        next[0].extend_from_slice(&done[self.top - 1]);
+    }
+
+    fn update(&mut self, removed: &[usize], added: &[usize]) {
+        let values = &mut self.stack[self.top];
+        for &f in removed {
+            sub_row(values, self.network.row(f));
+        }
+        for &f in added {
+            add_row(values, self.network.row(f));
+        }
+    }
+}
+
+impl<N: AccumulatedNet> Incremental for Accumulator<N> {
+    fn refresh(&mut self, pos: &Position) {
+        self.refresh_values(pos);
+    }
+
+    fn push_move(&mut self, undo: &Undo, pos: &Position) {
+        self.push();
+        let mut removed = [0; 8];
+        let mut added = [0; 8];
+        let (mut r, mut a) = (0, 0);
+
+        removed[r] = piece_feature(undo.moving_piece, undo.mv.from_sq());
+        r += 1;
+        if let Cell::Piece(piece) = pos.board[undo.mv.to_sq()] {
+            //the promoted piece for promotions
+            added[a] = piece_feature(piece, undo.mv.to_sq());
+            a += 1;
+        }
+        if let Some(captured) = undo.captured {
+            removed[r] = piece_feature(captured, undo.captured_sq.unwrap_or(undo.mv.to_sq()));
+            r += 1;
+        }
+        if let (Some(rook_from), Some(rook_to)) = (undo.rook_from, undo.rook_to)
+            && let Cell::Piece(rook) = pos.board[rook_to]
+        {
+            removed[r] = piece_feature(rook, rook_from);
+            added[a] = piece_feature(rook, rook_to);
+            r += 1;
+            a += 1;
+        }
+
+        //side to move always changes
+        if pos.player_to_move == Color::White {
+            added[a] = WHITE_TO_MOVE;
+            a += 1;
+        } else {
+            removed[r] = WHITE_TO_MOVE;
+            r += 1;
+        }
+        for (i, bit) in CASTLING_BITS.iter().enumerate() {
+            //rights are only ever lost
+            if undo.prev_castling & bit != 0 && pos.castling_rights & bit == 0 {
+                removed[r] = CASTLING + i;
+                r += 1;
+            }
+        }
+        if let Some(ep) = undo.prev_ep_sq {
+            removed[r] = en_passant_feature(ep);
+            r += 1;
+        }
+        if let Some(ep) = pos.en_passant_square {
+            added[a] = en_passant_feature(ep);
+            a += 1;
+        }
+        self.update(&removed[..r], &added[..a]);
+    }
+
+    fn push_null(&mut self, prev_ep: Option<Square>, pos: &Position) {
+        self.push();
+        let mut removed = [0; 2];
+        let mut added = [0; 1];
+        let (mut r, mut a) = (0, 0);
+        if pos.player_to_move == Color::White {
+            added[a] = WHITE_TO_MOVE;
+            a += 1;
+        } else {
+            removed[r] = WHITE_TO_MOVE;
+            r += 1;
+        }
+        if let Some(ep) = prev_ep {
+            removed[r] = en_passant_feature(ep);
+            r += 1;
+        }
+        self.update(&removed[..r], &added[..a]);
+    }
+
+    fn pop(&mut self) {
+        debug_assert!(self.top > 0, "accumulator popped below the root");
+        self.top = self.top.saturating_sub(1);
+    }
+
+    fn box_clone(&self) -> Box<dyn Incremental> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+//plain loops over slices, vectorized by the compiler
+#[inline]
+fn add_row<V: Copy + AddAssign>(values: &mut [V], row: &[V]) {
+    values.iter_mut().zip(row).for_each(|(v, &w)| *v += w);
+}
+
+#[inline]
+fn sub_row<V: Copy + SubAssign>(values: &mut [V], row: &[V]) {
+    values.iter_mut().zip(row).for_each(|(v, &w)| *v -= w);
+}
+
+impl<N: AccumulatedNet> Clone for Accumulator<N> {
+    fn clone(&self) -> Self {
+        Self { network: Arc::clone(&self.network), stack: self.stack.clone(), top: self.top }
+    }
+}
+
+impl<N: AccumulatedNet> fmt::Debug for Accumulator<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Accumulator").field("hidden", &self.network.hidden()).field("ply", &self.top).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::Evaluator;
+    use crate::movegen::{Move, generate_legal_moves};
+    use crate::search::{SearchLimits, Searcher};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    const HIDDEN: usize = 16;
+
+    fn random_dense(rng: &mut StdRng, inputs: usize, outputs: usize) -> Dense {
+        let weights = (0..inputs * outputs).map(|_| rng.gen_range(-0.1..0.1)).collect();
+        let bias = (0..outputs).map(|_| rng.gen_range(-0.1..0.1)).collect();
+        Dense::new(inputs, outputs, weights, bias).unwrap()
+    }
+
+    fn random_network() -> Arc<Network> {
+        let mut rng = StdRng::seed_from_u64(42);
+        let first = random_dense(&mut rng, FEATURES, HIDDEN);
+        let rest = vec![random_dense(&mut rng, HIDDEN, 8), random_dense(&mut rng, 8, 1)];
+        Arc::new(Network::new(first, rest).unwrap())
+    }
+
+    fn attached(pos: &Position) -> &Accumulator<Network> {
+        pos.accumulator.as_deref().unwrap().as_any().downcast_ref().unwrap()
+    }
+
+    fn assert_matches_refresh(pos: &Position) {
+        let acc = attached(pos);
+        let fresh = Accumulator::new(Arc::clone(acc.network()), pos);
+        for (a, b) in acc.values().iter().zip(fresh.values()) {
+            assert!((a - b).abs() < 1e-4, "incremental {a} vs refreshed {b} in {}", pos.to_fen());
+        }
+    }
+
+    fn find(pos: &Position, uci: &str) -> Move {
+        let mut legal = Vec::new();
+        generate_legal_moves(pos, &mut legal);
+        legal.into_iter().find(|m| m.to_uci() == uci).unwrap()
+    }
+
+    #[test]
+    fn forward_matches_dense_layers() {
+        let net = random_network();
+        let pos = Position::starting_position();
+        let mut input = vec![0.0; FEATURES];
+        active_features(&pos).into_iter().for_each(|f| input[f] = 1.0);
+        //slow path: every layer as a dense product
+        let mut x: Vec<f32> = net.first.forward(&input).into_iter().map(sc_relu).collect();
+        x = net.rest[0].forward(&x).into_iter().map(sc_relu).collect();
+        let expected = net.rest[1].forward(&x)[0];
+
+        let acc = Accumulator::new(net, &pos);
+        assert!((acc.evaluate() - expected).abs() < 1e-5);
+    }
+
+    #[test]
+    fn incremental_updates_match_refresh() {
+        let net = random_network();
+        //castling both ways, en passant, promotion with capture
+        let lines: [(&str, &[&str]); 3] = [
+            ("r3k2r/pppq1ppp/2n2n2/3pp3/4P3/2NP1N2/PPPQ1PPP/R3K2R w KQkq - 0 1", &["e1c1", "e8g8", "d2g5", "d5d4"]),
+            ("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1", &["e4e5", "d5d4", "e5e6", "d4d3"]),
+            ("rnbqkbnr/pP4pp/8/2p2p2/8/8/P1PPPPPP/RNBQKBNR w KQkq c6 0 5", &["b7a8q", "e8f7", "a8b8"]),
+        ];
+        for (fen, moves) in lines {
+            let mut pos = Position::from_fen(fen).unwrap();
+            pos.accumulator = Some(Box::new(Accumulator::new(Arc::clone(&net), &pos)));
+            let mut undos = Vec::new();
+            for uci in moves {
+                undos.push(pos.make_move_with_undo(find(&pos, uci)));
+                assert_matches_refresh(&pos);
+            }
+            let null = pos.make_null_move();
+            assert_matches_refresh(&pos);
+            pos.undo_null_move(null);
+            for undo in undos.into_iter().rev() {
+                pos.undo_move(undo);
+                assert_matches_refresh(&pos);
+            }
+            assert_eq!(attached(&pos).top, 0);
+        }
+
+        //en passant capture
+        let mut pos = Position::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        pos.accumulator = Some(Box::new(Accumulator::new(Arc::clone(&net), &pos)));
+        let undo = pos.make_move_with_undo(find(&pos, "e5d6"));
+        assert_matches_refresh(&pos);
+        pos.undo_move(undo);
+        assert_matches_refresh(&pos);
+        //plain make_move recomputes
+        pos.make_move(find(&pos, "e1d2"));
+        assert_matches_refresh(&pos);
+    }
+
+    //attaches like the neural evaluators, scores from scratch
+    struct AttachingEval(Arc<Network>);
+
+    impl Evaluator for AttachingEval {
+        fn evaluate(&mut self, pos: &Position) -> i32 {
+            (Accumulator::new(Arc::clone(&self.0), pos).evaluate() * 100.0) as i32
+        }
+
+        fn attach(&mut self, pos: &mut Position) {
+            pos.accumulator = Some(Box::new(Accumulator::new(Arc::clone(&self.0), pos)));
+        }
+    }
+
+    #[test]
+    fn search_leaves_the_position_unattached() {
+        let mut eval = AttachingEval(random_network());
+        let mut pos = Position::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        let before = pos.clone();
+        let mut attached = pos.clone();
+        eval.attach(&mut attached);
+        assert_eq!(attached, before);
+
+        let limits = SearchLimits { max_depth: 3, max_nodes: None, max_time_ms: None, multi_pv: 1, clock: None };
+        Searcher::new(eval).search(&mut pos, limits);
+        assert!(pos.accumulator.is_none());
+        assert_eq!(pos, before);
+    }
+
+    #[test]
+    fn network_checks_layer_sizes() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let first = || random_dense(&mut StdRng::seed_from_u64(2), FEATURES, HIDDEN);
+        assert!(Network::new(first(), vec![random_dense(&mut rng, 8, 1)]).is_none());
+        assert!(Network::new(first(), vec![random_dense(&mut rng, HIDDEN, 2)]).is_none());
+        assert!(Network::new(first(), vec![random_dense(&mut rng, HIDDEN, 1)]).is_some());
+        assert!(Dense::new(2, 2, vec![0.0; 3], vec![0.0; 2]).is_none());
+    }
+}
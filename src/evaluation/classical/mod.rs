@@ -4,6 +4,7 @@ use crate::board::mailbox120::SQUARE120_TO_SQUARE64;
 use crate::position::{Cell, Color, Piece, PieceKind, Position};
 use pst::*;
 
+#[derive(Clone, Copy)]
 pub struct ClassicalEval;
 
 // Values in Centipawns
@@ -166,7 +167,7 @@ mod tests {
 
     // Puts a piece on a sq
     fn put(pos: &mut Position, s: usize, color: Color, kind: PieceKind) {
-        pos.board[s] = Cell::Piece(crate::position::Piece { color, kind });
+        pos.put_piece(s, crate::position::Piece { color, kind });
     }
 
     #[test]
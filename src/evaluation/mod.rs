@@ -1,15 +1,25 @@
+use crate::nn_model::format::{is_network_file, load_network};
 use crate::position::Position;
 
+pub mod accumulator;
 pub mod classical;
+pub mod quantized;
 pub use classical::ClassicalEval;
+pub use quantized::QuantizedEval;
 #[cfg(feature = "nn")]
 pub mod neural;
 pub trait Evaluator {
     fn evaluate(&mut self, pos: &Position) -> i32;
+
+    //called on the root before a search, evaluators keeping incremental state
+    //(accumulator::Accumulator) install it on the position here
+    fn attach(&mut self, _pos: &mut Position) {}
 }
 
+#[derive(Clone)]
 pub enum EvalEngine {
     Classical(classical::ClassicalEval),
+    Quantized(quantized::QuantizedEval),
     #[cfg(feature = "nn")]
     Neural(neural::NeuralEval),
 }
@@ -19,16 +29,41 @@ impl EvalEngine {
         Self::Classical(classical::ClassicalEval::new())
     }
 
+    //a binary network file from the trainer (quantized on load) or one exported by
+    //export_nn, usable without the nn feature
+    pub fn quantized(path: &str) -> anyhow::Result<Self> {
+        let eval = if is_network_file(path)? {
+            let (_, network) = load_network(path)?;
+            let net = quantized::QuantizedNet::quantize(&network)?;
+            quantized::QuantizedEval::new(std::sync::Arc::new(net))
+        } else {
+            quantized::QuantizedEval::load(path)?
+        };
+        Ok(Self::Quantized(eval))
+    }
+
     #[cfg(feature = "nn")]
     pub fn neural(path: &str) -> anyhow::Result<Self> {
         Ok(Self::Neural(neural::NeuralEval::load(path)?))
     }
+}
 
-    pub fn evaluate(&mut self, pos: &Position) -> i32 {
+impl Evaluator for EvalEngine {
+    fn evaluate(&mut self, pos: &Position) -> i32 {
         match self {
             EvalEngine::Classical(e) => e.evaluate(pos),
+            EvalEngine::Quantized(e) => e.evaluate(pos),
             #[cfg(feature = "nn")]
             EvalEngine::Neural(e) => e.evaluate(pos),
         }
     }
+
+    fn attach(&mut self, pos: &mut Position) {
+        match self {
+            EvalEngine::Classical(e) => e.attach(pos),
+            EvalEngine::Quantized(e) => e.attach(pos),
+            #[cfg(feature = "nn")]
+            EvalEngine::Neural(e) => e.attach(pos),
+        }
+    }
 }
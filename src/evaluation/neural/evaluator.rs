@@ -1,8 +1,12 @@
 //here a board is given a score so calls fature.rs then the mlp structure from the trainer
 
+use std::sync::Arc;
+
 use super::super::Evaluator;
+use crate::evaluation::accumulator::{AccumulatedNet, Accumulator, Network, score_to_cp};
 use crate::evaluation::neural::feature::decode_pos_nn;
 use crate::position::Position;
+use crate::nn_model::format::{is_network_file, load_network};
 use crate::nn_model::mlp_structure::MLP;
 use burn::module::Module;
 use burn::record::FullPrecisionSettings;
@@ -10,22 +14,39 @@ use burn::record::PrettyJsonFileRecorder;
 use burn::tensor::Tensor;
 use burn::tensor::backend::Backend;
 
+#[derive(Clone)]
 pub struct NeuralEval<B: Backend> {
     model: MLP<B>,
     device: B::Device,
+    //the same weights for the search: accumulated first layer, rest in plain rust
+    network: Arc<Network>,
 }
 
 impl<B: Backend> NeuralEval<B> {
-    //loading our device recorder and model
+    //loading our device recorder and model, from a binary network file (sizes from its
+    //header) or a json record of the 781-256-64 trainer model
     pub fn load(model_path: &str) -> anyhow::Result<Self> {
         //type B = NdArrayDevice;//(CPU)
         let device = B::Device::default();
-        let recorder: PrettyJsonFileRecorder<FullPrecisionSettings> = PrettyJsonFileRecorder::new();
-        let mut model: MLP<B> = MLP::<B>::new(781, 256, 64, &device);
-        model = model.load_file(model_path, &recorder, &device).unwrap();
+        let (model, network) = if is_network_file(model_path)? {
+            let (_, network) = load_network(model_path).map_err(|err| anyhow::anyhow!("{model_path}: {err}"))?;
+            (MLP::from_network(&network, &device)?, network)
+        } else {
+            let recorder: PrettyJsonFileRecorder<FullPrecisionSettings> = PrettyJsonFileRecorder::new();
+            let model: MLP<B> = MLP::<B>::new(781, 256, 64, &device)
+                .load_file(model_path, &recorder, &device)
+                .map_err(|err| anyhow::anyhow!("{model_path}: {err:?}"))?;
+            let network = model.to_network()?;
+            (model, network)
+        };
+        Ok(Self { model, device, network: Arc::new(network) })
+    }
 
-        Ok(Self { model, device })
+    //float copy of the weights, what the quantized export starts from
+    pub fn network(&self) -> &Arc<Network> {
+        &self.network
     }
+
     //deocdeing the Position struct into our neuron format
     fn encode(&self, position: &Position) -> Tensor<B, 2> {
         let nn_input = decode_pos_nn(position);
@@ -33,24 +54,24 @@ impl<B: Backend> NeuralEval<B> {
         let nn_input_tensor_shaped = nn_input_tensor.reshape([1, 781]);
         return nn_input_tensor_shaped;
     }
+
+    //burn forward pass, the reference for the accumulated one
+    pub fn evaluate_full(&self, position: &Position) -> i32 {
+        let input = self.encode(position);
+        let prediction = self.model.forward(input);
+        let score: f32 = prediction.to_data().to_vec::<f32>().unwrap()[0];
+        score_to_cp(score, self.network.output_scale())
+    }
 }
 
 impl<B: Backend> Evaluator for NeuralEval<B> {
     //the pass into our mlp which returns a score
     fn evaluate(&mut self, position: &Position) -> i32 {
-        let input = self.encode(position);
-        let prediction = self.model.forward(input);
-        let score: f32 = prediction.to_data().to_vec::<f32>().unwrap()[0];
+        score_to_cp(Accumulator::evaluate_position(&self.network, position), self.network.output_scale())
+    }
 
-        if score >= 1.2 {
-            return 30_000;
-        }
-        if score <= -1.2 {
-            return -30_000;
-        }
-        let s = score.clamp(-1.0 , 1.0);
-        let cp_score: f32 = 600.0 * s.atanh(); //.atanh();
-        return cp_score.clamp(-30_000.0, 30_000.0) as i32;
+    fn attach(&mut self, position: &mut Position) {
+        position.accumulator = Some(Box::new(Accumulator::new(Arc::clone(&self.network), position)));
     }
 }
 
@@ -152,4 +173,23 @@ mod tests {
 
         assert!(score > 0);
     }
+
+    #[test]
+    fn accumulated_eval_matches_burn_forward() {
+        let model_path = "src/trainer_rust/models/mlp_checkpoint_3.json";
+        let mut eval = NeuralEval::<NdArray>::load(model_path).unwrap();
+
+        let mut pos =
+            Position::from_fen("r3k2r/pn1b1p1p/6p1/1Pp1P3/6n1/P4N2/2P2PPP/R3KB1R w KQkq - 0 16")
+                .unwrap();
+        eval.attach(&mut pos);
+        let mut moves = Vec::new();
+        crate::movegen::generate_legal_moves(&pos, &mut moves);
+        for mv in moves {
+            let undo = pos.make_move_with_undo(mv);
+            assert!((eval.evaluate(&pos) - eval.evaluate_full(&pos)).abs() <= 1, "{}", mv.to_uci());
+            pos.undo_move(undo);
+        }
+        assert!((eval.evaluate(&pos) - eval.evaluate_full(&pos)).abs() <= 1);
+    }
 }
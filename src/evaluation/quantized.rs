@@ -0,0 +1,461 @@
+//integer version of the evaluation mlp that runs without burn, exported from a float
+//Network with QuantizedNet::quantize and stored as a small binary file (.nnq).
+//
+//scales: the first layer is int16 at QA (4095 = 1.0) and accumulated in i32, activations
+//are int16 at QA, the other layers int16 at 2^shift (largest shift that keeps the dot
+//products in i32) with i32 biases at QA * 2^shift. The raw output stays within
+//QUANTIZATION_TOLERANCE of the float network for weights of the size the trainer
+//produces (checked by the tests below). int8 weights for the later layers were tried and
+//are about ten times further off on nets that weren't trained for quantization.
+
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use super::Evaluator;
+use super::accumulator::{AccumulatedNet, Accumulator, FEATURES, Network, score_to_cp};
+use crate::position::Position;
+
+const MAGIC: &[u8; 4] = b"RCEQ";
+const VERSION: u32 = 2;
+const CHECKSUM_SIZE: usize = 8;
+//bounds on the sizes read from a file, far above any net the trainer makes
+const MAX_WIDTH: usize = 1 << 14;
+const MAX_LAYERS: usize = 16;
+//1.0 for the first layer and the activations
+const QA: i32 = 4095;
+const MAX_SHIFT: u32 = 16;
+
+//documented bound on |quantized - float| of the raw network output
+pub const QUANTIZATION_TOLERANCE: f32 = 0.01;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QuantizeError {
+    //layer 0 is the accumulated one
+    WeightOutOfRange { layer: usize, value: f32 },
+}
+
+impl fmt::Display for QuantizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuantizeError::WeightOutOfRange { layer, value } => {
+                write!(f, "weight {value} of layer {layer} is out of the quantized range")
+            }
+        }
+    }
+}
+
+impl std::error::Error for QuantizeError {}
+
+//a layer after the first one, weights output-major for the dot products
+#[derive(Debug)]
+struct QuantizedLayer {
+    inputs: usize,
+    outputs: usize,
+    shift: u32,
+    weights: Vec<i16>,
+    bias: Vec<i32>,
+}
+
+#[derive(Debug)]
+pub struct QuantizedNet {
+    hidden: usize,
+    output_scale: f32,
+    //int16 in the file, widened so the accumulator can't overflow
+    ft_weights: Vec<i32>,
+    ft_bias: Vec<i32>,
+    layers: Vec<QuantizedLayer>,
+}
+
+impl QuantizedNet {
+    pub fn quantize(net: &Network) -> Result<Self, QuantizeError> {
+        let mut layers = net.layers();
+        let first = layers.next().expect("network without layers");
+        let ft = |layer: usize, w: f32| {
+            let q = (w * QA as f32).round();
+            if q.abs() > i16::MAX as f32 {
+                return Err(QuantizeError::WeightOutOfRange { layer, value: w });
+            }
+            Ok(q as i32)
+        };
+        let ft_bias = first.bias().iter().map(|&b| ft(0, b)).collect::<Result<_, _>>()?;
+        let ft_weights =
+            (0..first.inputs()).flat_map(|i| first.row(i)).map(|&w| ft(0, w)).collect::<Result<_, _>>()?;
+
+        let mut quantized = Vec::new();
+        for (l, layer) in layers.enumerate() {
+            let (inputs, outputs) = (layer.inputs(), layer.outputs());
+            let max = (0..inputs).flat_map(|i| layer.row(i)).fold(0.0f32, |m, w| m.max(w.abs()));
+            //|x| <= QA, so inputs * QA * limit has to fit in i32
+            let limit = (i32::MAX / (QA * inputs as i32)).min(i16::MAX as i32) as f32;
+            if max.round() > limit {
+                return Err(QuantizeError::WeightOutOfRange { layer: l + 1, value: max });
+            }
+            let shift = (0..=MAX_SHIFT).rev().find(|&s| (max * (1 << s) as f32).round() <= limit).unwrap_or(0);
+            let scale = (1 << shift) as f32;
+            let mut weights = vec![0; inputs * outputs];
+            for i in 0..inputs {
+                for (o, &w) in layer.row(i).iter().enumerate() {
+                    weights[o * inputs + i] = (w * scale).round() as i16;
+                }
+            }
+            let bias = layer.bias().iter().map(|&b| (b * QA as f32 * scale).round() as i32).collect();
+            quantized.push(QuantizedLayer { inputs, outputs, shift, weights, bias });
+        }
+        Ok(Self { hidden: first.outputs(), output_scale: net.output_scale(), ft_weights, ft_bias, layers: quantized })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::parse(&fs::read(path)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut bytes = Vec::new();
+        self.write(&mut bytes)?;
+        fs::write(path, bytes)
+    }
+
+    //little endian: magic, version, features, hidden, output scale (f32), first layer bias
+    //and weights (i16),
+    //layer count, then per layer inputs, outputs, shift, bias (i32), weights (i16),
+    //and at the end a FNV-1a 64 checksum of everything before it (as in nn_model::format)
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        for v in [VERSION, FEATURES as u32, self.hidden as u32] {
+            bytes.extend_from_slice(&v.to_le_bytes());
+        }
+        bytes.extend_from_slice(&self.output_scale.to_le_bytes());
+        for &v in self.ft_bias.iter().chain(&self.ft_weights) {
+            bytes.extend_from_slice(&(v as i16).to_le_bytes());
+        }
+        bytes.extend_from_slice(&(self.layers.len() as u32).to_le_bytes());
+        for layer in &self.layers {
+            for v in [layer.inputs as u32, layer.outputs as u32, layer.shift] {
+                bytes.extend_from_slice(&v.to_le_bytes());
+            }
+            for &b in &layer.bias {
+                bytes.extend_from_slice(&b.to_le_bytes());
+            }
+            for &w in &layer.weights {
+                bytes.extend_from_slice(&w.to_le_bytes());
+            }
+        }
+        let checksum = fnv1a(&bytes);
+        bytes.extend_from_slice(&checksum.to_le_bytes());
+        out.write_all(&bytes)
+    }
+
+    pub fn read<R: Read>(input: &mut R) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        input.read_to_end(&mut bytes)?;
+        Self::parse(&bytes)
+    }
+
+    //sizes are checked against the bounds and the bytes left before anything is allocated
+    fn parse(bytes: &[u8]) -> io::Result<Self> {
+        if bytes.len() < MAGIC.len() + 4 + CHECKSUM_SIZE
+            || &bytes[..4] != MAGIC
+            || bytes[4..8] != VERSION.to_le_bytes()
+        {
+            return Err(invalid("not a quantized network file"));
+        }
+        let (body, stored) = bytes.split_at(bytes.len() - CHECKSUM_SIZE);
+        if fnv1a(body).to_le_bytes() != stored {
+            return Err(invalid("checksum mismatch, the network file is damaged"));
+        }
+
+        let mut input = Reader { bytes: &body[8..] };
+        if input.u32()? as usize != FEATURES {
+            return Err(invalid("network input size doesn't match the features"));
+        }
+        let hidden = input.u32()? as usize;
+        if !(1..=MAX_WIDTH).contains(&hidden) {
+            return Err(invalid("hidden layer size out of range"));
+        }
+        let output_scale = f32::from_bits(input.u32()?);
+        let ft_bias = input.i16s(hidden)?.collect();
+        let ft_weights = input.i16s(FEATURES * hidden)?.collect();
+
+        let count = input.u32()? as usize;
+        if !(1..=MAX_LAYERS).contains(&count) {
+            return Err(invalid("layer count out of range"));
+        }
+        let mut layers = Vec::with_capacity(count);
+        let mut width = hidden;
+        for _ in 0..count {
+            let inputs = input.u32()? as usize;
+            let outputs = input.u32()? as usize;
+            let shift = input.u32()?;
+            if inputs != width || !(1..=MAX_WIDTH).contains(&outputs) || shift > MAX_SHIFT {
+                return Err(invalid("layer sizes don't chain up"));
+            }
+            let bias =
+                input.take(outputs, 4)?.chunks_exact(4).map(|b| i32::from_le_bytes(b.try_into().unwrap())).collect();
+            let weights = input.i16s(inputs * outputs)?.map(|w| w as i16).collect();
+            layers.push(QuantizedLayer { inputs, outputs, shift, weights, bias });
+            width = outputs;
+        }
+        if width != 1 {
+            return Err(invalid("network must end in a single output"));
+        }
+        if !input.bytes.is_empty() {
+            return Err(invalid("trailing data after the last layer"));
+        }
+        Ok(Self { hidden, output_scale, ft_weights, ft_bias, layers })
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+//FNV-1a 64 over the file, catches truncated and damaged downloads
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |h, &b| (h ^ b as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+//little endian reader over the checked part of the file
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    //n values of size bytes each
+    fn take(&mut self, n: usize, size: usize) -> io::Result<&'a [u8]> {
+        let len = n.checked_mul(size).ok_or_else(|| invalid("layer size overflows"))?;
+        let (head, rest) = self.bytes.split_at_checked(len).ok_or_else(|| invalid("network file is truncated"))?;
+        self.bytes = rest;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(1, 4)?.try_into().unwrap()))
+    }
+
+    //widened to i32
+    fn i16s(&mut self, n: usize) -> io::Result<impl Iterator<Item = i32> + 'a> {
+        Ok(self.take(n, 2)?.chunks_exact(2).map(|b| i16::from_le_bytes([b[0], b[1]]) as i32))
+    }
+}
+
+//sc_relu on the integer scale: clamp to [0, QA] and square back to QA
+#[inline]
+fn activate(v: i32) -> i16 {
+    let c = v.clamp(0, QA);
+    ((c * c + QA / 2) / QA) as i16
+}
+
+#[inline(always)]
+fn dot_generic(x: &[i16], w: &[i16]) -> i32 {
+    x.iter().zip(w).map(|(&a, &b)| a as i32 * b as i32).sum()
+}
+
+//the generic loop compiled for avx2, chosen at runtime
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn dot_avx2(x: &[i16], w: &[i16]) -> i32 {
+    dot_generic(x, w)
+}
+
+fn dot(x: &[i16], w: &[i16]) -> i32 {
+    #[cfg(target_arch = "x86_64")]
+    if std::arch::is_x86_feature_detected!("avx2") {
+        //SAFETY: the cpu supports avx2
+        return unsafe { dot_avx2(x, w) };
+    }
+    dot_generic(x, w)
+}
+
+impl AccumulatedNet for QuantizedNet {
+    type Value = i32;
+
+    fn hidden(&self) -> usize {
+        self.hidden
+    }
+
+    fn bias(&self) -> &[i32] {
+        &self.ft_bias
+    }
+
+    fn row(&self, feature: usize) -> &[i32] {
+        &self.ft_weights[feature * self.hidden..(feature + 1) * self.hidden]
+    }
+
+    fn forward(&self, accumulated: &[i32]) -> f32 {
+        let mut x: Vec<i16> = accumulated.iter().map(|&v| activate(v)).collect();
+        let mut out = Vec::new();
+        for (i, layer) in self.layers.iter().enumerate() {
+            out.clear();
+            out.extend(
+                layer.weights.chunks_exact(layer.inputs).zip(&layer.bias).map(|(w, &b)| b + dot(&x, w)),
+            );
+            if i + 1 < self.layers.len() {
+                x = out.iter().map(|&v| activate(v >> layer.shift)).collect();
+            }
+        }
+        let last = self.layers.last().expect("network without output layer");
+        out[0] as f32 / (QA << last.shift) as f32
+    }
+
+    fn output_scale(&self) -> f32 {
+        self.output_scale
+    }
+}
+
+//neural evaluation for default builds
+#[derive(Clone)]
+pub struct QuantizedEval {
+    net: Arc<QuantizedNet>,
+}
+
+impl QuantizedEval {
+    pub fn new(net: Arc<QuantizedNet>) -> Self {
+        Self { net }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::new(Arc::new(QuantizedNet::load(path)?)))
+    }
+}
+
+impl Evaluator for QuantizedEval {
+    fn evaluate(&mut self, pos: &Position) -> i32 {
+        score_to_cp(Accumulator::evaluate_position(&self.net, pos), self.net.output_scale)
+    }
+
+    fn attach(&mut self, pos: &mut Position) {
+        pos.accumulator = Some(Box::new(Accumulator::new(Arc::clone(&self.net), pos)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::accumulator::Dense;
+    use crate::movegen::generate_legal_moves;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    //ranges like the trained checkpoints: small first layer weights, larger later ones
+    fn random_network(seed: u64) -> Network {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut dense = |inputs: usize, outputs: usize, range: f32| {
+            let weights = (0..inputs * outputs).map(|_| rng.gen_range(-range..range)).collect();
+            let bias = (0..outputs).map(|_| rng.gen_range(-range..range)).collect();
+            Dense::new(inputs, outputs, weights, bias).unwrap()
+        };
+        let first = dense(FEATURES, 64, 0.15);
+        let rest = vec![dense(64, 16, 0.5), dense(16, 1, 1.0)];
+        Network::new(first, rest).unwrap()
+    }
+
+    #[test]
+    fn quantized_output_stays_close_to_float() {
+        for seed in 0..4 {
+            let float = Arc::new(random_network(seed));
+            let quantized = Arc::new(QuantizedNet::quantize(&float).unwrap());
+            let mut pos = Position::starting_position();
+            let mut rng = StdRng::seed_from_u64(seed + 100);
+            //random game, checked at every ply
+            for _ in 0..60 {
+                let f = Accumulator::evaluate_position(&float, &pos);
+                let q = Accumulator::evaluate_position(&quantized, &pos);
+                assert!((f - q).abs() <= QUANTIZATION_TOLERANCE, "float {f} vs quantized {q} in {}", pos.to_fen());
+                let mut legal = Vec::new();
+                generate_legal_moves(&pos, &mut legal);
+                if legal.is_empty() {
+                    break;
+                }
+                pos.make_move(legal[rng.gen_range(0..legal.len())]);
+            }
+        }
+    }
+
+    #[test]
+    fn file_round_trip_and_incremental_eval() {
+        let quantized = QuantizedNet::quantize(&random_network(5).with_output_scale(400.0)).unwrap();
+        let mut bytes = Vec::new();
+        quantized.write(&mut bytes).unwrap();
+        let loaded = Arc::new(QuantizedNet::read(&mut bytes.as_slice()).unwrap());
+        assert_eq!(loaded.output_scale(), 400.0);
+        let quantized = Arc::new(quantized);
+
+        let mut eval = QuantizedEval::new(Arc::clone(&loaded));
+        let mut pos = Position::from_fen("r3k2r/pppq1ppp/2n2n2/3pp3/4P3/2NP1N2/PPPQ1PPP/R3K2R w KQkq - 0 1").unwrap();
+        let scratch = eval.evaluate(&pos);
+        eval.attach(&mut pos);
+        assert_eq!(eval.evaluate(&pos), scratch);
+        let mut legal = Vec::new();
+        generate_legal_moves(&pos, &mut legal);
+        for mv in legal {
+            let undo = pos.make_move_with_undo(mv);
+            //integer accumulation is exact
+            assert_eq!(eval.evaluate(&pos), score_to_cp(Accumulator::evaluate_position(&quantized, &pos), 400.0));
+            assert_eq!(
+                Accumulator::attached(&pos, &loaded).unwrap().values(),
+                Accumulator::new(Arc::clone(&loaded), &pos).values()
+            );
+            pos.undo_move(undo);
+        }
+
+        assert!(QuantizedNet::read(&mut &bytes[..bytes.len() - 1]).is_err());
+        bytes[0] = b'X';
+        assert!(QuantizedNet::read(&mut bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn corrupt_files_are_rejected_before_allocating() {
+        let quantized = QuantizedNet::quantize(&random_network(7)).unwrap();
+        let mut good = Vec::new();
+        quantized.write(&mut good).unwrap();
+        let with_checksum = |mut bytes: Vec<u8>| {
+            bytes.truncate(bytes.len() - CHECKSUM_SIZE);
+            let checksum = fnv1a(&bytes);
+            bytes.extend_from_slice(&checksum.to_le_bytes());
+            bytes
+        };
+        let error = |bytes: &[u8]| QuantizedNet::read(&mut &bytes[..]).unwrap_err().kind();
+
+        //one flipped weight bit
+        let mut flipped = good.clone();
+        flipped[good.len() / 2] ^= 0x10;
+        assert_eq!(error(&flipped), io::ErrorKind::InvalidData);
+
+        //huge hidden size and layer count, with a checksum that matches: magic, version,
+        //features, then hidden at 12 and the layer count right after the first layer
+        let mut huge = good.clone();
+        huge[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(error(&with_checksum(huge)), io::ErrorKind::InvalidData);
+        let count_at = 20 + 2 * (FEATURES + 1) * quantized.hidden;
+        let mut many = good.clone();
+        many[count_at..count_at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(error(&with_checksum(many)), io::ErrorKind::InvalidData);
+        //a header promising more layer outputs than the file has
+        let mut wide = good.clone();
+        wide[count_at + 8..count_at + 12].copy_from_slice(&(MAX_WIDTH as u32).to_le_bytes());
+        assert_eq!(error(&with_checksum(wide)), io::ErrorKind::InvalidData);
+
+        assert!(QuantizedNet::read(&mut good.as_slice()).is_ok());
+    }
+
+    #[test]
+    fn dot_paths_agree_and_large_weights_are_rejected() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let x: Vec<i16> = (0..100).map(|_| rng.gen_range(0..=QA as i16)).collect();
+        let w: Vec<i16> = (0..100).map(|_| rng.gen_range(-2048..=2048)).collect();
+        let expected: i32 = x.iter().zip(&w).map(|(&a, &b)| a as i32 * b as i32).sum();
+        assert_eq!(dot(&x, &w), expected);
+
+        let mut first = vec![0.0; FEATURES];
+        first[0] = 10.0;
+        let net = Network::new(
+            Dense::new(FEATURES, 1, first, vec![0.0]).unwrap(),
+            vec![Dense::new(1, 1, vec![1.0], vec![0.0]).unwrap()],
+        )
+        .unwrap();
+        assert_eq!(QuantizedNet::quantize(&net).unwrap_err(), QuantizeError::WeightOutOfRange { layer: 0, value: 10.0 });
+    }
+}
@@ -1,11 +1,13 @@
 #![recursion_limit = "256"]
 pub mod board;
+pub mod book;
 pub mod bot;
 pub mod engine;
 pub mod evaluation;
 pub mod movegen;
 pub mod position;
 pub mod search;
+pub mod tablebase;
 pub mod trainer_rust;
 pub mod utils;
 pub mod nn_model;
\ No newline at end of file
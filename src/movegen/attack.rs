@@ -4,10 +4,10 @@
 // % then less problems if square120 + direction is negative for example
 // % not possible to cast it to usize.
 
-use crate::board::mailbox120::{
-    BISHOP_DIRECTIONS, KNIGHT_DIRECTIONS, QUEEN_DIRECTIONS as KING_QUEEN_DIRECTIONS,
-    ROOK_DIRECTIONS, is_on_board,
+use crate::board::bitboard::{
+    Bitboard, bishop_attacks, king_attacks, knight_attacks, pawn_attacks, rook_attacks, squares,
 };
+use crate::board::mailbox120::{SQUARE64_TO_SQUARE120, SQUARE120_TO_SQUARE64, is_on_board};
 use crate::position::position::PieceKind;
 use crate::position::{Cell, Color, Position, Square};
 
@@ -23,10 +23,8 @@ pub fn is_square_attacked(position: &Position, square: Square, by_color: Color)
         return false;
     }
 
-    attacked_by_pawn(position, square120, by_color)
-        || attacked_by_knight(position, square120, by_color)
-        || attacked_by_sliders(position, square120, by_color)
-        || attacked_by_king(position, square120, by_color)
+    let sq64 = SQUARE120_TO_SQUARE64[square120] as usize;
+    attackers_bb(position, sq64, by_color, position.bitboards.occupied()) != 0
 }
 
 pub fn is_in_check(position: &Position, color: Color) -> bool {
@@ -50,137 +48,20 @@ pub fn is_in_check(position: &Position, color: Color) -> bool {
     is_square_attacked(position, king_square, enemy)
 }
 
-//checks if a pawn of given color attacks given square
-//pawns attack diagonally
-fn attacked_by_pawn(position: &Position, square120: usize, by_color: Color) -> bool {
-    let pawn_attack_offsets = match by_color {
-        Color::White => [-9, -11],
-        Color::Black => [9, 11],
-    };
-
-    for &offset in &pawn_attack_offsets {
-        let attacker_square = square120 as i32 + offset as i32;
-        if attacker_square < 0 {
-            continue;
-        }
-        let attacker_square = attacker_square as usize;
-
-        if !is_on_board(attacker_square) {
-            continue;
-        }
-        if let Cell::Piece(piece) = &position.board[attacker_square] {
-            if piece.color == by_color && matches!(piece.kind, PieceKind::Pawn) {
-                return true;
-            }
-        }
-    }
-    false
-}
-
-//checks if knight of given color attacks given square
-fn attacked_by_knight(position: &Position, square120: usize, by_color: Color) -> bool {
-    for &offset in &KNIGHT_DIRECTIONS {
-        let attacker_square = square120 as i32 + offset as i32;
-        if attacker_square < 0 {
-            continue;
-        }
-        let attacker_square = attacker_square as usize;
-
-        if !is_on_board(attacker_square) {
-            continue;
-        }
-        if let Cell::Piece(piece) = &position.board[attacker_square] {
-            if piece.color == by_color && matches!(piece.kind, PieceKind::Knight) {
-                return true;
-            }
-        }
-    }
-    false
-}
-
-//checks if Slider of given color attacks given square
-fn attacked_by_sliders(position: &Position, square120: usize, by_color: Color) -> bool {
-    //checks ROOK
-    for &direction in &ROOK_DIRECTIONS {
-        if check_sliding_attack(position, square120, direction, by_color, true) {
-            return true;
-        }
-    }
-
-    //check Bishop
-    for &direction in &BISHOP_DIRECTIONS {
-        if check_sliding_attack(position, square120, direction, by_color, false) {
-            return true;
-        }
-    }
-
-    false
-}
-
-//helperfunction
-fn check_sliding_attack(
-    position: &Position,
-    square120: usize,
-    direction: i8,
-    by_color: Color,
-    is_rook_direction: bool, // true -> ROOK/QUEEN, false -> BISHOP/QUEEN
-) -> bool {
-    let dir_32 = direction as i32;
-    let mut current = (square120 as i32 + dir_32);
-
-    loop {
-        //while(true) till break or return
-
-        if current < 0 {
-            break;
-        }
-        let current_usize = current as usize;
-
-        if !is_on_board(current_usize) {
-            break;
-        }
-
-        if let Cell::Piece(piece) = &position.board[current_usize] {
-            //get out wrong colors
-            if piece.color != by_color {
-                return false;
-            }
-
-            //right color but which piecekind
-            return match (is_rook_direction, &piece.kind) {
-                (true, PieceKind::Rook) => true,
-                (true, PieceKind::Queen) => true,
-                (false, PieceKind::Bishop) => true,
-                (false, PieceKind::Queen) => true,
-                _ => false,
-            };
-        }
-
-        //next
-        current += dir_32;
-    }
-    false
-}
-
-fn attacked_by_king(position: &Position, square120: usize, by_color: Color) -> bool {
-    for &offset in &KING_QUEEN_DIRECTIONS {
-        let attacker_square = square120 as i32 + offset as i32;
-        if attacker_square < 0 {
-            continue;
-        }
-        let attacker_square = attacker_square as usize;
-
-        if !is_on_board(attacker_square) {
-            continue;
-        }
-
-        if let Cell::Piece(piece) = &position.board[attacker_square] {
-            if piece.color == by_color && matches!(piece.kind, PieceKind::King) {
-                return true;
-            }
-        }
-    }
-    false
+//all pieces of by_color attacking sq64, sliders see through nothing but `occupied`
+//(callers can remove pieces from it, e.g. for x-rays in exchange evaluation)
+pub fn attackers_bb(position: &Position, sq64: usize, by_color: Color, occupied: Bitboard) -> Bitboard {
+    let bbs = &position.bitboards;
+    let queens = bbs.piece(by_color, PieceKind::Queen);
+    let diagonal = bbs.piece(by_color, PieceKind::Bishop) | queens;
+    let straight = bbs.piece(by_color, PieceKind::Rook) | queens;
+
+    //a pawn of by_color attacks sq64 from where a pawn of the other color on sq64 would attack
+    (pawn_attacks(by_color.opposite(), sq64) & bbs.piece(by_color, PieceKind::Pawn))
+        | (knight_attacks(sq64) & bbs.piece(by_color, PieceKind::Knight))
+        | (king_attacks(sq64) & bbs.piece(by_color, PieceKind::King))
+        | (bishop_attacks(sq64, occupied) & diagonal)
+        | (rook_attacks(sq64, occupied) & straight)
 }
 
 pub fn find_king(position: &Position, color: Color) -> Option<usize> {
@@ -199,141 +80,16 @@ pub fn find_king(position: &Position, color: Color) -> Option<usize> {
 
 //gibt square120 indizes der angreifer wieder
 pub fn attackers_of_square(position: &Position, square120: usize, by_color: Color) -> Vec<usize> {
-    let mut attackers = Vec::new();
-
-    //Pawn
-    let pawn_attack_offsets = match by_color {
-        Color::White => [-9, -11],
-        Color::Black => [9, 11],
-    };
-
-    for &offset in &pawn_attack_offsets {
-        let attacker_square = square120 as i32 + offset as i32;
-        if attacker_square < 0 {
-            continue;
-        }
-        let attacker_square = attacker_square as usize;
-        if is_on_board(attacker_square) {
-            if let Cell::Piece(piece) = &position.board[attacker_square] {
-                if piece.color == by_color && matches!(piece.kind, PieceKind::Pawn) {
-                    attackers.push(attacker_square);
-                }
-            }
-        }
-    }
-
-    //Knight
-    for &offset in &KNIGHT_DIRECTIONS {
-        let attacker_square = square120 as i32 + offset as i32;
-        if attacker_square < 0 {
-            continue;
-        }
-        let attacker_square = attacker_square as usize;
-
-        if is_on_board(attacker_square) {
-            if let Cell::Piece(piece) = &position.board[attacker_square] {
-                if piece.color == by_color && matches!(piece.kind, PieceKind::Knight) {
-                    attackers.push(attacker_square);
-                }
-            }
-        }
-    }
-
-    //Slider ROOK;BISHOP;QUEEN
-    for &direction in &BISHOP_DIRECTIONS {
-        if let Some(attacker) =
-            find_sliding_attacker(position, square120, direction, by_color, false)
-        {
-            attackers.push(attacker);
-        }
-    }
-
-    for &direction in &ROOK_DIRECTIONS {
-        if let Some(attacker) =
-            find_sliding_attacker(position, square120, direction, by_color, true)
-        {
-            attackers.push(attacker);
-        }
-    }
-
-    //King
-
-    for &offset in &KING_QUEEN_DIRECTIONS {
-        let attacker_square = square120 as i32 + offset as i32;
-        if attacker_square < 0 {
-            continue;
-        }
-        let attacker_square = attacker_square as usize;
-
-        if is_on_board(attacker_square) {
-            if let Cell::Piece(piece) = &position.board[attacker_square] {
-                if piece.color == by_color && matches!(piece.kind, PieceKind::King) {
-                    attackers.push(attacker_square)
-                }
-            }
-        }
-    }
-
-    attackers
-}
-
-//helper function to help detecting sliding attacker
-fn find_sliding_attacker(
-    position: &Position,
-    square120: usize,
-    direction: i8,
-    by_color: Color,
-    is_rook_direction: bool, // true -> ROOK/QUEEN, false -> BISHOP/QUEEN
-) -> Option<usize> {
-    let dir_32 = direction as i32;
-    let mut current = square120 as i32 + dir_32;
-
-    loop {
-        //while(true) till break or return
-
-        if current < 0 {
-            break;
-        }
-        let current_usize = current as usize;
-
-        if !is_on_board(current_usize) {
-            break;
-        }
-
-        if let Cell::Piece(piece) = &position.board[current_usize] {
-            //get out wrong colors
-            if piece.color != by_color {
-                return None;
-            }
-
-            //right color but which piecekind
-            let is_attacker = match (is_rook_direction, &piece.kind) {
-                (true, PieceKind::Rook) => true,
-                (true, PieceKind::Queen) => true,
-                (false, PieceKind::Bishop) => true,
-                (false, PieceKind::Queen) => true,
-                _ => false,
-            };
-
-            return if is_attacker {
-                Some(current_usize)
-            } else {
-                None
-            };
-        }
-
-        //next
-        current += dir_32;
-    }
-
-    None
+    let sq64 = SQUARE120_TO_SQUARE64[square120] as usize;
+    let attackers = attackers_bb(position, sq64, by_color, position.bitboards.occupied());
+    squares(attackers).map(|sq| SQUARE64_TO_SQUARE120[sq]).collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::position::position::PieceKind;
-    use crate::position::{Cell, Color, Position};
+    use crate::position::{Color, Position};
 
     // Helperfunction a1 = sq(0,0); h8 = sq(7,7);
     fn sq(file: i32, rank: i32) -> Square {
@@ -342,7 +98,7 @@ mod tests {
 
     // Puts a piece on a sq
     fn put(pos: &mut Position, s: Square, color: Color, kind: PieceKind) {
-        pos.board[s.as_usize()] = Cell::Piece(crate::position::Piece { color, kind });
+        pos.put_piece(s.as_usize(), crate::position::Piece { color, kind });
     }
 
     #[test]
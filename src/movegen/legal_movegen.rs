@@ -0,0 +1,564 @@
+//Legal movegen: checkers, pins and the check mask are computed once per position,
+//so no move has to be made and unmade to test it
+
+use crate::board::bitboard::{
+    Bitboard, RANK_1, RANK_2, RANK_7, RANK_8, between, bishop_attacks, king_attacks, line,
+    pawn_attacks, piece_attacks, rook_attacks, square_bb, squares,
+};
+use crate::board::mailbox120::{SQUARE64_TO_SQUARE120, SQUARE120_TO_SQUARE64};
+use crate::movegen::attack::attackers_bb;
+use crate::movegen::{Move, pawn, piece};
+use crate::position::position::PieceKind;
+use crate::position::{Cell, Color, Position};
+
+//what the side to move has to respect in this position
+struct Restrictions {
+    king64: usize,
+    checkers: Bitboard,
+    //squares a non-king move has to land on: the checker or a blocking square
+    check_mask: Bitboard,
+    //own pieces that may only move along their line to the king
+    pinned: Bitboard,
+}
+
+impl Restrictions {
+    fn new(position: &Position) -> Self {
+        let us = position.player_to_move;
+        let them = us.opposite();
+        let bbs = &position.bitboards;
+        let occupied = bbs.occupied();
+
+        let king64 = bbs.piece(us, PieceKind::King).trailing_zeros() as usize;
+        debug_assert!(king64 < 64, "legal movegen: no king for {us:?}");
+
+        let checkers = attackers_bb(position, king64, them, occupied);
+        let check_mask = match checkers.count_ones() {
+            0 => !0,
+            1 => checkers | between(king64, checkers.trailing_zeros() as usize),
+            //double check: only the king can move
+            _ => 0,
+        };
+
+        //enemy sliders on a free line to the king except for exactly one own piece
+        let queens = bbs.piece(them, PieceKind::Queen);
+        let snipers = (rook_attacks(king64, 0) & (bbs.piece(them, PieceKind::Rook) | queens))
+            | (bishop_attacks(king64, 0) & (bbs.piece(them, PieceKind::Bishop) | queens));
+        let mut pinned = 0;
+        for sniper in squares(snipers) {
+            let blockers = between(king64, sniper) & occupied;
+            if blockers.count_ones() == 1 && blockers & bbs.color(us) != 0 {
+                pinned |= blockers;
+            }
+        }
+
+        Self { king64, checkers, check_mask, pinned }
+    }
+
+    //squares the piece on from64 may move to, apart from the check mask
+    #[inline]
+    fn pin_mask(&self, from64: usize) -> Bitboard {
+        if self.pinned & square_bb(from64) != 0 {
+            line(self.king64, from64)
+        } else {
+            !0
+        }
+    }
+}
+
+//which part of the legal moves to generate; captures are everything that changes
+//material (en passant and all promotions included), quiets the rest
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Gen {
+    All,
+    Captures,
+    Quiets,
+}
+
+impl Gen {
+    //target squares of king and piece moves
+    fn targets(self, position: &Position) -> Bitboard {
+        let us = position.player_to_move;
+        let bbs = &position.bitboards;
+        match self {
+            Gen::All => !bbs.color(us),
+            Gen::Captures => bbs.color(us.opposite()),
+            Gen::Quiets => !bbs.occupied(),
+        }
+    }
+
+    fn wants(self, position: &Position, mv: Move) -> bool {
+        let capture = mv.is_en_passant()
+            || mv.is_promotion()
+            || position.bitboards.occupied() & square_bb(SQUARE120_TO_SQUARE64[mv.to_sq()] as usize) != 0;
+        match self {
+            Gen::All => true,
+            Gen::Captures => capture,
+            Gen::Quiets => !capture,
+        }
+    }
+}
+
+pub fn generate_legal_moves(position: &Position, out: &mut Vec<Move>) {
+    out.clear();
+    generate(position, out, Gen::All);
+}
+
+//appends the legal captures and promotions to out
+pub fn generate_legal_captures(position: &Position, out: &mut Vec<Move>) {
+    generate(position, out, Gen::Captures);
+}
+
+//appends the legal moves generate_legal_captures leaves out, castling included
+pub fn generate_legal_quiets(position: &Position, out: &mut Vec<Move>) {
+    generate(position, out, Gen::Quiets);
+}
+
+//appends the quiet moves (no capture, no promotion) that give check
+pub fn generate_legal_checks(position: &Position, out: &mut Vec<Move>) {
+    let first = out.len();
+    generate(position, out, Gen::Quiets);
+
+    let mut kept = first;
+    for i in first..out.len() {
+        if gives_check(position, out[i]) {
+            out[kept] = out[i];
+            kept += 1;
+        }
+    }
+    out.truncate(kept);
+}
+
+//whether the legal move mv checks the opponent, directly or by uncovering a slider
+pub fn gives_check(position: &Position, mv: Move) -> bool {
+    let us = position.player_to_move;
+    let bbs = &position.bitboards;
+    let king = bbs.piece(us.opposite(), PieceKind::King);
+    let Cell::Piece(piece) = position.board[mv.from_sq()] else {
+        return false;
+    };
+    if king == 0 {
+        return false;
+    }
+    let king64 = king.trailing_zeros() as usize;
+    let from64 = SQUARE120_TO_SQUARE64[mv.from_sq()] as usize;
+    let to64 = SQUARE120_TO_SQUARE64[mv.to_sq()] as usize;
+
+    //occupancy after the move; left holds our squares the bitboards still show as occupied
+    let mut occupied = (bbs.occupied() ^ square_bb(from64)) | square_bb(to64);
+    let mut left = square_bb(from64);
+    if mv.is_en_passant() {
+        let captured64 = match us {
+            Color::White => to64 - 8,
+            Color::Black => to64 + 8,
+        };
+        occupied ^= square_bb(captured64);
+    }
+
+    let direct = if mv.is_castling() {
+        //king side: h-rook to f, queen side: a-rook to d
+        let (rook_from, rook_to) = if mv.to_sq() > mv.from_sq() {
+            (mv.from_sq() + 3, mv.from_sq() + 1)
+        } else {
+            (mv.from_sq() - 4, mv.from_sq() - 1)
+        };
+        let rook_from64 = SQUARE120_TO_SQUARE64[rook_from] as usize;
+        let rook_to64 = SQUARE120_TO_SQUARE64[rook_to] as usize;
+        occupied = (occupied ^ square_bb(rook_from64)) | square_bb(rook_to64);
+        left |= square_bb(rook_from64);
+        rook_attacks(rook_to64, occupied)
+    } else {
+        match mv.promotion.map_or(piece.kind, |p| p.to_piece_kind()) {
+            PieceKind::Pawn => pawn_attacks(us, to64),
+            PieceKind::King => 0,
+            kind => piece_attacks(kind, to64, occupied),
+        }
+    };
+    if direct & king != 0 {
+        return true;
+    }
+    //discovered: one of our sliders sees the king through the square that was left
+    attackers_bb(position, king64, us, occupied) & !left != 0
+}
+
+fn generate(position: &Position, out: &mut Vec<Move>, part: Gen) {
+    let r = Restrictions::new(position);
+    let targets = part.targets(position);
+
+    gen_king_moves(position, &r, targets, out);
+    //double check: only the king can move
+    if r.checkers.count_ones() > 1 {
+        return;
+    }
+    if r.checkers == 0 && part != Gen::Captures {
+        piece::gen_castling_moves(position, out, SQUARE64_TO_SQUARE120[r.king64]);
+    }
+
+    let us = position.player_to_move;
+    let bbs = &position.bitboards;
+    let occupied = bbs.occupied();
+
+    for kind in [PieceKind::Knight, PieceKind::Bishop, PieceKind::Rook, PieceKind::Queen] {
+        for from64 in squares(bbs.piece(us, kind)) {
+            let to_mask = targets & r.check_mask & r.pin_mask(from64);
+            let from = SQUARE64_TO_SQUARE120[from64];
+            for to64 in squares(piece_attacks(kind, from64, occupied) & to_mask) {
+                out.push(Move::new(from, SQUARE64_TO_SQUARE120[to64]));
+            }
+        }
+    }
+
+    //pawns: the pseudo legal moves of each pawn, filtered with the masks
+    for from64 in squares(bbs.piece(us, PieceKind::Pawn)) {
+        let first = out.len();
+        pawn::gen_pawn_moves(position, out, SQUARE64_TO_SQUARE120[from64]);
+
+        let allowed = r.check_mask & r.pin_mask(from64);
+        let mut kept = first;
+        for i in first..out.len() {
+            let mv = out[i];
+            if part.wants(position, mv) && pawn_move_is_legal(position, &r, allowed, mv) {
+                out[kept] = mv;
+                kept += 1;
+            }
+        }
+        out.truncate(kept);
+    }
+}
+
+#[inline]
+fn pawn_move_is_legal(position: &Position, r: &Restrictions, allowed: Bitboard, mv: Move) -> bool {
+    if mv.is_en_passant() {
+        en_passant_is_legal(position, r, mv)
+    } else {
+        square_bb(SQUARE120_TO_SQUARE64[mv.to_sq()] as usize) & allowed != 0
+    }
+}
+
+//whether a move from somewhere else (TT, killers) can be played in this position,
+//without generating the moves of the position
+pub fn is_legal_move(position: &Position, mv: Move) -> bool {
+    if mv.is_null() {
+        return false;
+    }
+    let us = position.player_to_move;
+    let piece = match position.board[mv.from_sq()] {
+        Cell::Piece(p) if p.color == us => p,
+        _ => return false,
+    };
+    let bbs = &position.bitboards;
+    let from64 = SQUARE120_TO_SQUARE64[mv.from_sq()] as usize;
+    let to64 = SQUARE120_TO_SQUARE64[mv.to_sq()] as usize;
+    if to64 >= 64 || bbs.color(us) & square_bb(to64) != 0 {
+        return false;
+    }
+    let r = Restrictions::new(position);
+
+    match piece.kind {
+        PieceKind::King if mv.is_castling() => {
+            if r.checkers != 0 {
+                return false;
+            }
+            let mut castles = Vec::new();
+            piece::gen_castling_moves(position, &mut castles, mv.from_sq());
+            castles.contains(&mv)
+        }
+        PieceKind::King => {
+            let occupied = bbs.occupied() ^ square_bb(r.king64);
+            mv == Move::new(mv.from_sq(), mv.to_sq())
+                && king_attacks(from64) & square_bb(to64) != 0
+                && attackers_bb(position, to64, us.opposite(), occupied) == 0
+        }
+        PieceKind::Pawn => {
+            r.checkers.count_ones() < 2
+                && pawn_move_is_pseudo_legal(position, mv, from64, to64)
+                && pawn_move_is_legal(position, &r, r.check_mask & r.pin_mask(from64), mv)
+        }
+        kind => {
+            mv == Move::new(mv.from_sq(), mv.to_sq())
+                && piece_attacks(kind, from64, bbs.occupied())
+                    & r.check_mask
+                    & r.pin_mask(from64)
+                    & square_bb(to64)
+                    != 0
+        }
+    }
+}
+
+//the pawn can make mv here, with the move type gen_pawn_moves would give it
+fn pawn_move_is_pseudo_legal(position: &Position, mv: Move, from64: usize, to64: usize) -> bool {
+    let us = position.player_to_move;
+    let bbs = &position.bitboards;
+    let occupied = bbs.occupied();
+    let to_bb = square_bb(to64);
+    let attacks = pawn_attacks(us, from64);
+
+    if mv.is_en_passant() {
+        return position.en_passant_square.map(|sq| sq.as_usize()) == Some(mv.to_sq())
+            && attacks & to_bb != 0
+            && mv == Move::new_en_passant(mv.from_sq(), mv.to_sq());
+    }
+
+    let (push, start_rank, promotion_rank) = match us {
+        Color::White => (8, RANK_2, RANK_8),
+        Color::Black => (-8, RANK_7, RANK_1),
+    };
+    //promotion exactly on the last rank, and only as a promotion
+    let kind_ok = if to_bb & promotion_rank != 0 {
+        mv.is_promotion() && mv.promotion.is_some()
+    } else {
+        mv == Move::new(mv.from_sq(), mv.to_sq())
+    };
+    let single = from64 as i32 + push;
+
+    if attacks & to_bb & bbs.color(us.opposite()) != 0 {
+        kind_ok
+    } else if to64 as i32 == single {
+        kind_ok && occupied & to_bb == 0
+    } else if to64 as i32 == single + push {
+        square_bb(from64) & start_rank != 0
+            && occupied & (to_bb | square_bb(single as usize)) == 0
+            && mv == Move::new_pawn_double(mv.from_sq(), mv.to_sq())
+    } else {
+        false
+    }
+}
+
+//king steps to squares that are not attacked once the king has left its square
+fn gen_king_moves(position: &Position, r: &Restrictions, targets: Bitboard, out: &mut Vec<Move>) {
+    let us = position.player_to_move;
+    let bbs = &position.bitboards;
+    //without the king, so sliders see through it (stepping back along a checking line)
+    let occupied = bbs.occupied() ^ square_bb(r.king64);
+    let from = SQUARE64_TO_SQUARE120[r.king64];
+
+    for to64 in squares(king_attacks(r.king64) & targets) {
+        if attackers_bb(position, to64, us.opposite(), occupied) == 0 {
+            out.push(Move::new(from, SQUARE64_TO_SQUARE120[to64]));
+        }
+    }
+}
+
+//en passant removes two pieces from one line, so masks aren't enough
+//(e.g. king and enemy rook on the same rank as both pawns): replay the occupancy
+fn en_passant_is_legal(position: &Position, r: &Restrictions, mv: Move) -> bool {
+    let us = position.player_to_move;
+    let from64 = SQUARE120_TO_SQUARE64[mv.from_sq()] as usize;
+    let to64 = SQUARE120_TO_SQUARE64[mv.to_sq()] as usize;
+    let captured64 = match us {
+        Color::White => to64 - 8,
+        Color::Black => to64 + 8,
+    };
+
+    let occupied = (position.bitboards.occupied() ^ square_bb(from64) ^ square_bb(captured64))
+        | square_bb(to64);
+    //the captured pawn can't give check anymore
+    attackers_bb(position, r.king64, us.opposite(), occupied) & !square_bb(captured64) == 0
+}
+
+//no move list needed: stops at the first legal move
+pub fn has_legal_move(position: &Position) -> bool {
+    let r = Restrictions::new(position);
+    let mut buf = Vec::new();
+    gen_king_moves(position, &r, Gen::All.targets(position), &mut buf);
+    if !buf.is_empty() {
+        return true;
+    }
+    if r.checkers.count_ones() > 1 {
+        return false;
+    }
+    //castling needs a free king step to the side, which was found above
+
+    let us = position.player_to_move;
+    let bbs = &position.bitboards;
+    let occupied = bbs.occupied();
+    let own = bbs.color(us);
+    for kind in [PieceKind::Knight, PieceKind::Bishop, PieceKind::Rook, PieceKind::Queen] {
+        for from64 in squares(bbs.piece(us, kind)) {
+            if piece_attacks(kind, from64, occupied) & !own & r.check_mask & r.pin_mask(from64) != 0 {
+                return true;
+            }
+        }
+    }
+
+    for from64 in squares(bbs.piece(us, PieceKind::Pawn)) {
+        buf.clear();
+        pawn::gen_pawn_moves(position, &mut buf, SQUARE64_TO_SQUARE120[from64]);
+        let allowed = r.check_mask & r.pin_mask(from64);
+        if buf.iter().any(|&mv| pawn_move_is_legal(position, &r, allowed, mv)) {
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movegen::{generate_pseudo_legal_moves, is_in_check};
+
+    fn legal(fen: &str) -> Vec<String> {
+        let pos = Position::from_fen(fen).unwrap();
+        let mut moves = Vec::new();
+        generate_legal_moves(&pos, &mut moves);
+        let mut uci: Vec<String> = moves.iter().map(|m| m.to_uci()).collect();
+        uci.sort();
+        uci
+    }
+
+    //the old way: make every pseudo legal move and look at the king
+    fn legal_by_make_unmake(pos: &mut Position) -> Vec<Move> {
+        let stm = pos.player_to_move;
+        generate_pseudo_legal_moves(pos)
+            .into_iter()
+            .filter(|&mv| {
+                let undo = pos.make_move_with_undo(mv);
+                let ok = !is_in_check(pos, stm);
+                pos.undo_move(undo);
+                ok
+            })
+            .collect()
+    }
+
+    fn assert_same_moves_in_tree(pos: &mut Position, depth: u32) {
+        let mut fast = Vec::new();
+        generate_legal_moves(pos, &mut fast);
+        let mut slow = legal_by_make_unmake(pos);
+
+        let key = |m: &Move| (m.from_sq(), m.to_sq(), m.to_uci());
+        fast.sort_by_key(key);
+        slow.sort_by_key(key);
+        assert_eq!(fast, slow, "fen={}", pos.to_fen());
+        assert_eq!(has_legal_move(pos), !slow.is_empty(), "fen={}", pos.to_fen());
+
+        //captures and quiets split the legal moves
+        let mut split = Vec::new();
+        generate_legal_captures(pos, &mut split);
+        let captures = split.len();
+        generate_legal_quiets(pos, &mut split);
+        assert!(split[..captures].iter().all(|m| !Gen::Quiets.wants(pos, *m)));
+        split.sort_by_key(key);
+        assert_eq!(split, slow, "fen={}", pos.to_fen());
+
+        //gives_check and the checks generator against making the move
+        let mut checks = Vec::new();
+        generate_legal_checks(pos, &mut checks);
+        for &mv in &slow {
+            let undo = pos.make_move_with_undo(mv);
+            let check = is_in_check(pos, pos.player_to_move);
+            pos.undo_move(undo);
+            assert_eq!(gives_check(pos, mv), check, "{} in {}", mv.to_uci(), pos.to_fen());
+            assert_eq!(checks.contains(&mv), check && Gen::Quiets.wants(pos, mv), "{} in {}", mv.to_uci(), pos.to_fen());
+        }
+
+        if depth > 1 {
+            for mv in slow {
+                let undo = pos.make_move_with_undo(mv);
+                assert_same_moves_in_tree(pos, depth - 1);
+                pos.undo_move(undo);
+            }
+        }
+    }
+
+    #[test]
+    fn matches_make_unmake_filter_on_tricky_positions() {
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            //discovered checks, castling into check, en passant check
+            "5k2/8/8/2N5/8/B2p4/4P3/R3K2R w KQ - 0 1",
+            "8/8/8/2k5/3Pp3/8/8/4K2Q b - d3 0 1",
+        ];
+        for fen in fens {
+            let mut pos = Position::from_fen(fen).unwrap();
+            assert_same_moves_in_tree(&mut pos, 2);
+        }
+    }
+
+    #[test]
+    fn is_legal_move_accepts_exactly_the_generated_moves() {
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "8/8/8/K2pP2r/8/8/8/7k w - d6 0 1",
+            "4k3/8/5N2/8/8/8/8/K3R3 b - - 0 1",
+        ];
+        let mut positions = Vec::new();
+        let mut candidates = Vec::new();
+        for fen in fens {
+            let mut pos = Position::from_fen(fen).unwrap();
+            let mut moves = Vec::new();
+            generate_legal_moves(&pos, &mut moves);
+            for mv in moves.clone() {
+                let undo = pos.make_move_with_undo(mv);
+                let mut replies = Vec::new();
+                generate_legal_moves(&pos, &mut replies);
+                candidates.extend(replies);
+                positions.push(pos.clone());
+                pos.undo_move(undo);
+            }
+            candidates.extend(moves);
+            positions.push(pos);
+        }
+        candidates.sort_by_key(|m| (m.from_sq(), m.to_sq(), m.to_uci()));
+        candidates.dedup();
+
+        //moves of related positions are the usual TT/killer candidates
+        for pos in &positions {
+            let mut legal = Vec::new();
+            generate_legal_moves(pos, &mut legal);
+            for &mv in &candidates {
+                assert_eq!(is_legal_move(pos, mv), legal.contains(&mv), "{} in {}", mv.to_uci(), pos.to_fen());
+            }
+        }
+    }
+
+    #[test]
+    fn en_passant_that_exposes_the_king_along_the_rank_is_illegal() {
+        //exd6 would remove both pawns between the king on a5 and the rook on h5
+        let moves = legal("8/8/8/K2pP2r/8/8/8/7k w - d6 0 1");
+        assert!(!moves.contains(&"e5d6".to_string()));
+        assert!(moves.contains(&"e5e6".to_string()));
+    }
+
+    #[test]
+    fn en_passant_can_capture_the_checking_pawn() {
+        //d7d5 gave check to the king on e4
+        let moves = legal("8/8/8/3pP3/4K3/8/8/7k w - d6 0 1");
+        assert!(moves.contains(&"e5d6".to_string()));
+    }
+
+    #[test]
+    fn pinned_piece_moves_only_along_the_pin() {
+        //rook e2 pinned by the rook on e8: can move on the e-file only
+        let moves = legal("k3r3/8/8/8/8/8/4R3/4K3 w - - 0 1");
+        let rook_moves: Vec<&String> = moves.iter().filter(|m| m.starts_with("e2")).collect();
+        assert_eq!(rook_moves.len(), 6);
+        assert!(rook_moves.iter().all(|m| m.as_bytes()[2] == b'e'));
+    }
+
+    #[test]
+    fn double_check_allows_only_king_moves() {
+        //knight f6 and rook e1 both check the king on e8
+        let moves = legal("4k3/8/5N2/8/8/8/8/K3R3 b - - 0 1");
+        assert!(!moves.is_empty());
+        assert!(moves.iter().all(|m| m.starts_with("e8")));
+    }
+
+    #[test]
+    fn king_cannot_step_back_along_the_checking_ray() {
+        let moves = legal("4k3/8/8/8/8/8/8/K3R3 b - - 0 1");
+        assert!(!moves.contains(&"e8e7".to_string()));
+        assert!(moves.contains(&"e8d7".to_string()));
+    }
+
+    #[test]
+    fn has_legal_move_detects_mate_and_stalemate() {
+        let mate = Position::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        assert!(!has_legal_move(&mate));
+        let stalemate = Position::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert!(!has_legal_move(&stalemate));
+        assert!(has_legal_move(&Position::starting_position()));
+    }
+}
@@ -1,156 +1,53 @@
 //Pawn specific movegen
 //here parameter square color and vector WE ABSOLUTELY NEED LAST MOVE FOR EN PASSANT
+use crate::board::bitboard::{RANK_1, RANK_2, RANK_7, RANK_8, pawn_attacks, square_bb, squares};
+use crate::board::mailbox120::{SQUARE64_TO_SQUARE120, SQUARE120_TO_SQUARE64};
 use crate::movegen::{Move, PromotionPiece};
 use crate::position::{Cell, Color, Position};
 
-const PAWN_START_WHITE: [usize; 8] = [31, 32, 33, 34, 35, 36, 37, 38];
-const PAWN_START_BLACK: [usize; 8] = [81, 82, 83, 84, 85, 86, 87, 88];
-const PROMOTION_RANK_WHITE: [usize; 8] = [91, 92, 93, 94, 95, 96, 97, 98];
-const PROMOTION_RANK_BLACK: [usize; 8] = [21, 22, 23, 24, 25, 26, 27, 28];
-
 pub fn gen_pawn_moves(position: &Position, moves: &mut Vec<Move>, square: usize) {
     let Cell::Piece(piece) = position.board[square] else {
         return;
     };
+    let sq64 = SQUARE120_TO_SQUARE64[square] as usize;
+    let bbs = &position.bitboards;
+    let occupied = bbs.occupied();
+
+    //push direction in square64 and mailbox120 steps
+    let (push64, push120, start_rank, promotion_rank) = match piece.color {
+        Color::White => (8, 10, RANK_2, RANK_8),
+        Color::Black => (-8, -10, RANK_7, RANK_1),
+    };
 
-    match piece.color {
-        Color::White => {
-            // Double push from starting position
-            if PAWN_START_WHITE.contains(&square) {
-                let one_forward = square + 10;
-                let two_forward = square + 20;
-
-                if two_forward < 120
-                    && position.board[one_forward] == Cell::Empty
-                    && position.board[two_forward] == Cell::Empty
-                {
-                    //uses now new_pawn_double for En-Passant-Square
-                    moves.push(Move::new_pawn_double(square, two_forward));
-                }
-            }
-
-            // Single push forward
-            let forward = square + 10;
-            if forward < 120 && position.board[forward] == Cell::Empty {
-                if PROMOTION_RANK_WHITE.contains(&forward) {
-                    gen_all_promotion_pieces(square, moves, 10);
-                } else {
-                    moves.push(Move::new(square, forward));
-                }
-            }
-
-            // Capture right (diagonal +11)
-            let capture_right = square as i32 + 11;
-            if capture_right >= 0 && capture_right < 120 {
-                let capture_right = capture_right as usize;
-                //Offboard-Check added
-                if position.board[capture_right] != Cell::Offboard {
-                    if let Cell::Piece(target) = position.board[capture_right] {
-                        if target.color == Color::Black {
-                            if PROMOTION_RANK_WHITE.contains(&capture_right) {
-                                gen_all_promotion_pieces(square, moves, 11);
-                            } else {
-                                moves.push(Move::new(square, capture_right));
-                            }
-                        }
-                    }
-                }
-            }
-
-            // Capture left (diagonal +9)
-            let capture_left = square as i32 + 9;
-            if capture_left >= 0 && capture_left < 120 {
-                let capture_left = capture_left as usize;
-                //Offboard-Check added
-                if position.board[capture_left] != Cell::Offboard {
-                    if let Cell::Piece(target) = position.board[capture_left] {
-                        if target.color == Color::Black {
-                            if PROMOTION_RANK_WHITE.contains(&capture_left) {
-                                gen_all_promotion_pieces(square, moves, 9);
-                            } else {
-                                moves.push(Move::new(square, capture_left));
-                            }
-                        }
-                    }
-                }
+    // Single push forward, double push from starting position
+    let forward = sq64 as i32 + push64;
+    if (0..64).contains(&forward) && occupied & square_bb(forward as usize) == 0 {
+        if square_bb(forward as usize) & promotion_rank != 0 {
+            gen_all_promotion_pieces(square, moves, push120);
+        } else {
+            moves.push(Move::new(square, (square as i32 + push120) as usize));
+
+            let two_forward = (forward + push64) as usize;
+            if square_bb(sq64) & start_rank != 0 && occupied & square_bb(two_forward) == 0 {
+                //uses now new_pawn_double for En-Passant-Square
+                moves.push(Move::new_pawn_double(square, (square as i32 + 2 * push120) as usize));
             }
-
-            // En-Passant moves added
-            en_passant_moves(position, moves, square);
         }
+    }
 
-        Color::Black => {
-            // Double push from starting position
-            if PAWN_START_BLACK.contains(&square) {
-                let one_forward = square as i32 - 10;
-                let two_forward = square as i32 - 20;
-
-                if one_forward >= 0 && two_forward >= 0 {
-                    let one_forward = one_forward as usize;
-                    let two_forward = two_forward as usize;
-
-                    if position.board[one_forward] == Cell::Empty
-                        && position.board[two_forward] == Cell::Empty
-                    {
-                        //uses new_pawn_double
-                        moves.push(Move::new_pawn_double(square, two_forward));
-                    }
-                }
-            }
-
-            // Single push forward
-            let forward = square as i32 - 10;
-            if forward >= 0 && forward < 120 {
-                let forward = forward as usize;
-                if position.board[forward] == Cell::Empty {
-                    if PROMOTION_RANK_BLACK.contains(&forward) {
-                        gen_all_promotion_pieces(square, moves, -10);
-                    } else {
-                        moves.push(Move::new(square, forward));
-                    }
-                }
-            }
-
-            // Capture right (diagonal -11)
-            let capture_right = square as i32 - 11;
-            if capture_right >= 0 && capture_right < 120 {
-                let capture_right = capture_right as usize;
-                //Offboard-Check added
-                if position.board[capture_right] != Cell::Offboard {
-                    if let Cell::Piece(target) = position.board[capture_right] {
-                        if target.color == Color::White {
-                            if PROMOTION_RANK_BLACK.contains(&capture_right) {
-                                gen_all_promotion_pieces(square, moves, -11);
-                            } else {
-                                moves.push(Move::new(square, capture_right));
-                            }
-                        }
-                    }
-                }
-            }
-
-            // Capture left (diagonal -9)
-            let capture_left = square as i32 - 9;
-            if capture_left >= 0 && capture_left < 120 {
-                let capture_left = capture_left as usize;
-                // Offboard-Check added
-                if position.board[capture_left] != Cell::Offboard {
-                    if let Cell::Piece(target) = position.board[capture_left] {
-                        if target.color == Color::White {
-                            if PROMOTION_RANK_BLACK.contains(&capture_left) {
-                                gen_all_promotion_pieces(square, moves, -9);
-                            } else {
-                                moves.push(Move::new(square, capture_left));
-                            }
-                        }
-                    }
-                }
-            }
-
-            //En-Passant Züge added
-            en_passant_moves(position, moves, square);
+    // Captures
+    let targets = pawn_attacks(piece.color, sq64) & bbs.color(piece.color.opposite());
+    for target64 in squares(targets) {
+        let target = SQUARE64_TO_SQUARE120[target64];
+        if square_bb(target64) & promotion_rank != 0 {
+            gen_all_promotion_pieces(square, moves, target as i32 - square as i32);
+        } else {
+            moves.push(Move::new(square, target));
         }
     }
+
+    // En-Passant moves added
+    en_passant_moves(position, moves, square);
 }
 
 pub fn en_passant_moves(
@@ -351,7 +248,7 @@ mod tests {
 
     // Puts a piece on a sq
     fn put(pos: &mut Position, s: usize, color: Color, kind: PieceKind) {
-        pos.board[s] = Cell::Piece(crate::position::Piece { color, kind });
+        pos.put_piece(s, crate::position::Piece { color, kind });
     }
 
     #[test]
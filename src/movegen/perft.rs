@@ -1,5 +1,8 @@
 //Perft
-use crate::movegen::{is_in_check, generate_pseudo_legal_moves_in_place, Move};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use crate::movegen::{generate_legal_moves, Move};
 use crate::position::Position;
 
 pub fn perft(position: &Position, depth: u32) -> u64 {
@@ -13,26 +16,137 @@ pub fn perft_mut(pos: &mut Position, depth: u32, buf: &mut Vec<Move>) -> u64 {
         return 1;
     }
 
-    let side_to_move = pos.player_to_move;
-
-    buf.clear();
-    generate_pseudo_legal_moves_in_place(pos, buf);
+    generate_legal_moves(pos, buf);
+    //bulk counting: the legal moves are the leaf nodes
+    if depth == 1 {
+        return buf.len() as u64;
+    }
 
     let mut nodes: u64 = 0;
 
     let moves: Vec<Move> = buf.clone();
     for mv in moves {
         let undo = pos.make_move_with_undo(mv);
+        nodes += perft_mut(pos, depth -1, buf);
+        pos.undo_move(undo);
+    }
+    nodes
+}
+
+//nodes below every root move, in move generation order
+pub fn perft_divide(position: &Position, depth: u32) -> Vec<(Move, u64)> {
+    let mut pos = position.clone();
+    let mut buf: Vec<Move> = Vec::with_capacity(256);
+    generate_legal_moves(&pos, &mut buf);
+    let moves = buf.clone();
+    moves
+        .into_iter()
+        .map(|mv| {
+            let undo = pos.make_move_with_undo(mv);
+            let nodes = perft_mut(&mut pos, depth.saturating_sub(1), &mut buf);
+            pos.undo_move(undo);
+            (mv, nodes)
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, Default)]
+struct PerftEntry {
+    key: u64,
+    depth: u32,
+    nodes: u64,
+}
+
+//node counts of subtrees by zobrist key and remaining depth, always replace
+pub struct PerftTable {
+    entries: Vec<PerftEntry>,
+    mask: usize,
+}
 
-        if !is_in_check(pos, side_to_move) {
-            nodes += perft_mut(pos, depth -1, buf);
-        }
+impl PerftTable {
+    pub fn new(mb: usize) -> Self {
+        let wanted = (mb.max(1) << 20) / std::mem::size_of::<PerftEntry>();
+        //power of two, rounded down
+        let len = 1usize << (usize::BITS - 1 - wanted.leading_zeros());
+        Self { entries: vec![PerftEntry::default(); len], mask: len - 1 }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.fill(PerftEntry::default());
+    }
+
+    fn probe(&self, key: u64, depth: u32) -> Option<u64> {
+        let e = self.entries[key as usize & self.mask];
+        (e.depth == depth && e.key == key).then_some(e.nodes)
+    }
+
+    fn store(&mut self, key: u64, depth: u32, nodes: u64) {
+        self.entries[key as usize & self.mask] = PerftEntry { key, depth, nodes };
+    }
+}
+
+pub fn perft_hashed(position: &Position, depth: u32, table: &mut PerftTable) -> u64 {
+    let mut pos = position.clone();
+    let mut buf: Vec<Move> = Vec::with_capacity(256);
+    perft_hashed_mut(&mut pos, depth, &mut buf, table)
+}
+
+fn perft_hashed_mut(pos: &mut Position, depth: u32, buf: &mut Vec<Move>, table: &mut PerftTable) -> u64 {
+    //depth 0 is never stored, so an empty entry can't match
+    if depth <= 1 {
+        return perft_mut(pos, depth, buf);
+    }
+    if let Some(nodes) = table.probe(pos.zobrist, depth) {
+        return nodes;
+    }
 
+    generate_legal_moves(pos, buf);
+    let mut nodes: u64 = 0;
+    let moves: Vec<Move> = buf.clone();
+    for mv in moves {
+        let undo = pos.make_move_with_undo(mv);
+        nodes += perft_hashed_mut(pos, depth - 1, buf, table);
         pos.undo_move(undo);
     }
+    table.store(pos.zobrist, depth, nodes);
     nodes
 }
 
+//root moves are handed out to the threads one at a time; hash_mb is split between
+//the threads (every thread has its own table), 0 searches without tables
+pub fn perft_parallel(position: &Position, depth: u32, threads: usize, hash_mb: usize) -> u64 {
+    if depth <= 1 {
+        return perft(position, depth);
+    }
+    let mut root: Vec<Move> = Vec::with_capacity(256);
+    generate_legal_moves(position, &mut root);
+    let next = AtomicUsize::new(0);
+    let threads = threads.clamp(1, root.len().max(1));
+
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut pos = position.clone();
+                    let mut buf: Vec<Move> = Vec::with_capacity(256);
+                    let mut table = (hash_mb > 0).then(|| PerftTable::new(hash_mb / threads));
+                    let mut nodes = 0;
+                    while let Some(&mv) = root.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        let undo = pos.make_move_with_undo(mv);
+                        nodes += match table.as_mut() {
+                            Some(table) => perft_hashed_mut(&mut pos, depth - 1, &mut buf, table),
+                            None => perft_mut(&mut pos, depth - 1, &mut buf),
+                        };
+                        pos.undo_move(undo);
+                    }
+                    nodes
+                })
+            })
+            .collect();
+        workers.into_iter().map(|w| w.join().expect("perft thread panicked")).sum()
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -70,10 +184,64 @@ mod tests {
         let pos = Position::starting_position();
         assert_eq!(perft(&pos, 5), 4_865_609);
     }
+    //en passant, pins along the rank
+    #[test]
+    fn position3_depth_four() {
+        let pos = Position::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap();
+        assert_eq!(perft(&pos, 4), 43_238);
+    }
+
+    //promotions with captures, castling through attacked squares
+    #[test]
+    fn position4_depth_three() {
+        let pos = Position::from_fen("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1").unwrap();
+        assert_eq!(perft(&pos, 3), 9_467);
+    }
+
+    #[test]
+    fn position5_depth_three() {
+        let pos = Position::from_fen("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8").unwrap();
+        assert_eq!(perft(&pos, 3), 62_379);
+    }
+
     #[test]
     #[ignore]
     fn kiwipete_depth_four() {
         let pos = Position::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
         assert_eq!(perft(&pos, 4), 4_085_603);
     }
+
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+    #[test]
+    fn divide_sums_to_perft() {
+        let pos = Position::from_fen(KIWIPETE).unwrap();
+        let divide = perft_divide(&pos, 3);
+        assert_eq!(divide.len(), 48);
+        assert_eq!(divide.iter().map(|&(_, n)| n).sum::<u64>(), 97_862);
+        let e2a6 = divide.iter().find(|(mv, _)| mv.to_uci() == "e2a6").unwrap();
+        assert_eq!(e2a6.1, 1_907);
+    }
+
+    #[test]
+    fn hashed_perft_matches_plain_perft() {
+        let mut table = PerftTable::new(1);
+        let pos = Position::from_fen(KIWIPETE).unwrap();
+        assert_eq!(perft_hashed(&pos, 3, &mut table), 97_862);
+        //second run is answered from the table
+        assert_eq!(perft_hashed(&pos, 3, &mut table), 97_862);
+
+        table.clear();
+        let pos = Position::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap();
+        assert_eq!(perft_hashed(&pos, 4, &mut table), 43_238);
+        assert_eq!(perft_hashed(&pos, 0, &mut table), 1);
+    }
+
+    #[test]
+    fn parallel_perft_matches_plain_perft() {
+        let pos = Position::from_fen("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8").unwrap();
+        assert_eq!(perft_parallel(&pos, 3, 4, 0), 62_379);
+        assert_eq!(perft_parallel(&pos, 3, 3, 2), 62_379);
+        assert_eq!(perft_parallel(&pos, 1, 4, 0), 44);
+    }
 }
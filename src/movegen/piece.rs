@@ -2,83 +2,24 @@
 
 //hier parameter square color offsets und der vector
 
+use crate::board::bitboard::{piece_attacks, squares};
+use crate::board::mailbox120::{SQUARE64_TO_SQUARE120, SQUARE120_TO_SQUARE64};
 use crate::movegen::Move;
 use crate::movegen::attack::is_square_attacked;
 use crate::position::position::PieceKind;
 use crate::position::{Cell, Color, Piece, Position, Square};
 
-pub fn gen_sliding_moves(
-    position: &Position,
-    moves: &mut Vec<Move>,
-    square: usize,
-    piece_offsets: &[i8],
-) {
-    let Cell::Piece(moving_piece) = position.board[square] else {
-        return;
-    }; // early return added 
-
-    for offset in piece_offsets {
-        let mut target = square as i32 + *offset as i32;
-
-        if target < 0 {
-            continue;
-        }
-
-        while target >= 0 && target < 120 {
-            let target_usize = target as usize;
-
-            match position.board[target_usize] {
-                Cell::Offboard => break,
-
-                Cell::Empty => {
-                    moves.push(Move::new(square, target_usize));
-                    target += *offset as i32;
-                }
-
-                Cell::Piece(target_piece) => {
-                    if target_piece.color != moving_piece.color {
-                        moves.push(Move::new(square, target_usize));
-                    }
-                    break;
-                }
-            }
-        }
-    }
-}
-
-//maybe could save lines here by checking for if not offboard instead of Cell::Empty and Piece
-pub fn gen_jumping_moves(
-    position: &Position,
-    moves: &mut Vec<Move>,
-    square: usize,
-    piece_offsets: &[i8],
-) {
+//quiet moves and captures of a knight, bishop, rook, queen or king (castling separately)
+pub fn gen_piece_moves(position: &Position, moves: &mut Vec<Move>, square: usize) {
     let Cell::Piece(moving_piece) = position.board[square] else {
         return;
     };
+    let bbs = &position.bitboards;
+    let sq64 = SQUARE120_TO_SQUARE64[square] as usize;
 
-    for offset in piece_offsets {
-        let target = square as i32 + *offset as i32;
-
-        if target < 0 || target >= 120 {
-            continue;
-        }
-
-        let target_usize = target as usize;
-
-        match position.board[target_usize] {
-            Cell::Offboard => continue,
-
-            Cell::Empty => {
-                moves.push(Move::new(square, target_usize));
-            }
-
-            Cell::Piece(target_piece) => {
-                if target_piece.color != moving_piece.color {
-                    moves.push(Move::new(square, target_usize));
-                }
-            }
-        }
+    let targets = piece_attacks(moving_piece.kind, sq64, bbs.occupied()) & !bbs.color(moving_piece.color);
+    for target64 in squares(targets) {
+        moves.push(Move::new(square, SQUARE64_TO_SQUARE120[target64]));
     }
 }
 
@@ -0,0 +1,375 @@
+//Standard Algebraic Notation (SAN) for Move
+//SAN depends on the position (piece letter, disambiguation, check suffix),
+//so both directions need the position the move is played from
+
+use crate::board::conversion::{
+    file_rank_from_square120, square120_from_string, square120_to_string,
+};
+use crate::movegen::attack::is_in_check;
+use crate::movegen::{Move, PromotionPiece, generate_legal_moves_in_place};
+use crate::position::{Cell, PieceKind, Position};
+
+impl Move {
+    //converts a legal move into SAN, e.g. "Nbd7", "exd6", "e8=Q+", "O-O-O#"
+    pub fn to_san(&self, pos: &Position) -> String {
+        let mut scratch = pos.clone();
+        let mut legal = Vec::new();
+        generate_legal_moves_in_place(&mut scratch, &mut legal);
+
+        let mut san = if self.is_castling() {
+            castling_san(*self).to_string()
+        } else {
+            piece_move_san(pos, *self, &legal)
+        };
+
+        //check / mate suffix
+        let undo = scratch.make_move_with_undo(*self);
+        let them = scratch.player_to_move;
+        if is_in_check(&scratch, them) {
+            generate_legal_moves_in_place(&mut scratch, &mut legal);
+            san.push(if legal.is_empty() { '#' } else { '+' });
+        }
+        scratch.undo_move(undo);
+
+        san
+    }
+
+    //parses SAN against the legal moves of pos, lenient about missing "x",
+    //"0-0" castling, missing "=" before the promotion piece and trailing annotations
+    pub fn from_san(pos: &Position, san: &str) -> Option<Self> {
+        let mut scratch = pos.clone();
+        let mut legal = Vec::new();
+        generate_legal_moves_in_place(&mut scratch, &mut legal);
+
+        let cleaned = strip_suffixes(san.trim());
+        if cleaned.is_empty() {
+            return None;
+        }
+
+        if let Some(kingside) = parse_castling(cleaned) {
+            return legal
+                .iter()
+                .copied()
+                .find(|m| m.is_castling() && is_kingside_castling(*m) == kingside);
+        }
+
+        let parsed = parse_san_body(cleaned)?;
+
+        let mut candidates = legal.iter().copied().filter(|m| {
+            let Cell::Piece(moving) = pos.board[m.from_sq()] else {
+                return false;
+            };
+            if m.is_castling() || moving.kind != parsed.kind || m.to_sq() != parsed.to {
+                return false;
+            }
+            let (from_file, from_rank) = file_rank_from_square120(m.from_sq());
+            if parsed.from_file.is_some_and(|f| f != from_file) {
+                return false;
+            }
+            if parsed.from_rank.is_some_and(|r| r != from_rank) {
+                return false;
+            }
+            m.promotion_piece() == parsed.promotion
+        });
+
+        let found = candidates.next()?;
+        //ambiguous input is rejected instead of guessing
+        if candidates.next().is_some() {
+            return None;
+        }
+        Some(found)
+    }
+}
+
+//SAN minus castling and check suffix
+fn piece_move_san(pos: &Position, mv: Move, legal: &[Move]) -> String {
+    let Cell::Piece(moving) = pos.board[mv.from_sq()] else {
+        debug_assert!(false, "to_san: from-square has no piece");
+        return mv.to_uci();
+    };
+
+    let is_capture = mv.is_en_passant() || matches!(pos.board[mv.to_sq()], Cell::Piece(_));
+    let to = square120_to_string(mv.to_sq()).unwrap_or_default();
+    let (from_file, from_rank) = file_rank_from_square120(mv.from_sq());
+    let mut san = String::new();
+
+    if moving.kind == PieceKind::Pawn {
+        if is_capture {
+            san.push(file_char(from_file));
+            san.push('x');
+        }
+        san.push_str(&to);
+        if let Some(promo) = mv.promotion_piece() {
+            san.push('=');
+            san.push(promo.to_uci_char().to_ascii_uppercase());
+        }
+        return san;
+    }
+
+    san.push(piece_letter(moving.kind));
+
+    //other pieces of the same kind that can reach the same square
+    let rivals: Vec<(u8, u8)> = legal
+        .iter()
+        .filter(|m| m.to_sq() == mv.to_sq() && m.from_sq() != mv.from_sq())
+        .filter(|m| matches!(pos.board[m.from_sq()], Cell::Piece(p) if p.kind == moving.kind))
+        .map(|m| file_rank_from_square120(m.from_sq()))
+        .collect();
+
+    if !rivals.is_empty() {
+        if rivals.iter().all(|&(file, _)| file != from_file) {
+            san.push(file_char(from_file));
+        } else if rivals.iter().all(|&(_, rank)| rank != from_rank) {
+            san.push(rank_char(from_rank));
+        } else {
+            san.push(file_char(from_file));
+            san.push(rank_char(from_rank));
+        }
+    }
+
+    if is_capture {
+        san.push('x');
+    }
+    san.push_str(&to);
+    san
+}
+
+fn castling_san(mv: Move) -> &'static str {
+    if is_kingside_castling(mv) {
+        "O-O"
+    } else {
+        "O-O-O"
+    }
+}
+
+fn is_kingside_castling(mv: Move) -> bool {
+    mv.to_sq() > mv.from_sq()
+}
+
+fn piece_letter(kind: PieceKind) -> char {
+    match kind {
+        PieceKind::Pawn => 'P',
+        PieceKind::Knight => 'N',
+        PieceKind::Bishop => 'B',
+        PieceKind::Rook => 'R',
+        PieceKind::Queen => 'Q',
+        PieceKind::King => 'K',
+    }
+}
+
+fn file_char(file: u8) -> char {
+    (b'a' + file) as char
+}
+
+fn rank_char(rank: u8) -> char {
+    (b'1' + rank) as char
+}
+
+//drops "+", "#", "!", "?" and "e.p." which carry no move information
+fn strip_suffixes(san: &str) -> &str {
+    let san = san.strip_suffix("e.p.").unwrap_or(san).trim_end();
+    san.trim_end_matches(['+', '#', '!', '?'])
+}
+
+//Some(true) = kingside, Some(false) = queenside
+fn parse_castling(san: &str) -> Option<bool> {
+    match san {
+        "O-O" | "0-0" | "o-o" => Some(true),
+        "O-O-O" | "0-0-0" | "o-o-o" => Some(false),
+        _ => None,
+    }
+}
+
+struct SanBody {
+    kind: PieceKind,
+    from_file: Option<u8>,
+    from_rank: Option<u8>,
+    to: usize,
+    promotion: Option<PromotionPiece>,
+}
+
+fn parse_san_body(san: &str) -> Option<SanBody> {
+    //separators carry no information, "x" is optional in lenient input
+    let mut chars: Vec<char> = san
+        .chars()
+        .filter(|c| !matches!(c, 'x' | 'X' | ':' | '-'))
+        .collect();
+
+    let kind = match chars.first()? {
+        'N' => PieceKind::Knight,
+        'B' => PieceKind::Bishop,
+        'R' => PieceKind::Rook,
+        'Q' => PieceKind::Queen,
+        'K' => PieceKind::King,
+        'P' => PieceKind::Pawn,
+        _ => {
+            chars.insert(0, 'P');
+            PieceKind::Pawn
+        }
+    };
+    chars.remove(0);
+
+    //promotion: "e8=Q", "e8Q" or "e8q"
+    let mut promotion = None;
+    if kind == PieceKind::Pawn && chars.len() >= 3 {
+        let last = *chars.last()?;
+        let promo = match last.to_ascii_lowercase() {
+            'n' => Some(PromotionPiece::Knight),
+            'b' => Some(PromotionPiece::Bishop),
+            'r' => Some(PromotionPiece::Rook),
+            'q' => Some(PromotionPiece::Queen),
+            _ => None,
+        };
+        //a lone lowercase 'b' could also be a file, only take it after a rank digit
+        if promo.is_some() && chars[chars.len() - 2].is_ascii_digit()
+            || chars[chars.len() - 2] == '='
+        {
+            promotion = Some(promo?);
+            chars.pop();
+            if chars.last() == Some(&'=') {
+                chars.pop();
+            }
+        }
+    }
+
+    if chars.len() < 2 || chars.len() > 4 {
+        return None;
+    }
+
+    let dest: String = chars[chars.len() - 2..].iter().collect();
+    let to = square120_from_string(&dest)?;
+
+    let mut from_file = None;
+    let mut from_rank = None;
+    for &c in &chars[..chars.len() - 2] {
+        match c {
+            'a'..='h' => from_file = Some(c as u8 - b'a'),
+            '1'..='8' => from_rank = Some(c as u8 - b'1'),
+            _ => return None,
+        }
+    }
+
+    Some(SanBody {
+        kind,
+        from_file,
+        from_rank,
+        to,
+        promotion,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn legal_uci(pos: &Position, uci: &str) -> Move {
+        let mut scratch = pos.clone();
+        let mut legal = Vec::new();
+        generate_legal_moves_in_place(&mut scratch, &mut legal);
+        legal
+            .into_iter()
+            .find(|m| m.to_uci() == uci)
+            .expect("move should be legal")
+    }
+
+    #[test]
+    fn pawn_piece_and_capture_moves() {
+        let pos =
+            Position::from_fen("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2")
+                .unwrap();
+
+        assert_eq!(legal_uci(&pos, "e4e5").to_san(&pos), "e5");
+        assert_eq!(legal_uci(&pos, "e4d5").to_san(&pos), "exd5");
+        assert_eq!(legal_uci(&pos, "g1f3").to_san(&pos), "Nf3");
+        assert_eq!(legal_uci(&pos, "f1b5").to_san(&pos), "Bb5+");
+    }
+
+    #[test]
+    fn disambiguation_by_file_rank_and_square() {
+        //knights on b1 and f3 (file), rooks on a1 and a5 (rank)
+        let pos = Position::from_fen("7k/8/8/R7/8/5N2/8/RN2K3 w - - 0 1").unwrap();
+        assert_eq!(legal_uci(&pos, "b1d2").to_san(&pos), "Nbd2");
+        assert_eq!(legal_uci(&pos, "a1a3").to_san(&pos), "R1a3");
+
+        //queens on a4, h4 and h1 all reach e4 (square)
+        let queens = Position::from_fen("2k5/8/8/8/Q6Q/8/8/K6Q w - - 0 1").unwrap();
+        assert_eq!(legal_uci(&queens, "h4e4").to_san(&queens), "Qh4e4");
+        assert_eq!(legal_uci(&queens, "a4e4").to_san(&queens), "Qae4");
+    }
+
+    #[test]
+    fn castling_promotion_en_passant_and_mate() {
+        let pos = Position::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(legal_uci(&pos, "e1g1").to_san(&pos), "O-O");
+        assert_eq!(legal_uci(&pos, "e1c1").to_san(&pos), "O-O-O");
+
+        let promo = Position::from_fen("1n5k/P7/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        assert_eq!(legal_uci(&promo, "a7a8q").to_san(&promo), "a8=Q");
+        assert_eq!(legal_uci(&promo, "a7b8n").to_san(&promo), "axb8=N");
+
+        let ep = Position::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        assert_eq!(legal_uci(&ep, "e5d6").to_san(&ep), "exd6");
+
+        let mate = Position::from_fen("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1").unwrap();
+        assert_eq!(legal_uci(&mate, "a1a8").to_san(&mate), "Ra8#");
+    }
+
+    #[test]
+    fn from_san_roundtrips_every_legal_move() {
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "7k/8/8/R7/8/5N2/8/RN2K3 w - - 0 1",
+            "2k5/8/8/8/Q6Q/8/8/K6Q w - - 0 1",
+            "1n5k/P7/8/8/8/8/8/K7 w - - 0 1",
+        ];
+        for fen in fens {
+            let pos = Position::from_fen(fen).unwrap();
+            let mut scratch = pos.clone();
+            let mut legal = Vec::new();
+            generate_legal_moves_in_place(&mut scratch, &mut legal);
+
+            for mv in legal {
+                let san = mv.to_san(&pos);
+                assert_eq!(Move::from_san(&pos, &san), Some(mv), "{fen}: {san}");
+            }
+        }
+    }
+
+    #[test]
+    fn from_san_is_lenient() {
+        let pos =
+            Position::from_fen("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2")
+                .unwrap();
+        assert_eq!(Move::from_san(&pos, "ed5"), Some(legal_uci(&pos, "e4d5")));
+        assert_eq!(Move::from_san(&pos, "Bb5!?"), Some(legal_uci(&pos, "f1b5")));
+
+        let castle = Position::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(
+            Move::from_san(&castle, "0-0"),
+            Some(legal_uci(&castle, "e1g1"))
+        );
+        assert_eq!(
+            Move::from_san(&castle, "O-O-O+"),
+            Some(legal_uci(&castle, "e1c1"))
+        );
+
+        let promo = Position::from_fen("1n5k/P7/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        assert_eq!(
+            Move::from_san(&promo, "a8Q"),
+            Some(legal_uci(&promo, "a7a8q"))
+        );
+        assert_eq!(
+            Move::from_san(&promo, "axb8=R"),
+            Some(legal_uci(&promo, "a7b8r"))
+        );
+    }
+
+    #[test]
+    fn from_san_rejects_illegal_and_ambiguous_input() {
+        let pos = Position::from_fen("7k/8/8/R7/8/5N2/8/RN2K3 w - - 0 1").unwrap();
+        assert_eq!(Move::from_san(&pos, "Nd2"), None); //two knights can go there
+        assert_eq!(Move::from_san(&pos, "Ke5"), None); //king can't reach e5
+        assert_eq!(Move::from_san(&pos, ""), None);
+        assert_eq!(Move::from_san(&pos, "Zz9"), None);
+    }
+}
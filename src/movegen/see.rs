@@ -0,0 +1,219 @@
+//Static exchange evaluation: material outcome of the capture sequence on the target
+//square of a move, both sides always recapturing with their least valuable piece
+//and free to stop when going on would lose material
+
+use crate::board::bitboard::{Bitboard, bishop_attacks, rook_attacks, square_bb};
+use crate::board::mailbox120::SQUARE120_TO_SQUARE64;
+use crate::movegen::Move;
+use crate::movegen::attack::attackers_bb;
+use crate::position::position::PieceKind;
+use crate::position::{Cell, Color, Position};
+
+//exchange values, the king is worth more than everything else together
+pub const SEE_VALUES: [i32; 6] = [100, 320, 330, 500, 900, 20000];
+
+//least valuable first
+const KINDS: [PieceKind; 6] = [
+    PieceKind::Pawn,
+    PieceKind::Knight,
+    PieceKind::Bishop,
+    PieceKind::Rook,
+    PieceKind::Queen,
+    PieceKind::King,
+];
+
+#[inline]
+pub fn see_value(kind: PieceKind) -> i32 {
+    SEE_VALUES[kind.idx()]
+}
+
+//material won (negative: lost) by mv and the exchange it starts, in centipawns
+pub fn see(position: &Position, mv: Move) -> i32 {
+    if mv.is_castling() {
+        return 0;
+    }
+    let Cell::Piece(mover) = position.board[mv.from_sq()] else {
+        return 0;
+    };
+    let bbs = &position.bitboards;
+    let from64 = SQUARE120_TO_SQUARE64[mv.from_sq()] as usize;
+    let to64 = SQUARE120_TO_SQUARE64[mv.to_sq()] as usize;
+
+    //gain[d]: what the side making capture d wins if the exchange stops after it
+    let mut gain = [0i32; 32];
+    let mut occupied = bbs.occupied() ^ square_bb(from64);
+
+    gain[0] = match position.board[mv.to_sq()] {
+        Cell::Piece(victim) => see_value(victim.kind),
+        _ => 0,
+    };
+    if mv.is_en_passant() {
+        let captured64 = match mover.color {
+            Color::White => to64 - 8,
+            Color::Black => to64 + 8,
+        };
+        occupied ^= square_bb(captured64);
+        gain[0] = see_value(PieceKind::Pawn);
+    }
+    //the piece standing on the target square after the capture
+    let mut on_target = see_value(mover.kind);
+    if let Some(promotion) = mv.promotion {
+        let promoted = see_value(promotion.to_piece_kind());
+        gain[0] += promoted - see_value(PieceKind::Pawn);
+        on_target = promoted;
+    }
+
+    let diagonal = bbs.kind(PieceKind::Bishop) | bbs.kind(PieceKind::Queen);
+    let straight = bbs.kind(PieceKind::Rook) | bbs.kind(PieceKind::Queen);
+    let mut attackers = all_attackers(position, to64, occupied);
+    let mut side = mover.color.opposite();
+    let mut depth = 0;
+
+    loop {
+        let own = attackers & occupied & bbs.color(side);
+        let Some((kind, sq_bb)) = least_valuable(position, own, side) else {
+            break;
+        };
+        if kind == PieceKind::King && attackers & occupied & bbs.color(side.opposite()) != 0 {
+            //the king can't take a defended piece
+            break;
+        }
+
+        depth += 1;
+        gain[depth] = on_target - gain[depth - 1];
+        on_target = see_value(kind);
+        if depth == gain.len() - 1 {
+            break;
+        }
+
+        //x-rays: sliders behind the piece that just moved join in
+        occupied ^= sq_bb;
+        if matches!(kind, PieceKind::Pawn | PieceKind::Bishop | PieceKind::Queen) {
+            attackers |= bishop_attacks(to64, occupied) & diagonal;
+        }
+        if matches!(kind, PieceKind::Rook | PieceKind::Queen) {
+            attackers |= rook_attacks(to64, occupied) & straight;
+        }
+        side = side.opposite();
+    }
+
+    while depth > 0 {
+        gain[depth - 1] = -(-gain[depth - 1]).max(gain[depth]);
+        depth -= 1;
+    }
+    gain[0]
+}
+
+//see(position, mv) >= threshold, without the exchange when the first capture decides it
+pub fn see_ge(position: &Position, mv: Move, threshold: i32) -> bool {
+    if mv.is_castling() || mv.is_promotion() {
+        return see(position, mv) >= threshold;
+    }
+    let victim = match position.board[mv.to_sq()] {
+        Cell::Piece(victim) => see_value(victim.kind),
+        _ if mv.is_en_passant() => see_value(PieceKind::Pawn),
+        _ => 0,
+    };
+    //can't win more than the captured piece
+    if victim < threshold {
+        return false;
+    }
+    //even losing the moving piece for it is enough
+    if let Cell::Piece(mover) = position.board[mv.from_sq()]
+        && victim - see_value(mover.kind) >= threshold
+    {
+        return true;
+    }
+    see(position, mv) >= threshold
+}
+
+#[inline]
+fn all_attackers(position: &Position, sq64: usize, occupied: Bitboard) -> Bitboard {
+    attackers_bb(position, sq64, Color::White, occupied) | attackers_bb(position, sq64, Color::Black, occupied)
+}
+
+#[inline]
+fn least_valuable(position: &Position, attackers: Bitboard, color: Color) -> Option<(PieceKind, Bitboard)> {
+    KINDS.into_iter().find_map(|kind| {
+        let bb = attackers & position.bitboards.piece(color, kind);
+        (bb != 0).then(|| (kind, bb & bb.wrapping_neg()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn see_uci(fen: &str, mv: &str) -> i32 {
+        let pos = Position::from_fen(fen).unwrap();
+        let mut moves = Vec::new();
+        crate::movegen::generate_legal_moves(&pos, &mut moves);
+        let mv = *moves
+            .iter()
+            .find(|m| m.to_uci() == mv)
+            .unwrap_or_else(|| panic!("{mv} not legal in {fen}"));
+        see(&pos, mv)
+    }
+
+    #[test]
+    fn undefended_piece_is_won() {
+        assert_eq!(see_uci("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "e1e5"), 100);
+    }
+
+    #[test]
+    fn defended_pawn_costs_the_knight() {
+        //Nxe5 Nxe5: rook and queen can take back, but bishop and queen defend as well
+        assert_eq!(
+            see_uci("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1", "d3e5"),
+            100 - 320
+        );
+    }
+
+    #[test]
+    fn xray_behind_the_capturer_counts() {
+        //Rxd5 Rxd5 Rxd5: the second white rook recaptures through the first
+        assert_eq!(see_uci("3r2k1/8/8/3p4/8/8/3R4/3R2K1 w - - 0 1", "d2d5"), 100);
+        //without the second rook the exchange loses the rook for a pawn
+        assert_eq!(see_uci("3r2k1/8/8/3p4/8/8/3R4/6K1 w - - 0 1", "d2d5"), 100 - 500);
+    }
+
+    #[test]
+    fn order_of_the_attackers_matters() {
+        //Qxd5 Rxd5 Rxd5 loses the queen for knight and rook
+        assert_eq!(see_uci("3r2k1/8/8/3n4/8/8/3Q4/3R2K1 w - - 0 1", "d2d5"), 320 - 900 + 500);
+        //Rxd5 first: recapturing only loses the black rook as well
+        assert_eq!(see_uci("3r2k1/8/8/3n4/8/8/3R4/3Q2K1 w - - 0 1", "d2d5"), 320);
+    }
+
+    #[test]
+    fn king_does_not_recapture_defended_piece() {
+        //Rxe7: the king can't take back because the bishop on b4 covers e7
+        assert_eq!(see_uci("4k3/4n3/8/8/1B6/8/8/4R1K1 w - - 0 1", "e1e7"), 320);
+        assert_eq!(see_uci("4k3/4n3/8/8/8/8/8/4R1K1 w - - 0 1", "e1e7"), 320 - 500);
+    }
+
+    #[test]
+    fn quiet_move_to_attacked_square_loses_the_piece() {
+        assert_eq!(see_uci("4k3/8/8/8/2p5/8/8/2N1K3 w - - 0 1", "c1e2"), 0);
+        assert_eq!(see_uci("4k3/8/8/8/2p5/8/8/2N1K3 w - - 0 1", "c1d3"), -320);
+    }
+
+    #[test]
+    fn see_ge_matches_see() {
+        let cases = [
+            ("3r2k1/8/8/3p4/8/8/3R4/6K1 w - - 0 1", "d2d5"),
+            ("3r2k1/8/8/3p4/8/8/3R4/3R2K1 w - - 0 1", "d2d5"),
+            ("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "e1e5"),
+            ("4k3/8/8/8/2p5/8/8/2N1K3 w - - 0 1", "c1d3"),
+            ("3r2k1/8/8/3n4/8/8/3Q4/3R2K1 w - - 0 1", "d2d5"),
+        ];
+        for (fen, uci) in cases {
+            let pos = Position::from_fen(fen).unwrap();
+            let value = see_uci(fen, uci);
+            let mv = Move::from_uci(uci).unwrap();
+            for threshold in [-500, -320, -1, 0, 1, 100, 101, 500] {
+                assert_eq!(see_ge(&pos, mv, threshold), value >= threshold, "{fen} {uci} {threshold}");
+            }
+        }
+    }
+}
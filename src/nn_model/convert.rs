@@ -0,0 +1,61 @@
+//the burn MLP as a plain rust Network and back, so burn models can be stored in the
+//binary network format (format.rs)
+
+use std::path::Path;
+
+use burn::module::Param;
+use burn::nn::Linear;
+use burn::tensor::backend::Backend;
+use burn::tensor::{Tensor, TensorData};
+
+use super::format::{NetworkFileError, load_network, save_network};
+use super::mlp_structure::MLP;
+use crate::evaluation::accumulator::{Dense, FEATURES, Network};
+
+fn dense<B: Backend>(layer: &Linear<B>) -> Dense {
+    let [inputs, outputs] = layer.weight.val().dims();
+    let weights = layer.weight.val().into_data().iter::<f32>().collect();
+    let bias = match &layer.bias {
+        Some(bias) => bias.val().into_data().iter::<f32>().collect(),
+        None => vec![0.0; outputs],
+    };
+    Dense::new(inputs, outputs, weights, bias).expect("tensor data matches its dims")
+}
+
+//row layout ([input][output]), the LinearConfig default
+fn linear<B: Backend>(dense: &Dense, device: &B::Device) -> Linear<B> {
+    let weight = TensorData::new(dense.weights().to_vec(), [dense.inputs(), dense.outputs()]);
+    let bias = TensorData::new(dense.bias().to_vec(), [dense.outputs()]);
+    Linear {
+        weight: Param::from_tensor(Tensor::from_data(weight, device)),
+        bias: Some(Param::from_tensor(Tensor::from_data(bias, device))),
+    }
+}
+
+impl<B: Backend> MLP<B> {
+    pub fn to_network(&self) -> Result<Network, NetworkFileError> {
+        let layers = [dense(&self.fc1), dense(&self.fc2), dense(&self.fc3)];
+        let found = std::iter::once(FEATURES).chain(layers.iter().map(Dense::outputs)).collect();
+        let [fc1, fc2, fc3] = layers;
+        Network::new(fc1, vec![fc2, fc3]).ok_or(NetworkFileError::InvalidLayerSizes { found })
+    }
+
+    //hidden sizes come from the network, not from MLP::new
+    pub fn from_network(net: &Network, device: &B::Device) -> Result<Self, NetworkFileError> {
+        let layers: Vec<&Dense> = net.layers().collect();
+        let [fc1, fc2, fc3] = layers.as_slice() else {
+            return Err(NetworkFileError::LayerCountMismatch { expected: 3, found: layers.len() });
+        };
+        Ok(Self { fc1: linear(fc1, device), fc2: linear(fc2, device), fc3: linear(fc3, device) })
+    }
+
+    pub fn save_network<P: AsRef<Path>>(&self, path: P) -> Result<(), NetworkFileError> {
+        save_network(path, &self.to_network()?)?;
+        Ok(())
+    }
+
+    pub fn load_network<P: AsRef<Path>>(path: P, device: &B::Device) -> Result<Self, NetworkFileError> {
+        let (_, net) = load_network(path)?;
+        Self::from_network(&net, device)
+    }
+}
@@ -0,0 +1,319 @@
+//binary network files (.nn), written by the trainer and read by the engine without burn.
+//
+//little endian: magic "RCNN", version, feature set id, activation id, output scale (f32),
+//number of layer sizes, the sizes (inputs first, 1 last), then per layer the weights
+//(f32, input-major) and the biases (f32), and at the end a FNV-1a 64 checksum of
+//everything before it
+
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use crate::evaluation::accumulator::{AccumulatedNet, Dense, FEATURE_SET_ID, FEATURES, Network};
+use crate::evaluation::quantized::fnv1a;
+
+pub const MAGIC: &[u8; 4] = b"RCNN";
+const VERSION: u32 = 1;
+const CHECKSUM_SIZE: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Activation {
+    //clamp to [0, 1] and square (MLP::sc_relu)
+    ScRelu,
+}
+
+impl Activation {
+    fn id(self) -> u32 {
+        match self {
+            Activation::ScRelu => 1,
+        }
+    }
+
+    fn from_id(id: u32) -> Option<Self> {
+        match id {
+            1 => Some(Activation::ScRelu),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NetworkHeader {
+    pub feature_set: u32,
+    pub activation: Activation,
+    pub output_scale: f32,
+    //inputs of the first layer, then the outputs of every layer
+    pub layer_sizes: Vec<usize>,
+}
+
+#[derive(Debug)]
+pub enum NetworkFileError {
+    Io(io::Error),
+    NotANetworkFile,
+    //the checksum passed but the header runs past the end
+    TruncatedHeader,
+    UnsupportedVersion { found: u32 },
+    ChecksumMismatch { stored: u64, computed: u64 },
+    FeatureSetMismatch { expected: u32, found: u32 },
+    UnsupportedActivation { found: u32 },
+    //must start with the feature count and end with a single output
+    InvalidLayerSizes { found: Vec<usize> },
+    //payload size doesn't match the layer sizes
+    SizeMismatch { expected: usize, found: usize },
+    //the model this is loaded into has another number of layers
+    LayerCountMismatch { expected: usize, found: usize },
+}
+
+impl fmt::Display for NetworkFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkFileError::Io(err) => write!(f, "{err}"),
+            NetworkFileError::NotANetworkFile => write!(f, "not a network file (bad magic)"),
+            NetworkFileError::TruncatedHeader => write!(f, "network file ends inside its header"),
+            NetworkFileError::UnsupportedVersion { found } => {
+                write!(f, "unsupported network file version {found}, expected {VERSION}")
+            }
+            NetworkFileError::ChecksumMismatch { stored, computed } => {
+                write!(f, "checksum mismatch: stored {stored:016x}, computed {computed:016x}")
+            }
+            NetworkFileError::FeatureSetMismatch { expected, found } => {
+                write!(f, "network was trained on feature set {found}, the engine uses {expected}")
+            }
+            NetworkFileError::UnsupportedActivation { found } => write!(f, "unsupported activation id {found}"),
+            NetworkFileError::InvalidLayerSizes { found } => {
+                write!(f, "layer sizes {found:?} must start with {FEATURES} inputs and end with 1 output")
+            }
+            NetworkFileError::SizeMismatch { expected, found } => {
+                write!(f, "weights take {found} bytes, the layer sizes need {expected}")
+            }
+            NetworkFileError::LayerCountMismatch { expected, found } => {
+                write!(f, "network has {found} layers, the model {expected}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for NetworkFileError {}
+
+impl From<io::Error> for NetworkFileError {
+    fn from(err: io::Error) -> Self {
+        NetworkFileError::Io(err)
+    }
+}
+
+//checks the magic only, to tell network files from the json records
+pub fn is_network_file<P: AsRef<Path>>(path: P) -> io::Result<bool> {
+    let mut magic = [0; 4];
+    match fs::File::open(path)?.read_exact(&mut magic) {
+        Ok(()) => Ok(&magic == MAGIC),
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+pub fn header_of(net: &Network) -> NetworkHeader {
+    let mut layer_sizes = vec![FEATURES];
+    layer_sizes.extend(net.layers().map(Dense::outputs));
+    NetworkHeader {
+        feature_set: FEATURE_SET_ID,
+        activation: Activation::ScRelu,
+        output_scale: net.output_scale(),
+        layer_sizes,
+    }
+}
+
+pub fn write_network<W: Write>(out: &mut W, net: &Network) -> io::Result<()> {
+    let header = header_of(net);
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(MAGIC);
+    for v in [VERSION, header.feature_set, header.activation.id()] {
+        bytes.extend_from_slice(&v.to_le_bytes());
+    }
+    bytes.extend_from_slice(&header.output_scale.to_le_bytes());
+    bytes.extend_from_slice(&(header.layer_sizes.len() as u32).to_le_bytes());
+    for &size in &header.layer_sizes {
+        bytes.extend_from_slice(&(size as u32).to_le_bytes());
+    }
+    for layer in net.layers() {
+        for &v in layer.weights().iter().chain(layer.bias()) {
+            bytes.extend_from_slice(&v.to_le_bytes());
+        }
+    }
+    let checksum = fnv1a(&bytes);
+    bytes.extend_from_slice(&checksum.to_le_bytes());
+    out.write_all(&bytes)
+}
+
+pub fn save_network<P: AsRef<Path>>(path: P, net: &Network) -> io::Result<()> {
+    let mut bytes = Vec::new();
+    write_network(&mut bytes, net)?;
+    fs::write(path, bytes)
+}
+
+pub fn read_network<R: Read>(input: &mut R) -> Result<(NetworkHeader, Network), NetworkFileError> {
+    let mut bytes = Vec::new();
+    input.read_to_end(&mut bytes)?;
+    parse_network(&bytes)
+}
+
+pub fn load_network<P: AsRef<Path>>(path: P) -> Result<(NetworkHeader, Network), NetworkFileError> {
+    parse_network(&fs::read(path)?)
+}
+
+//little endian reader over the checked part of the file
+struct Cursor<'a> {
+    bytes: &'a [u8],
+}
+
+impl Cursor<'_> {
+    fn u32(&mut self) -> Result<u32, NetworkFileError> {
+        let (head, rest) = self.bytes.split_at_checked(4).ok_or(NetworkFileError::TruncatedHeader)?;
+        self.bytes = rest;
+        Ok(u32::from_le_bytes(head.try_into().unwrap()))
+    }
+}
+
+fn parse_network(bytes: &[u8]) -> Result<(NetworkHeader, Network), NetworkFileError> {
+    if bytes.len() < MAGIC.len() + 4 + CHECKSUM_SIZE || &bytes[..4] != MAGIC {
+        return Err(NetworkFileError::NotANetworkFile);
+    }
+    let mut cursor = Cursor { bytes: &bytes[4..bytes.len() - CHECKSUM_SIZE] };
+    let version = cursor.u32()?;
+    if version != VERSION {
+        return Err(NetworkFileError::UnsupportedVersion { found: version });
+    }
+    let (body, stored) = bytes.split_at(bytes.len() - CHECKSUM_SIZE);
+    let stored = u64::from_le_bytes(stored.try_into().unwrap());
+    let computed = fnv1a(body);
+    if stored != computed {
+        return Err(NetworkFileError::ChecksumMismatch { stored, computed });
+    }
+
+    let feature_set = cursor.u32()?;
+    if feature_set != FEATURE_SET_ID {
+        return Err(NetworkFileError::FeatureSetMismatch { expected: FEATURE_SET_ID, found: feature_set });
+    }
+    let activation_id = cursor.u32()?;
+    let activation =
+        Activation::from_id(activation_id).ok_or(NetworkFileError::UnsupportedActivation { found: activation_id })?;
+    let output_scale = f32::from_bits(cursor.u32()?);
+    let count = cursor.u32()? as usize;
+    let mut layer_sizes = Vec::with_capacity(count.min(64));
+    for _ in 0..count {
+        layer_sizes.push(cursor.u32()? as usize);
+    }
+    if layer_sizes.len() < 2 || layer_sizes[0] != FEATURES || layer_sizes.last() != Some(&1) {
+        return Err(NetworkFileError::InvalidLayerSizes { found: layer_sizes });
+    }
+
+    //saturating, the sizes aren't trusted yet
+    let floats = layer_sizes.windows(2).map(|w| w[0].saturating_mul(w[1]).saturating_add(w[1]));
+    let expected = floats.fold(0usize, usize::saturating_add).saturating_mul(4);
+    if cursor.bytes.len() != expected {
+        return Err(NetworkFileError::SizeMismatch { expected, found: cursor.bytes.len() });
+    }
+    let mut values = cursor.bytes.chunks_exact(4).map(|b| f32::from_le_bytes(b.try_into().unwrap()));
+    let mut layers: Vec<Dense> = layer_sizes
+        .windows(2)
+        .map(|w| {
+            let weights = values.by_ref().take(w[0] * w[1]).collect();
+            let bias = values.by_ref().take(w[1]).collect();
+            Dense::new(w[0], w[1], weights, bias).expect("sizes checked above")
+        })
+        .collect();
+    let first = layers.remove(0);
+    let network = Network::new(first, layers).expect("sizes checked above").with_output_scale(output_scale);
+    Ok((NetworkHeader { feature_set, activation, output_scale, layer_sizes }, network))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::accumulator::Accumulator;
+    use crate::position::Position;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::sync::Arc;
+
+    fn random_network() -> Network {
+        let mut rng = StdRng::seed_from_u64(11);
+        let mut dense = |inputs: usize, outputs: usize| {
+            let weights = (0..inputs * outputs).map(|_| rng.gen_range(-0.2..0.2)).collect();
+            let bias = (0..outputs).map(|_| rng.gen_range(-0.2..0.2)).collect();
+            Dense::new(inputs, outputs, weights, bias).unwrap()
+        };
+        let first = dense(FEATURES, 8);
+        let rest = vec![dense(8, 4), dense(4, 1)];
+        Network::new(first, rest).unwrap().with_output_scale(550.0)
+    }
+
+    fn bytes_of(net: &Network) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_network(&mut bytes, net).unwrap();
+        bytes
+    }
+
+    fn reseal(bytes: &mut Vec<u8>) {
+        bytes.truncate(bytes.len() - CHECKSUM_SIZE);
+        let checksum = fnv1a(bytes);
+        bytes.extend_from_slice(&checksum.to_le_bytes());
+    }
+
+    #[test]
+    fn round_trip_keeps_header_and_weights() {
+        let net = random_network();
+        let (header, loaded) = read_network(&mut bytes_of(&net).as_slice()).unwrap();
+        assert_eq!(header, header_of(&net));
+        assert_eq!(header.layer_sizes, vec![FEATURES, 8, 4, 1]);
+        assert_eq!(loaded.output_scale(), 550.0);
+        for (a, b) in net.layers().zip(loaded.layers()) {
+            assert_eq!((a.weights(), a.bias()), (b.weights(), b.bias()));
+        }
+        let pos = Position::starting_position();
+        assert_eq!(
+            Accumulator::evaluate_position(&Arc::new(net), &pos),
+            Accumulator::evaluate_position(&Arc::new(loaded), &pos)
+        );
+    }
+
+    #[test]
+    fn mismatches_are_reported() {
+        let good = bytes_of(&random_network());
+        let read = |bytes: &[u8]| read_network(&mut &bytes[..]).map(|_| ()).unwrap_err();
+
+        let mut flipped = good.clone();
+        flipped[100] ^= 1;
+        assert!(matches!(read(&flipped), NetworkFileError::ChecksumMismatch { .. }));
+
+        assert!(matches!(read(b"{\"fc1\": {}}"), NetworkFileError::NotANetworkFile));
+
+        let mut version = good.clone();
+        version[4] = 9;
+        assert!(matches!(read(&version), NetworkFileError::UnsupportedVersion { found: 9 }));
+
+        //resealed so the header checks are reached
+        let mut features = good.clone();
+        features[8] = 7;
+        reseal(&mut features);
+        let err = read(&features);
+        assert!(matches!(err, NetworkFileError::FeatureSetMismatch { expected: FEATURE_SET_ID, found: 7 }));
+        assert_eq!(err.to_string(), "network was trained on feature set 7, the engine uses 1");
+
+        let mut activation = good.clone();
+        activation[12] = 3;
+        reseal(&mut activation);
+        assert!(matches!(read(&activation), NetworkFileError::UnsupportedActivation { found: 3 }));
+
+        //sealed, but cut before the layer count
+        let mut header = good[..20].to_vec();
+        header.extend_from_slice(&[0; CHECKSUM_SIZE]);
+        reseal(&mut header);
+        assert!(matches!(read(&header), NetworkFileError::TruncatedHeader));
+
+        let mut short = good[..good.len() - CHECKSUM_SIZE - 4].to_vec();
+        short.extend_from_slice(&[0; CHECKSUM_SIZE]);
+        reseal(&mut short);
+        assert!(matches!(read(&short), NetworkFileError::SizeMismatch { .. }));
+    }
+}
@@ -1,3 +1,6 @@
 // place-holder
+pub mod format;
 #[cfg(feature = "nn")]
-pub mod mlp_structure;
\ No newline at end of file
+pub mod convert;
+#[cfg(feature = "nn")]
+pub mod mlp_structure;
@@ -1,5 +1,6 @@
 use crate::board::conversion::{square120_from_string, square120_to_string};
 use crate::board::mailbox120::{is_on_board, square120_from_file_rank};
+use crate::movegen::Move;
 use crate::position::{Cell, Color, Piece, PieceKind, Position, Square};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -19,6 +20,34 @@ pub enum FenError {
     InvalidFullmove,
 
     InvalidKingCount { color: Color, found: usize },
+
+    //epd errors
+    InvalidEpdOperation { opcode: String },
+    InvalidEpdMove { san: String },
+}
+
+//one epd record: the first four fen fields and the operations behind them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Epd {
+    pub position: Position,
+    pub id: Option<String>,
+    //c0
+    pub comment: Option<String>,
+    //bm and am, resolved against the position
+    pub best_moves: Vec<Move>,
+    pub avoid_moves: Vec<Move>,
+    //dm: mate in this many moves
+    pub direct_mate: Option<u32>,
+    //acd: analysis depth
+    pub depth: Option<u32>,
+    //all operations as (opcode, operands) in file order, including the ones above
+    pub operations: Vec<(String, Vec<String>)>,
+}
+
+impl Epd {
+    pub fn operation(&self, opcode: &str) -> Option<&[String]> {
+        self.operations.iter().find(|(op, _)| op == opcode).map(|(_, operands)| operands.as_slice())
+    }
 }
 
 impl Position {
@@ -58,10 +87,76 @@ impl Position {
         pos.king_sq = pos.compute_king_sq();
         pos.piece_counter = pos.compute_piece_counter();
         pos.zobrist = pos.compute_zobrist();
+        pos.bitboards = pos.compute_bitboards();
 
         Ok(pos)
     }
 
+    //"<board> <color> <castling> <ep> [<halfmove> <fullmove>] op operand ...; op ...;"
+    //the clocks may also come from the hmvc and fmvn operations
+    pub fn from_epd(line: &str) -> Result<Epd, FenError> {
+        let mut rest = line.trim();
+        let mut fields = Vec::with_capacity(6);
+        for _ in 0..4 {
+            match take_epd_field(&mut rest) {
+                Some(field) => fields.push(field),
+                None => return Err(FenError::InvalidFieldCount { found: fields.len() }),
+            }
+        }
+        //some files carry the full fen
+        let mut clocks = rest.split_whitespace();
+        if let (Some(half), Some(full)) = (clocks.next(), clocks.next())
+            && half.parse::<u16>().is_ok()
+            && full.trim_end_matches(';').parse::<u16>().is_ok()
+        {
+            fields.push(take_epd_field(&mut rest).unwrap_or_default());
+            fields.push(take_epd_field(&mut rest).unwrap_or_default().trim_end_matches(';'));
+        }
+
+        let operations = parse_epd_operations(rest)?;
+        let operands = |opcode: &str| operations.iter().find(|(op, _)| op == opcode).map(|(_, o)| o);
+        let number = |opcode: &str| -> Result<Option<u32>, FenError> {
+            operands(opcode)
+                .map(|o| {
+                    o.first()
+                        .and_then(|n| n.parse().ok())
+                        .ok_or(FenError::InvalidEpdOperation { opcode: opcode.to_string() })
+                })
+                .transpose()
+        };
+
+        let halfmove = match number("hmvc")? {
+            Some(n) => n.to_string(),
+            None => fields.get(4).unwrap_or(&"0").to_string(),
+        };
+        let fullmove = match number("fmvn")? {
+            Some(n) => n.to_string(),
+            None => fields.get(5).unwrap_or(&"1").to_string(),
+        };
+        let fen = format!("{} {} {} {} {} {}", fields[0], fields[1], fields[2], fields[3], halfmove, fullmove);
+        let position = Position::from_fen(&fen)?;
+
+        let moves = |opcode: &str| -> Result<Vec<Move>, FenError> {
+            operands(opcode)
+                .into_iter()
+                .flatten()
+                .map(|san| Move::from_san(&position, san).ok_or(FenError::InvalidEpdMove { san: san.clone() }))
+                .collect()
+        };
+        let text = |opcode: &str| operands(opcode).map(|o| o.join(" "));
+
+        Ok(Epd {
+            best_moves: moves("bm")?,
+            avoid_moves: moves("am")?,
+            id: text("id"),
+            comment: text("c0"),
+            direct_mate: number("dm")?,
+            depth: number("acd")?,
+            position,
+            operations,
+        })
+    }
+
     pub fn to_fen(&self) -> String {
         let board = piece_placement_to_string(self);
         let active_color = active_color_to_string(self.player_to_move);
@@ -259,6 +354,68 @@ fn parse_fullmove_counter(field: &str) -> Result<u16, FenError> {
     }
 }
 
+//next whitespace separated field of an epd line
+fn take_epd_field<'a>(rest: &mut &'a str) -> Option<&'a str> {
+    let text: &'a str = rest;
+    if text.is_empty() {
+        return None;
+    }
+    let end = text.find(char::is_whitespace).unwrap_or(text.len());
+    *rest = text[end..].trim_start();
+    Some(&text[..end])
+}
+
+//"op operand operand; op "quoted string";" -> [(op, operands)], the last ';' may be missing
+fn parse_epd_operations(text: &str) -> Result<Vec<(String, Vec<String>)>, FenError> {
+    let mut operations = Vec::new();
+    let mut tokens: Vec<String> = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    loop {
+        match chars.next() {
+            None | Some(';') => {
+                if !tokens.is_empty() {
+                    let opcode = tokens.remove(0);
+                    if !opcode.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                        return Err(FenError::InvalidEpdOperation { opcode });
+                    }
+                    operations.push((opcode, std::mem::take(&mut tokens)));
+                }
+                if chars.peek().is_none() {
+                    break;
+                }
+            }
+            Some('"') => {
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => string.push(c),
+                        None => {
+                            let opcode = tokens.first().cloned().unwrap_or_default();
+                            return Err(FenError::InvalidEpdOperation { opcode });
+                        }
+                    }
+                }
+                tokens.push(string);
+            }
+            Some(c) if c.is_whitespace() => {}
+            Some(c) => {
+                let mut word = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || next == ';' {
+                        break;
+                    }
+                    word.push(next);
+                    chars.next();
+                }
+                tokens.push(word);
+            }
+        }
+    }
+    Ok(operations)
+}
+
 //helpers for to_fen()
 
 fn piece_placement_to_string(pos: &Position) -> String {
@@ -864,4 +1021,52 @@ mod tests {
         let fen = "8/8/8/8/8/8/8/4K2k w KK - 0 1";
         assert!(Position::from_fen(fen).is_err());
     }
+
+    #[test]
+    fn from_epd_parses_operations() {
+        let epd = Position::from_epd(
+            r#"2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id "WAC.001"; c0 "mate; in three";"#,
+        )
+        .unwrap();
+        assert_eq!(epd.position.to_fen(), "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1");
+        assert_eq!(epd.best_moves.iter().map(|m| m.to_uci()).collect::<Vec<_>>(), ["g3g6"]);
+        assert_eq!(epd.id.as_deref(), Some("WAC.001"));
+        assert_eq!(epd.comment.as_deref(), Some("mate; in three"));
+        assert_eq!(epd.operation("bm"), Some(&["Qg6".to_string()][..]));
+        assert!(epd.avoid_moves.is_empty());
+    }
+
+    #[test]
+    fn from_epd_reads_numbers_clocks_and_several_moves() {
+        let epd = Position::from_epd(
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKBNR w KQkq - am Qe2 Ba6; bm Bb5 Bc4; dm 3; acd 12; hmvc 2; fmvn 3",
+        )
+        .unwrap();
+        assert_eq!(epd.avoid_moves.len(), 2);
+        assert_eq!(epd.best_moves.iter().map(|m| m.to_uci()).collect::<Vec<_>>(), ["f1b5", "f1c4"]);
+        assert_eq!((epd.direct_mate, epd.depth), (Some(3), Some(12)));
+        assert_eq!((epd.position.half_move_clock, epd.position.move_counter), (2, 3));
+
+        //full fen in front of the operations
+        let epd = Position::from_epd("4k3/8/8/8/8/8/8/4K2R w K - 5 40 bm O-O;").unwrap();
+        assert_eq!((epd.position.half_move_clock, epd.position.move_counter), (5, 40));
+        assert!(epd.best_moves[0].is_castling());
+    }
+
+    #[test]
+    fn from_epd_rejects_bad_records() {
+        assert_eq!(Position::from_epd("4k3/8/8/8/8/8/8/4K3 w").unwrap_err(), FenError::InvalidFieldCount { found: 2 });
+        assert_eq!(
+            Position::from_epd("4k3/8/8/8/8/8/8/4K3 w - - bm Qh5;").unwrap_err(),
+            FenError::InvalidEpdMove { san: "Qh5".to_string() }
+        );
+        assert_eq!(
+            Position::from_epd("4k3/8/8/8/8/8/8/4K3 w - - dm x;").unwrap_err(),
+            FenError::InvalidEpdOperation { opcode: "dm".to_string() }
+        );
+        assert_eq!(
+            Position::from_epd(r#"4k3/8/8/8/8/8/8/4K3 w - - id "open"#).unwrap_err(),
+            FenError::InvalidEpdOperation { opcode: "id".to_string() }
+        );
+    }
 }
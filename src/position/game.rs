@@ -1,8 +1,7 @@
 use crate::board::mailbox120::SQUARE120_TO_SQUARE64;
 use crate::movegen::Move;
 use crate::movegen::attack::is_in_check;
-use crate::movegen::legal_move_filter::filter_legal_moves;
-use crate::movegen::pseudo_legal_movegen::generate_pseudo_legal_moves;
+use crate::movegen::legal_movegen::has_legal_move;
 use crate::position::{Color, GameState, PieceKind, Position};
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -23,15 +22,21 @@ pub struct Game {
 
 impl Game {
     pub fn new() -> Self {
-        let position = Position::starting_position();
+        Self::from_position(Position::starting_position())
+    }
+
+    //game starting from an arbitrary position, e.g. a PGN [FEN] tag
+    pub fn from_position(position: Position) -> Self {
         let mut gamestate = GameState::new();
         gamestate.reset(&position);
 
-        Self {
+        let mut game = Self {
             position,
             gamestate,
             gamestatus: GameStatus::Ongoing,
-        }
+        };
+        game.gamestatus = game.compute_status();
+        game
     }
     pub fn position(&self) -> &Position {&self.position}
     pub fn position_mut(&mut self) -> &mut Position {&mut self.position}
@@ -51,12 +56,36 @@ impl Game {
         if self.gamestatus != GameStatus::Ongoing {
             return;
         }
+        self.record_move(mv);
+    }
 
+    //plays mv even if the game is already over; repetition and 50-move draws
+    //have to be claimed, so recorded games (PGN) may continue after them
+    pub fn record_move(&mut self, mv: Move) {
         let undo = self.position.make_move_with_undo(mv);
         self.gamestate.record_after_make(undo, &self.position);
         self.gamestatus = self.compute_status();
     }
 
+    //moves played so far, in order
+    pub fn moves(&self) -> impl Iterator<Item = Move> + '_ {
+        self.gamestate.undo_stack.iter().map(|undo| undo.mv)
+    }
+
+    //zobrist keys of the positions before the current one, oldest first
+    pub fn prior_keys(&self) -> Vec<u64> {
+        let history = &self.gamestate.history;
+        history[..history.len().saturating_sub(1)].iter().map(|state| state.zobrist).collect()
+    }
+
+    //position the game started from (not necessarily the standard start)
+    pub fn start_position(&self) -> Position {
+        match self.gamestate.history.first() {
+            Some(state) => state.to_position(),
+            None => self.position.clone(),
+        }
+    }
+
     pub fn undo(&mut self) -> bool {
         let Some(undo) = self.gamestate.pop_undo() else {
             return false;
@@ -136,12 +165,11 @@ impl Game {
     fn check_checkmate_or_stalemate(&self) -> Option<GameStatus> {
         let side = self.position.player_to_move;
         let check = is_in_check(&self.position, side);
-        let pseudo = generate_pseudo_legal_moves(&self.position);
-        let legal = filter_legal_moves(&self.position, &pseudo);
+        let no_moves = !has_legal_move(&self.position);
 
-        if legal.is_empty() && !check {
+        if no_moves && !check {
             return Some(GameStatus::Stalemate);
-        } else if legal.is_empty() && check {
+        } else if no_moves && check {
             return Some(GameStatus::Checkmate {
                 winner: side.opposite(),
             });
@@ -162,7 +190,7 @@ impl Game {
 mod tests {
     use super::*;
     use crate::movegen::Move;
-    use crate::position::{Cell, Position};
+    use crate::position::Position;
 
     // Helperfunction a1 = sq(0,0); h8 = sq(7,7);
     fn sq(file: i32, rank: i32) -> usize {
@@ -171,7 +199,7 @@ mod tests {
 
     // Puts a piece on a sq
     fn put(pos: &mut Position, s: usize, color: Color, kind: PieceKind) {
-        pos.board[s] = Cell::Piece(crate::position::Piece { color, kind });
+        pos.put_piece(s, crate::position::Piece { color, kind });
     }
 
     #[test]
@@ -387,4 +415,19 @@ mod tests {
         game.try_play_move(mv_b2);
         debug_assert_eq!(game.gamestatus, GameStatus::DrawRepetition);
     }
+
+    #[test]
+    fn prior_keys_exclude_current_position() {
+        let mut game = Game::new();
+        assert!(game.prior_keys().is_empty());
+
+        let start = game.position().zobrist;
+        game.try_play_move(Move::new(sq(6, 0), sq(5, 2)));
+        let after_nf3 = game.position().zobrist;
+        game.try_play_move(Move::new(sq(6, 7), sq(5, 5)));
+        assert_eq!(game.prior_keys(), vec![start, after_nf3]);
+
+        game.undo();
+        assert_eq!(game.prior_keys(), vec![start]);
+    }
 }
@@ -1,11 +1,15 @@
 pub mod fen;
 pub mod game;
+pub mod pgn;
+pub mod polyglot;
 pub mod position;
 pub mod state;
 
 pub use position::{Cell, Color, Piece, PieceKind, Position, Square};
-pub use state::{GameState, State, Undo};
+pub use state::{GameState, NullUndo, State, Undo};
 
 pub use game::{Game, GameStatus};
 
-pub use fen::FenError;
+pub use fen::{Epd, FenError};
+pub use polyglot::polyglot_key;
+pub use pgn::{PgnError, PgnGame};
@@ -0,0 +1,632 @@
+//Portable Game Notation (PGN) import / export for Game
+//only the main line is kept: variations are parsed (so nesting is checked) but skipped,
+//comments and NAGs are attached to the move they follow
+
+use crate::movegen::Move;
+use crate::position::{Color, FenError, Game, GameStatus, Position};
+
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+//lines of movetext are wrapped below this length (export format allows 80)
+const MAX_LINE_LEN: usize = 79;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PgnError {
+    InvalidTag { line: usize },
+    UnterminatedComment { line: usize },
+    UnbalancedVariation { line: usize },
+    InvalidFen(FenError),
+    IllegalMove { san: String, ply: usize },
+}
+
+//comment and numeric annotation glyphs ($1 = "!", $2 = "?", ...) of a single move
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MoveAnnotation {
+    pub comment: Option<String>,
+    pub nags: Vec<u8>,
+}
+
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub game: Game,
+    //comment in front of the first move
+    pub comment: Option<String>,
+    //one entry per played move (may be shorter if moves were added afterwards)
+    pub annotations: Vec<MoveAnnotation>,
+}
+
+impl PgnGame {
+    pub fn new(game: Game) -> Self {
+        Self {
+            tags: SEVEN_TAG_ROSTER
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            game,
+            comment: None,
+            annotations: Vec::new(),
+        }
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(n, _)| n == name) {
+            Some(tag) => tag.1 = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    //result from the game status; an ongoing game falls back to the Result tag
+    //(resignation, time forfeit, agreed draw can't be seen on the board)
+    pub fn result(&self) -> &str {
+        if let Some(result) = result_from_status(self.game.status()) {
+            return result;
+        }
+        match self.tag("Result") {
+            Some(tag) if RESULTS.contains(&tag) => tag,
+            _ => "*",
+        }
+    }
+
+    pub fn to_pgn(&self) -> String {
+        write_game(
+            &self.game,
+            &self.tags,
+            self.result(),
+            self.comment.as_deref(),
+            &self.annotations,
+        )
+    }
+}
+
+//exports a game with the default Seven Tag Roster
+pub fn write_pgn(game: &Game) -> String {
+    let result = result_from_status(game.status()).unwrap_or("*");
+    write_game(game, &[], result, None, &[])
+}
+
+//parses all games of a PGN file
+pub fn read_pgn(text: &str) -> Result<Vec<PgnGame>, PgnError> {
+    let tokens = tokenize(text)?;
+    let mut games = Vec::new();
+    let mut builder = GameBuilder::default();
+    let mut variation_depth = 0usize;
+
+    for (token, line) in tokens {
+        match token {
+            Token::OpenVariation => variation_depth += 1,
+            Token::CloseVariation => {
+                if variation_depth == 0 {
+                    return Err(PgnError::UnbalancedVariation { line });
+                }
+                variation_depth -= 1;
+            }
+            _ if variation_depth > 0 => {}
+            Token::Tag(name, value) => {
+                //new tag section without a result token in between
+                if builder.in_movetext() {
+                    games.push(std::mem::take(&mut builder).finish()?);
+                }
+                builder.tags.push((name, value));
+            }
+            Token::Comment(text) => builder.add_comment(text),
+            Token::Nag(nag) => builder.add_nag(nag),
+            Token::Symbol(symbol) => {
+                if RESULTS.contains(&symbol.as_str()) {
+                    builder.result = Some(symbol);
+                    games.push(std::mem::take(&mut builder).finish()?);
+                } else {
+                    builder.play_symbol(&symbol)?;
+                }
+            }
+        }
+    }
+
+    if variation_depth > 0 {
+        let line = text.lines().count();
+        return Err(PgnError::UnbalancedVariation { line });
+    }
+    if builder.in_movetext() || !builder.tags.is_empty() {
+        games.push(builder.finish()?);
+    }
+    Ok(games)
+}
+
+fn result_from_status(status: GameStatus) -> Option<&'static str> {
+    match status {
+        GameStatus::Ongoing => None,
+        GameStatus::Checkmate {
+            winner: Color::White,
+        } => Some("1-0"),
+        GameStatus::Checkmate {
+            winner: Color::Black,
+        } => Some("0-1"),
+        GameStatus::Stalemate
+        | GameStatus::DrawRepetition
+        | GameStatus::DrawInsufficientMaterial
+        | GameStatus::Draw50Moves => Some("1/2-1/2"),
+    }
+}
+
+fn write_game(
+    game: &Game,
+    tags: &[(String, String)],
+    result: &str,
+    comment: Option<&str>,
+    annotations: &[MoveAnnotation],
+) -> String {
+    let mut out = String::new();
+    let start = game.start_position();
+
+    //tag section: roster first and in order, then everything else
+    for (name, default) in SEVEN_TAG_ROSTER {
+        let value = match name {
+            "Result" => result,
+            _ => tags
+                .iter()
+                .find(|(n, _)| n == name)
+                .map_or(default, |(_, v)| v.as_str()),
+        };
+        push_tag(&mut out, name, value);
+    }
+    for (name, value) in tags {
+        let is_roster = SEVEN_TAG_ROSTER.iter().any(|(n, _)| n == name);
+        if !is_roster && name != "SetUp" && name != "FEN" {
+            push_tag(&mut out, name, value);
+        }
+    }
+    let fen = start.to_fen();
+    if fen != Position::starting_position().to_fen() {
+        push_tag(&mut out, "SetUp", "1");
+        push_tag(&mut out, "FEN", &fen);
+    }
+    out.push('\n');
+
+    //movetext
+    let mut words = Vec::new();
+    let mut need_number = true;
+    if let Some(comment) = comment {
+        words.push(format_comment(comment));
+    }
+    let mut pos = start;
+    for (ply, mv) in game.moves().enumerate() {
+        if pos.player_to_move == Color::White {
+            words.push(format!("{}.", pos.move_counter));
+        } else if need_number {
+            words.push(format!("{}...", pos.move_counter));
+        }
+        need_number = false;
+        words.push(mv.to_san(&pos));
+
+        if let Some(annotation) = annotations.get(ply) {
+            for nag in &annotation.nags {
+                words.push(format!("${nag}"));
+            }
+            if let Some(comment) = &annotation.comment {
+                words.push(format_comment(comment));
+                need_number = true;
+            }
+        }
+        pos.make_move(mv);
+    }
+    words.push(result.to_string());
+
+    let mut line_len = 0;
+    for word in words {
+        if line_len > 0 && line_len + 1 + word.len() > MAX_LINE_LEN {
+            out.push('\n');
+            line_len = 0;
+        } else if line_len > 0 {
+            out.push(' ');
+            line_len += 1;
+        }
+        line_len += word.len();
+        out.push_str(&word);
+    }
+    out.push('\n');
+    out
+}
+
+fn push_tag(out: &mut String, name: &str, value: &str) {
+    let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+    out.push_str(&format!("[{name} \"{escaped}\"]\n"));
+}
+
+fn format_comment(comment: &str) -> String {
+    //a "}" can't be escaped inside a brace comment
+    format!("{{{}}}", comment.replace('}', ")"))
+}
+
+#[derive(Default)]
+struct GameBuilder {
+    tags: Vec<(String, String)>,
+    game: Option<Game>,
+    comment: Option<String>,
+    annotations: Vec<MoveAnnotation>,
+    result: Option<String>,
+}
+
+impl GameBuilder {
+    fn in_movetext(&self) -> bool {
+        self.game.is_some() || self.comment.is_some() || self.result.is_some()
+    }
+
+    fn game_mut(&mut self) -> Result<&mut Game, PgnError> {
+        if self.game.is_none() {
+            let fen = self.tags.iter().find(|(n, _)| n == "FEN");
+            let position = match fen {
+                Some((_, fen)) => Position::from_fen(fen).map_err(PgnError::InvalidFen)?,
+                None => Position::starting_position(),
+            };
+            self.game = Some(Game::from_position(position));
+        }
+        Ok(self.game.as_mut().expect("game was just created"))
+    }
+
+    fn add_comment(&mut self, text: String) {
+        let target = match self.annotations.last_mut() {
+            Some(annotation) => &mut annotation.comment,
+            None => &mut self.comment,
+        };
+        match target {
+            Some(existing) => {
+                existing.push(' ');
+                existing.push_str(&text);
+            }
+            None => *target = Some(text),
+        }
+    }
+
+    fn add_nag(&mut self, nag: u8) {
+        //a NAG in front of the first move has nothing to refer to
+        if let Some(annotation) = self.annotations.last_mut() {
+            annotation.nags.push(nag);
+        }
+    }
+
+    //move number, SAN move or SAN with suffix annotation ("e4!?")
+    fn play_symbol(&mut self, symbol: &str) -> Result<(), PgnError> {
+        let san = symbol
+            .trim_start_matches(|c: char| c.is_ascii_digit())
+            .trim_start_matches('.');
+        if san.is_empty() {
+            return Ok(());
+        }
+
+        let body = san.trim_end_matches(['!', '?']);
+        let suffix_nag = match &san[body.len()..] {
+            "!" => Some(1),
+            "?" => Some(2),
+            "!!" => Some(3),
+            "??" => Some(4),
+            "!?" => Some(5),
+            "?!" => Some(6),
+            _ => None,
+        };
+
+        let ply = self.annotations.len();
+        let game = self.game_mut()?;
+        let Some(mv) = Move::from_san(game.position(), body) else {
+            return Err(PgnError::IllegalMove {
+                san: body.to_string(),
+                ply,
+            });
+        };
+        game.record_move(mv);
+
+        self.annotations.push(MoveAnnotation {
+            comment: None,
+            nags: suffix_nag.into_iter().collect(),
+        });
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<PgnGame, PgnError> {
+        self.game_mut()?;
+        let mut pgn = PgnGame {
+            tags: self.tags,
+            game: self.game.expect("game_mut creates the game"),
+            comment: self.comment,
+            annotations: self.annotations,
+        };
+        if let Some(result) = self.result {
+            pgn.set_tag("Result", &result);
+        }
+        Ok(pgn)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Token {
+    Tag(String, String),
+    Comment(String),
+    Nag(u8),
+    OpenVariation,
+    CloseVariation,
+    Symbol(String),
+}
+
+//splits PGN text into tokens, each with the line it starts on
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, PgnError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let at_line_start = i == 0 || chars[i - 1] == '\n';
+
+        match c {
+            '\n' => {
+                line += 1;
+                i += 1;
+            }
+            c if c.is_whitespace() => i += 1,
+            //escape mechanism and rest-of-line comments
+            '%' | ';' if c == ';' || at_line_start => {
+                let start = i + 1;
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                if c == ';' {
+                    let comment: String = chars[start..i].iter().collect();
+                    tokens.push((Token::Comment(comment.trim().to_string()), line));
+                }
+            }
+            '{' => {
+                let start_line = line;
+                let start = i + 1;
+                i += 1;
+                while i < chars.len() && chars[i] != '}' {
+                    if chars[i] == '\n' {
+                        line += 1;
+                    }
+                    i += 1;
+                }
+                if i == chars.len() {
+                    return Err(PgnError::UnterminatedComment { line: start_line });
+                }
+                let comment: String = chars[start..i].iter().collect();
+                let comment = comment.split_whitespace().collect::<Vec<_>>().join(" ");
+                tokens.push((Token::Comment(comment), start_line));
+                i += 1;
+            }
+            '[' => {
+                let (tag, end) = parse_tag(&chars, i).ok_or(PgnError::InvalidTag { line })?;
+                tokens.push((tag, line));
+                i = end;
+            }
+            '(' => {
+                tokens.push((Token::OpenVariation, line));
+                i += 1;
+            }
+            ')' => {
+                tokens.push((Token::CloseVariation, line));
+                i += 1;
+            }
+            '$' => {
+                let start = i + 1;
+                i += 1;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+                //out of range NAGs are dropped, they carry no standard meaning
+                let digits: String = chars[start..i].iter().collect();
+                if let Ok(nag) = digits.parse::<u8>() {
+                    tokens.push((Token::Nag(nag), line));
+                }
+            }
+            _ => {
+                let start = i;
+                while i < chars.len()
+                    && !chars[i].is_whitespace()
+                    && !"{}()[];$".contains(chars[i])
+                {
+                    i += 1;
+                }
+                let symbol: String = chars[start..i].iter().collect();
+                tokens.push((Token::Symbol(symbol), line));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+//parses [Name "value"] starting at chars[start] == '[', returns the tag and the index after ']'
+fn parse_tag(chars: &[char], start: usize) -> Option<(Token, usize)> {
+    let mut i = start + 1;
+    let skip_blanks = |i: &mut usize| {
+        while *i < chars.len() && (chars[*i] == ' ' || chars[*i] == '\t') {
+            *i += 1;
+        }
+    };
+
+    skip_blanks(&mut i);
+    let name_start = i;
+    while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+        i += 1;
+    }
+    if i == name_start {
+        return None;
+    }
+    let name: String = chars[name_start..i].iter().collect();
+
+    skip_blanks(&mut i);
+    if chars.get(i) != Some(&'"') {
+        return None;
+    }
+    i += 1;
+    let mut value = String::new();
+    loop {
+        match chars.get(i)? {
+            '"' => break,
+            '\\' => {
+                value.push(*chars.get(i + 1)?);
+                i += 2;
+            }
+            '\n' => return None,
+            c => {
+                value.push(*c);
+                i += 1;
+            }
+        }
+    }
+    i += 1;
+
+    skip_blanks(&mut i);
+    if chars.get(i) != Some(&']') {
+        return None;
+    }
+    Some((Token::Tag(name, value), i + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(game: &mut Game, san: &str) {
+        let mv = Move::from_san(game.position(), san).expect("legal SAN");
+        game.try_play_move(mv);
+    }
+
+    #[test]
+    fn writes_seven_tag_roster_and_mate_result() {
+        let mut game = Game::new();
+        for san in ["f3", "e5", "g4", "Qh4#"] {
+            play(&mut game, san);
+        }
+
+        let pgn = write_pgn(&game);
+        let expected = "[Event \"?\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n\
+                        [White \"?\"]\n[Black \"?\"]\n[Result \"0-1\"]\n\n\
+                        1. f3 e5 2. g4 Qh4# 0-1\n";
+        assert_eq!(pgn, expected);
+    }
+
+    #[test]
+    fn roundtrip_keeps_tags_comments_and_nags() {
+        let mut game = Game::new();
+        for san in ["e4", "e5", "Nf3", "Nc6", "Bb5"] {
+            play(&mut game, san);
+        }
+        let mut pgn = PgnGame::new(game);
+        pgn.set_tag("White", "Morphy, \"Paul\"");
+        pgn.set_tag("ECO", "C60");
+        pgn.comment = Some("Ruy Lopez".to_string());
+        pgn.annotations = vec![MoveAnnotation::default(); 5];
+        pgn.annotations[2].nags.push(1);
+        pgn.annotations[2].comment = Some("develops".to_string());
+
+        let text = pgn.to_pgn();
+        assert!(text.contains("{Ruy Lopez} 1. e4 e5 2. Nf3 $1 {develops} 2... Nc6 3. Bb5 *"));
+
+        let games = read_pgn(&text).unwrap();
+        assert_eq!(games.len(), 1);
+        let back = &games[0];
+        assert_eq!(back.tag("White"), Some("Morphy, \"Paul\""));
+        assert_eq!(back.tag("ECO"), Some("C60"));
+        assert_eq!(back.comment.as_deref(), Some("Ruy Lopez"));
+        assert_eq!(back.annotations[2].nags, vec![1]);
+        assert_eq!(back.annotations[2].comment.as_deref(), Some("develops"));
+        assert_eq!(back.game.moves().count(), 5);
+        assert_eq!(back.to_pgn(), text);
+    }
+
+    #[test]
+    fn reads_multiple_games_and_skips_variations() {
+        let text = r#"
+[Event "First"]
+[Result "1-0"]
+
+1. e4 e5 (1... c5 2. Nf3 (2. c3) d6) 2. Nf3! Nc6?! ; rest of line comment
+3. Bc4 {Italian} 1-0
+
+[Event "Second"]
+[Result "1/2-1/2"]
+% escaped line, ignored
+1.d4 d5 2.c4 $2 dxc4 1/2-1/2
+"#;
+        let games = read_pgn(text).unwrap();
+        assert_eq!(games.len(), 2);
+
+        let first = &games[0];
+        assert_eq!(first.tag("Event"), Some("First"));
+        assert_eq!(first.game.moves().count(), 5);
+        assert_eq!(first.annotations[2].nags, vec![1]);
+        assert_eq!(first.annotations[3].nags, vec![6]);
+        assert_eq!(first.annotations[3].comment.as_deref(), Some("rest of line comment"));
+        assert_eq!(first.annotations[4].comment.as_deref(), Some("Italian"));
+        assert_eq!(first.result(), "1-0");
+
+        let second = &games[1];
+        assert_eq!(second.game.moves().count(), 4);
+        assert_eq!(second.annotations[2].nags, vec![2]);
+        assert_eq!(second.result(), "1/2-1/2");
+    }
+
+    #[test]
+    fn fen_tag_sets_start_position() {
+        let fen = "4k3/8/8/8/8/8/4P3/4K3 b - - 0 12";
+        let text = format!("[SetUp \"1\"]\n[FEN \"{fen}\"]\n\n12... Kd7 13. e4 *\n");
+        let games = read_pgn(&text).unwrap();
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].game.start_position().to_fen(), fen);
+        assert_eq!(games[0].game.moves().count(), 2);
+
+        let written = games[0].to_pgn();
+        assert!(written.contains(&format!("[FEN \"{fen}\"]")));
+        assert!(written.contains("12... Kd7 13. e4 *"));
+    }
+
+    #[test]
+    fn moves_after_claimable_draw_are_kept() {
+        let text = "1. Nf3 Nf6 2. Ng1 Ng8 3. Nf3 Nf6 4. Ng1 Ng8 5. e4 *";
+        let games = read_pgn(text).unwrap();
+        assert_eq!(games[0].game.moves().count(), 9);
+        assert_eq!(games[0].game.status(), GameStatus::Ongoing);
+    }
+
+    #[test]
+    fn reports_errors() {
+        assert_eq!(
+            read_pgn("1. e4 e5 2. Ke3 *").err(),
+            Some(PgnError::IllegalMove {
+                san: "Ke3".to_string(),
+                ply: 2
+            })
+        );
+        assert_eq!(
+            read_pgn("[Event \"x]\n1. e4 *").err(),
+            Some(PgnError::InvalidTag { line: 1 })
+        );
+        assert_eq!(
+            read_pgn("1. e4 {open\n*").err(),
+            Some(PgnError::UnterminatedComment { line: 1 })
+        );
+        assert!(matches!(
+            read_pgn("1. e4 (1. d4 *").err(),
+            Some(PgnError::UnbalancedVariation { .. })
+        ));
+        assert!(matches!(
+            read_pgn("[FEN \"bad\"]\n1. e4 *").err(),
+            Some(PgnError::InvalidFen(_))
+        ));
+    }
+}
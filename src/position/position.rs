@@ -1,4 +1,6 @@
-use super::state::Undo;
+use super::state::{NullUndo, Undo};
+use crate::board::bitboard::Bitboards;
+use crate::evaluation::accumulator::Incremental;
 pub use crate::board::mailbox120::BOARD_SIZE as BOARD120;
 use crate::board::mailbox120::SQUARE120_TO_SQUARE64;
 use crate::movegen::Move;
@@ -141,7 +143,7 @@ pub static ZOBRIST: Lazy<Zobrist> = Lazy::new(Zobrist::init_zobrist);
 
 // castling_rights uses 4 bits: White 0-0 (0b0001), White 0-0-0 (0b0010),
 // Black 0-0 (0b0100), Black 0-0-0 (0b1000)
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug)]
 pub struct Position {
     pub board: [Cell; BOARD120],
     pub player_to_move: Color,
@@ -152,8 +154,31 @@ pub struct Position {
     pub move_counter: u16,
     pub king_sq: [u8; 2],
     pub piece_counter: [u8; 12],
+    //mirrors board, kept in sync by make_move/undo_move
+    pub bitboards: Bitboards,
+    //first layer of the neural evaluation, installed by Evaluator::attach and kept in
+    //sync by make_move_with_undo/undo_move and the null moves
+    pub accumulator: Option<Box<dyn Incremental>>,
 }
 
+//positions compare by the board, attached or not; the accumulator is derived from it
+impl PartialEq for Position {
+    fn eq(&self, other: &Self) -> bool {
+        self.board == other.board
+            && self.player_to_move == other.player_to_move
+            && self.en_passant_square == other.en_passant_square
+            && self.castling_rights == other.castling_rights
+            && self.zobrist == other.zobrist
+            && self.half_move_clock == other.half_move_clock
+            && self.move_counter == other.move_counter
+            && self.king_sq == other.king_sq
+            && self.piece_counter == other.piece_counter
+            && self.bitboards == other.bitboards
+    }
+}
+
+impl Eq for Position {}
+
 impl Position {
     pub fn empty() -> Self {
         Self {
@@ -166,6 +191,8 @@ impl Position {
             move_counter: 1,
             king_sq: [0; 2],
             piece_counter: [0; 12],
+            bitboards: Bitboards::default(),
+            accumulator: None,
         }
     }
 
@@ -176,6 +203,7 @@ impl Position {
         pos.zobrist = pos.compute_zobrist();
         pos.king_sq = pos.compute_king_sq();
         pos.piece_counter = pos.compute_piece_counter();
+        pos.bitboards = pos.compute_bitboards();
 
         pos
     }
@@ -297,6 +325,26 @@ impl Position {
         all_pieces
     }
 
+    pub fn compute_bitboards(&self) -> Bitboards {
+        let mut bitboards = Bitboards::default();
+        for (sq120, cell) in self.board.iter().enumerate() {
+            if let Cell::Piece(piece) = cell {
+                bitboards.toggle(*piece, Self::sq64(sq120));
+            }
+        }
+        bitboards
+    }
+
+    //puts a piece on the board and into the bitboards; zobrist, king and counter
+    //caches are left to the compute_* functions (setup code and tests)
+    pub fn put_piece(&mut self, sq120: usize, piece: Piece) {
+        if let Cell::Piece(old) = self.board[sq120] {
+            self.bitboards.toggle(old, Self::sq64(sq120));
+        }
+        self.board[sq120] = Cell::Piece(piece);
+        self.bitboards.toggle(piece, Self::sq64(sq120));
+    }
+
     // computes a hash-value for every Piece on the Board, Player to Move, Castling rights
     // and en-passant-row. So every Boardstate has a unique hash-value
     pub fn compute_zobrist(&self) -> u64 {
@@ -353,6 +401,15 @@ impl Position {
 
     //Attention: works only on legal moves
     pub fn make_move(&mut self, mv: Move) {
+        self.apply_move(mv);
+        //no undo to diff against, recompute
+        if let Some(mut acc) = self.accumulator.take() {
+            acc.refresh(self);
+            self.accumulator = Some(acc);
+        }
+    }
+
+    fn apply_move(&mut self, mv: Move) {
         const WK: u8 = 0b0001;
         const WQ: u8 = 0b0010;
         const BK: u8 = 0b0100;
@@ -426,9 +483,9 @@ impl Position {
             did_capture = true;
 
             //incremental zobrist + piece counter
-            self.zobrist ^= Self::zob_piece(moving_piece, from);
-            self.zobrist ^= Self::zob_piece(captured_pawn, captured_sq);
-            self.zobrist ^= Self::zob_piece(moving_piece, to);
+            self.toggle_piece(moving_piece, from);
+            self.toggle_piece(captured_pawn, captured_sq);
+            self.toggle_piece(moving_piece, to);
 
             let ci = Self::pc_idx(captured_pawn);
             debug_assert!(self.piece_counter[ci] > 0);
@@ -441,8 +498,8 @@ impl Position {
         //castling
         else if mv.is_castling() {
             //zobrist king move
-            self.zobrist ^= Self::zob_piece(moving_piece, from);
-            self.zobrist ^= Self::zob_piece(moving_piece, to);
+            self.toggle_piece(moving_piece, from);
+            self.toggle_piece(moving_piece, to);
 
             //king move
             self.board[from] = Cell::Empty;
@@ -466,8 +523,8 @@ impl Position {
 
                 debug_assert!(rook_piece.kind == PieceKind::Rook && rook_piece.color == moving_piece.color, "castling: wrong rook on rook_from");
 
-                self.zobrist ^= Self::zob_piece(rook_piece, rook_from);
-                self.zobrist ^= Self::zob_piece(rook_piece, rook_to);
+                self.toggle_piece(rook_piece, rook_from);
+                self.toggle_piece(rook_piece, rook_to);
 
                 self.board[rook_to] = self.board[rook_from];
                 self.board[rook_from] = Cell::Empty;
@@ -486,8 +543,8 @@ impl Position {
 
                 debug_assert!(rook_piece.kind == PieceKind::Rook && rook_piece.color == moving_piece.color, "castling: wrong rook on rook_from");
 
-                self.zobrist ^= Self::zob_piece(rook_piece, rook_from);
-                self.zobrist ^= Self::zob_piece(rook_piece, rook_to);
+                self.toggle_piece(rook_piece, rook_from);
+                self.toggle_piece(rook_piece, rook_to);
 
                 self.board[rook_to] = self.board[rook_from];
                 self.board[rook_from] = Cell::Empty;
@@ -496,14 +553,14 @@ impl Position {
         //Normal, Promotion, DoublePawnPush
         else {
             //moving piece leaves from
-            self.zobrist ^= Self::zob_piece(moving_piece, from);
+            self.toggle_piece(moving_piece, from);
 
             if let Cell::Piece(p) = self.board[to] {
                 did_capture = true;
                 captured_piece = Some(p);
 
                 //remove captured from has + decrease counter
-                self.zobrist ^= Self::zob_piece(p, to);
+                self.toggle_piece(p, to);
                 let captured_idx = Self::pc_idx(p);
                 debug_assert!(self.piece_counter[captured_idx] > 0);
                 self.piece_counter[captured_idx] -= 1;
@@ -531,7 +588,7 @@ impl Position {
                 self.board[to] = Cell::Piece(promoted);
 
                 //hash promoted piece + counters pawn promo++
-                self.zobrist ^= Self::zob_piece(promoted, to);
+                self.toggle_piece(promoted, to);
 
                 let pawn = Piece {
                     color: moving_piece.color,
@@ -547,7 +604,7 @@ impl Position {
                 self.board[to] = Cell::Piece(moving_piece);
 
                 //moving piece arrives
-                self.zobrist ^= Self::zob_piece(moving_piece, to);
+                self.toggle_piece(moving_piece, to);
             }
 
             //if double pawn push, set the EP target
@@ -625,6 +682,7 @@ impl Position {
         debug_assert_eq!(self.zobrist, self.compute_zobrist());
         debug_assert_eq!(self.piece_counter, self.compute_piece_counter());
         debug_assert_eq!(self.king_sq, self.compute_king_sq());
+        debug_assert_eq!(self.bitboards, self.compute_bitboards());
 
         /*removed
         self.piece_counter = self.compute_piece_counter();
@@ -641,6 +699,13 @@ impl Position {
         s as usize
     }
 
+    //piece appears on / leaves sq120: hash and bitboards, the board itself is set by the caller
+    #[inline]
+    fn toggle_piece(&mut self, piece: Piece, sq120: usize) {
+        self.zobrist ^= Self::zob_piece(piece, sq120);
+        self.bitboards.toggle(piece, Self::sq64(sq120));
+    }
+
     #[inline]
     fn zob_piece(piece: Piece, sq120: usize) -> u64 {
         let s64 = Self::sq64(sq120);
@@ -699,6 +764,7 @@ impl Position {
                     prev_move_counter: self.move_counter,
                     prev_king_sq: self.king_sq,
                     prev_piece_counter: self.piece_counter,
+                    prev_bitboards: self.bitboards,
                 };
             }
         };
@@ -753,10 +819,15 @@ impl Position {
             prev_move_counter: self.move_counter,
             prev_king_sq: self.king_sq,
             prev_piece_counter: self.piece_counter,
+            prev_bitboards: self.bitboards,
         };
 
         //apply move
-        self.make_move(mv);
+        self.apply_move(mv);
+        if let Some(mut acc) = self.accumulator.take() {
+            acc.push_move(&undo, self);
+            self.accumulator = Some(acc);
+        }
 
         undo
     }
@@ -833,9 +904,14 @@ impl Position {
         self.move_counter = undo.prev_move_counter;
         self.king_sq = undo.prev_king_sq;
         self.piece_counter = undo.prev_piece_counter;
+        self.bitboards = undo.prev_bitboards;
+        if let Some(acc) = self.accumulator.as_mut() {
+            acc.pop();
+        }
 
         debug_assert_eq!(self.zobrist, self.compute_zobrist());
         debug_assert_eq!(self.piece_counter, self.compute_piece_counter());
+        debug_assert_eq!(self.bitboards, self.compute_bitboards());
         
         #[cfg(debug_assertions)]
         {
@@ -849,6 +925,44 @@ impl Position {
         );
         }
     }
+
+    //passes the turn: clears en passant and flips the side to move
+    pub fn make_null_move(&mut self) -> NullUndo {
+        let undo = NullUndo {
+            prev_ep_sq: self.en_passant_square,
+            prev_zobrist: self.zobrist,
+            prev_hm_clock: self.half_move_clock,
+            prev_move_counter: self.move_counter,
+        };
+
+        if let Some(ep) = self.en_passant_square.take() {
+            self.zobrist ^= Self::zob_ep(ep);
+        }
+        self.half_move_clock = self.half_move_clock.saturating_add(1);
+        if self.player_to_move == Color::Black {
+            self.move_counter = self.move_counter.saturating_add(1);
+        }
+        self.player_to_move = self.player_to_move.opposite();
+        self.zobrist ^= ZOBRIST.zobrist_side_to_move;
+        if let Some(mut acc) = self.accumulator.take() {
+            acc.push_null(undo.prev_ep_sq, self);
+            self.accumulator = Some(acc);
+        }
+
+        debug_assert_eq!(self.zobrist, self.compute_zobrist());
+        undo
+    }
+
+    pub fn undo_null_move(&mut self, undo: NullUndo) {
+        self.player_to_move = self.player_to_move.opposite();
+        self.en_passant_square = undo.prev_ep_sq;
+        self.zobrist = undo.prev_zobrist;
+        self.half_move_clock = undo.prev_hm_clock;
+        self.move_counter = undo.prev_move_counter;
+        if let Some(acc) = self.accumulator.as_mut() {
+            acc.pop();
+        }
+    }
 }
 
 #[cfg(test)]
@@ -864,7 +978,7 @@ pub(super) mod test_util {
 
     //puts a specific piece on a field
     pub(super) fn put(pos: &mut Position, sq: &str, color: Color, kind: PieceKind) {
-        pos.board[sq_str(sq)] = Cell::Piece(Piece { color, kind });
+        pos.put_piece(sq_str(sq), Piece { color, kind });
     }
 
     //builds a specified safe test position
@@ -1175,4 +1289,25 @@ mod undo_tests {
         pos.undo_move(undo);
         assert_eq!(pos, before);
     }
+
+    #[test]
+    fn null_move_roundtrip_clears_ep_and_restores_state() {
+        let mut pos = Position::starting_position();
+        pos.make_move(Move {
+            move_type: crate::movegen::MoveType::DoublePawnPush,
+            ..Move::new(sq_str("e2"), sq_str("e4"))
+        });
+        assert!(pos.en_passant_square.is_some());
+        let before = pos.clone();
+
+        let undo = pos.make_null_move();
+        assert_eq!(pos.en_passant_square, None);
+        assert_eq!(pos.player_to_move, Color::White);
+        assert_eq!(pos.move_counter, before.move_counter + 1);
+        assert_eq!(pos.zobrist, pos.compute_zobrist());
+        assert_ne!(pos.zobrist, before.zobrist);
+
+        pos.undo_null_move(undo);
+        assert_eq!(pos, before);
+    }
 }
@@ -1,3 +1,4 @@
+use crate::board::bitboard::Bitboards;
 use crate::board::mailbox120::BOARD_SIZE as BOARD120;
 use crate::movegen::Move;
 use crate::position::{Cell, Color, Piece, Position, Square};
@@ -15,6 +16,7 @@ pub struct State {
     pub move_counter: u16,
     pub king_sq: [u8; 2],
     pub piece_counter: [u8; 12],
+    pub bitboards: Bitboards,
 }
 
 impl State {
@@ -30,6 +32,24 @@ impl State {
             move_counter: pos.move_counter,
             king_sq: pos.king_sq,
             piece_counter: pos.piece_counter,
+            bitboards: pos.bitboards,
+        }
+    }
+
+    #[inline]
+    pub fn to_position(&self) -> Position {
+        Position {
+            board: self.board,
+            player_to_move: self.player_to_move,
+            en_passant_square: self.en_passant_square,
+            castling_rights: self.castling_rights,
+            zobrist: self.zobrist,
+            half_move_clock: self.half_move_clock,
+            move_counter: self.move_counter,
+            king_sq: self.king_sq,
+            piece_counter: self.piece_counter,
+            bitboards: self.bitboards,
+            accumulator: None,
         }
     }
 }
@@ -77,6 +97,15 @@ impl GameState {
 }
 
 
+//the side to move passes (null-move pruning), only the state fields change
+#[derive(Clone, Copy, Debug)]
+pub struct NullUndo {
+    pub prev_ep_sq: Option<Square>,
+    pub prev_zobrist: u64,
+    pub prev_hm_clock: u16,
+    pub prev_move_counter: u16,
+}
+
 #[derive(Clone, Debug)]
 pub struct Undo {
     pub mv: Move,
@@ -99,4 +128,5 @@ pub struct Undo {
     pub prev_move_counter: u16,
     pub prev_king_sq: [u8; 2],
     pub prev_piece_counter: [u8; 12],
+    pub prev_bitboards: Bitboards,
 }
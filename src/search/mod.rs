@@ -1,4 +1,11 @@
+pub mod ordering;
+pub mod picker;
 pub mod searcher;
+pub mod time;
 pub mod tt;
 
-pub use searcher::{SearchLimits, SearchResult, Searcher};
\ No newline at end of file
+pub use searcher::{
+    IterationCallback, IterationInfo, MATE, PvLine, SearchConfig, SearchLimits, SearchResult,
+    Searcher,
+};
+pub use time::{Clock, TimeBudget, TimeManager};
\ No newline at end of file
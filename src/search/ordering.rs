@@ -0,0 +1,203 @@
+//quiet move ordering heuristics: killer moves, butterfly history and countermoves
+//all of them are learned from beta cutoffs in negamax
+
+use crate::board::mailbox120::SQUARE120_TO_SQUARE64;
+use crate::movegen::Move;
+use crate::position::Color;
+
+//ordering bonuses, all below the capture scores of move_order_score (10000+)
+pub const KILLER_1_BONUS: i32 = 9000;
+pub const KILLER_2_BONUS: i32 = 8500;
+pub const COUNTERMOVE_BONUS: i32 = 8000;
+//history scores stay within +-HISTORY_MAX, so they never overtake the bonuses above
+pub const HISTORY_MAX: i32 = 7000;
+
+pub struct MoveOrdering {
+    //two killer slots per ply, slot 0 is the most recent
+    killers: Vec<[Move; 2]>,
+    //butterfly table [color][from64][to64]
+    history: Box<[[[i32; 64]; 64]; 2]>,
+    //reply that refuted the previous move, [from64][to64] of the previous move
+    countermoves: Box<[[Move; 64]; 64]>,
+}
+
+impl Default for MoveOrdering {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MoveOrdering {
+    pub fn new() -> Self {
+        Self {
+            killers: Vec::new(),
+            history: Box::new([[[0; 64]; 64]; 2]),
+            countermoves: Box::new([[Move::NULL; 64]; 64]),
+        }
+    }
+
+    //between two searches: killers and countermoves belong to the old position,
+    //history is only halved so good quiet moves keep a head start
+    pub fn new_search(&mut self) {
+        self.killers.clear();
+        for side in self.history.iter_mut() {
+            for from in side.iter_mut() {
+                for score in from.iter_mut() {
+                    *score /= 2;
+                }
+            }
+        }
+        for from in self.countermoves.iter_mut() {
+            from.fill(Move::NULL);
+        }
+    }
+
+    //ordering bonus of a quiet move
+    pub fn quiet_score(&self, color: Color, mv: Move, ply: usize, prev: Move) -> i32 {
+        if let Some(killers) = self.killers.get(ply) {
+            if mv == killers[0] {
+                return KILLER_1_BONUS;
+            }
+            if mv == killers[1] {
+                return KILLER_2_BONUS;
+            }
+        }
+        if !prev.is_null() && self.countermove(prev) == mv {
+            return COUNTERMOVE_BONUS;
+        }
+        self.history_score(color, mv)
+    }
+
+    pub fn history_score(&self, color: Color, mv: Move) -> i32 {
+        let (from, to) = Self::idx(mv);
+        self.history[color.idx()][from][to]
+    }
+
+    pub fn killers(&self, ply: usize) -> [Move; 2] {
+        self.killers.get(ply).copied().unwrap_or([Move::NULL; 2])
+    }
+
+    pub fn countermove(&self, prev: Move) -> Move {
+        let (from, to) = Self::idx(prev);
+        self.countermoves[from][to]
+    }
+
+    //a quiet move caused a beta cutoff; the quiets tried before it did not
+    pub fn update_on_cutoff(
+        &mut self,
+        color: Color,
+        mv: Move,
+        ply: usize,
+        prev: Move,
+        depth: i32,
+        tried_quiets: &[Move],
+    ) {
+        if self.killers.len() <= ply {
+            self.killers.resize(ply + 1, [Move::NULL; 2]);
+        }
+        let killers = &mut self.killers[ply];
+        if killers[0] != mv {
+            killers[1] = killers[0];
+            killers[0] = mv;
+        }
+
+        let bonus = (depth * depth).min(HISTORY_MAX);
+        self.add_history(color, mv, bonus);
+        for &quiet in tried_quiets {
+            if quiet != mv {
+                self.add_history(color, quiet, -bonus);
+            }
+        }
+
+        if !prev.is_null() {
+            let (from, to) = Self::idx(prev);
+            self.countermoves[from][to] = mv;
+        }
+    }
+
+    //history gravity: the closer to HISTORY_MAX, the smaller the step
+    fn add_history(&mut self, color: Color, mv: Move, bonus: i32) {
+        let (from, to) = Self::idx(mv);
+        let entry = &mut self.history[color.idx()][from][to];
+        *entry += bonus - *entry * bonus.abs() / HISTORY_MAX;
+    }
+
+    #[inline]
+    fn idx(mv: Move) -> (usize, usize) {
+        (
+            SQUARE120_TO_SQUARE64[mv.from_sq()] as usize,
+            SQUARE120_TO_SQUARE64[mv.to_sq()] as usize,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mv(from: usize, to: usize) -> Move {
+        Move::new(from, to)
+    }
+
+    #[test]
+    fn killers_shift_and_ignore_duplicates() {
+        let mut ordering = MoveOrdering::new();
+        let (a, b, c) = (mv(31, 51), mv(32, 52), mv(22, 43));
+
+        ordering.update_on_cutoff(Color::White, a, 3, Move::NULL, 4, &[]);
+        ordering.update_on_cutoff(Color::White, a, 3, Move::NULL, 4, &[]);
+        assert_eq!(ordering.killers(3), [a, Move::NULL]);
+
+        ordering.update_on_cutoff(Color::White, b, 3, Move::NULL, 4, &[]);
+        ordering.update_on_cutoff(Color::White, c, 3, Move::NULL, 4, &[]);
+        assert_eq!(ordering.killers(3), [c, b]);
+        assert_eq!(ordering.killers(2), [Move::NULL; 2]);
+
+        assert_eq!(ordering.quiet_score(Color::White, c, 3, Move::NULL), KILLER_1_BONUS);
+        assert_eq!(ordering.quiet_score(Color::White, b, 3, Move::NULL), KILLER_2_BONUS);
+    }
+
+    #[test]
+    fn history_rewards_cutoff_move_and_punishes_tried_quiets() {
+        let mut ordering = MoveOrdering::new();
+        let (good, bad) = (mv(22, 43), mv(27, 46));
+
+        ordering.update_on_cutoff(Color::Black, good, 1, Move::NULL, 5, &[bad, good]);
+        assert_eq!(ordering.history_score(Color::Black, good), 25);
+        assert_eq!(ordering.history_score(Color::Black, bad), -25);
+        assert_eq!(ordering.history_score(Color::White, good), 0);
+
+        //gravity keeps scores bounded
+        for _ in 0..10_000 {
+            ordering.update_on_cutoff(Color::Black, good, 1, Move::NULL, 80, &[]);
+        }
+        let score = ordering.history_score(Color::Black, good);
+        assert!(score > 0 && score <= HISTORY_MAX);
+    }
+
+    #[test]
+    fn countermove_is_keyed_by_previous_move() {
+        let mut ordering = MoveOrdering::new();
+        let prev = mv(85, 65);
+        let reply = mv(22, 43);
+
+        ordering.update_on_cutoff(Color::White, reply, 2, prev, 3, &[]);
+        assert_eq!(ordering.countermove(prev), reply);
+        //other ply, so not a killer there, but still the countermove
+        assert_eq!(ordering.quiet_score(Color::White, reply, 5, prev), COUNTERMOVE_BONUS);
+    }
+
+    #[test]
+    fn new_search_clears_killers_and_countermoves_and_halves_history() {
+        let mut ordering = MoveOrdering::new();
+        let prev = mv(85, 65);
+        let reply = mv(22, 43);
+        ordering.update_on_cutoff(Color::White, reply, 0, prev, 10, &[]);
+        let before = ordering.history_score(Color::White, reply);
+
+        ordering.new_search();
+        assert_eq!(ordering.killers(0), [Move::NULL; 2]);
+        assert_eq!(ordering.countermove(prev), Move::NULL);
+        assert_eq!(ordering.history_score(Color::White, reply), before / 2);
+    }
+}
@@ -0,0 +1,231 @@
+//staged move picker for negamax: tt move, good captures, killers, quiets, bad captures
+//(captures losing material by SEE)
+//captures and quiets are only generated once the stages before them are used up,
+//so a cutoff on the tt move or a capture never generates the quiet moves
+
+use crate::movegen::see::see_ge;
+use crate::movegen::{Move, generate_legal_captures, generate_legal_quiets, is_legal_move};
+use crate::position::{Cell, Position};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Stage {
+    TtMove,
+    GenerateCaptures,
+    GoodCaptures,
+    Killer(usize),
+    GenerateQuiets,
+    Quiets,
+    BadCaptures,
+    Done,
+}
+
+//buffers of one node, handed back after the node so the search doesn't allocate per node
+#[derive(Debug, Default)]
+pub struct MoveList {
+    generated: Vec<Move>,
+    //captures at the front, bad ones moved to the very front, quiets after the captures
+    entries: Vec<(Move, i32)>,
+}
+
+pub struct MovePicker {
+    stage: Stage,
+    tt_move: Move,
+    killers: [Move; 2],
+    list: MoveList,
+    //next entry to look at in the current stage
+    cur: usize,
+    //end of the captures in entries
+    captures_end: usize,
+    //entries[..bad_end] are captures that lose material, searched last
+    bad_end: usize,
+}
+
+impl MovePicker {
+    pub fn new(mut list: MoveList, tt_move: Move, killers: [Move; 2]) -> Self {
+        list.entries.clear();
+        Self {
+            stage: Stage::TtMove,
+            tt_move,
+            killers,
+            list,
+            cur: 0,
+            captures_end: 0,
+            bad_end: 0,
+        }
+    }
+
+    //the buffers for the next node
+    pub fn into_list(self) -> MoveList {
+        self.list
+    }
+
+    //next legal move, best first; score orders the moves of one stage
+    pub fn next(&mut self, pos: &Position, score: impl Fn(Move) -> i32) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::TtMove => {
+                    self.stage = Stage::GenerateCaptures;
+                    if is_legal_move(pos, self.tt_move) {
+                        return Some(self.tt_move);
+                    }
+                }
+                Stage::GenerateCaptures => {
+                    self.generate(pos, generate_legal_captures, &score);
+                    self.captures_end = self.list.entries.len();
+                    self.stage = Stage::GoodCaptures;
+                }
+                Stage::GoodCaptures => {
+                    let Some(mv) = self.select_best(self.captures_end) else {
+                        self.stage = Stage::Killer(0);
+                        continue;
+                    };
+                    if mv == self.tt_move {
+                        continue;
+                    }
+                    if !mv.is_promotion() && !see_ge(pos, mv, 0) {
+                        self.list.entries.swap(self.bad_end, self.cur - 1);
+                        self.bad_end += 1;
+                        continue;
+                    }
+                    return Some(mv);
+                }
+                Stage::Killer(i) => {
+                    self.stage = if i + 1 < self.killers.len() {
+                        Stage::Killer(i + 1)
+                    } else {
+                        Stage::GenerateQuiets
+                    };
+                    let killer = self.killers[i];
+                    if killer != self.tt_move && is_quiet(pos, killer) && is_legal_move(pos, killer) {
+                        return Some(killer);
+                    }
+                }
+                Stage::GenerateQuiets => {
+                    self.generate(pos, generate_legal_quiets, &score);
+                    //stable, so equal scores keep the generation order
+                    self.list.entries[self.captures_end..].sort_by_key(|&(_, s)| -s);
+                    self.cur = self.captures_end;
+                    self.stage = Stage::Quiets;
+                }
+                Stage::Quiets => {
+                    let Some(&(mv, _)) = self.list.entries.get(self.cur) else {
+                        self.cur = 0;
+                        self.stage = Stage::BadCaptures;
+                        continue;
+                    };
+                    self.cur += 1;
+                    if mv != self.tt_move && !self.killers.contains(&mv) {
+                        return Some(mv);
+                    }
+                }
+                Stage::BadCaptures => {
+                    if self.cur >= self.bad_end {
+                        self.stage = Stage::Done;
+                        continue;
+                    }
+                    self.cur += 1;
+                    return Some(self.list.entries[self.cur - 1].0);
+                }
+                Stage::Done => return None,
+            }
+        }
+    }
+
+    fn generate(
+        &mut self,
+        pos: &Position,
+        generator: fn(&Position, &mut Vec<Move>),
+        score: &impl Fn(Move) -> i32,
+    ) {
+        let list = &mut self.list;
+        list.generated.clear();
+        generator(pos, &mut list.generated);
+        list.entries.extend(list.generated.iter().map(|&m| (m, score(m))));
+    }
+
+    //selection sort step over entries[cur..end]: captures are few and a cutoff
+    //usually comes early, so sorting them all up front is wasted work
+    fn select_best(&mut self, end: usize) -> Option<Move> {
+        if self.cur >= end {
+            return None;
+        }
+        let entries = &mut self.list.entries;
+        let mut best = self.cur;
+        for i in self.cur + 1..end {
+            if entries[i].1 > entries[best].1 {
+                best = i;
+            }
+        }
+        entries.swap(self.cur, best);
+        self.cur += 1;
+        Some(entries[self.cur - 1].0)
+    }
+}
+
+#[inline]
+fn is_quiet(pos: &Position, mv: Move) -> bool {
+    !mv.is_promotion() && !mv.is_en_passant() && !matches!(pos.board[mv.to_sq()], Cell::Piece(_))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movegen::generate_legal_moves;
+
+    fn picked(pos: &Position, tt_move: Move, killers: [Move; 2]) -> Vec<Move> {
+        let mut picker = MovePicker::new(MoveList::default(), tt_move, killers);
+        let mut moves = Vec::new();
+        while let Some(mv) = picker.next(pos, |_| 0) {
+            moves.push(mv);
+        }
+        moves
+    }
+
+    fn uci(mv: &str) -> Move {
+        Move::from_uci(mv).unwrap()
+    }
+
+    #[test]
+    fn yields_every_legal_move_once() {
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "4k3/8/5N2/8/8/8/8/K3R3 b - - 0 1",
+        ];
+        for fen in fens {
+            let pos = Position::from_fen(fen).unwrap();
+            let mut legal = Vec::new();
+            generate_legal_moves(&pos, &mut legal);
+            //tt move and killers taken from the move list, plus ones that don't fit the position
+            let killers = [legal[legal.len() / 2], uci("a1a8")];
+            let mut moves = picked(&pos, legal[legal.len() - 1], killers);
+
+            let key = |m: &Move| (m.from_sq(), m.to_sq(), m.to_uci());
+            moves.sort_by_key(key);
+            legal.sort_by_key(key);
+            assert_eq!(moves, legal, "fen={fen}");
+        }
+    }
+
+    #[test]
+    fn stages_come_in_order() {
+        //white: the queen can take a defended pawn (bad) or the free rook (good)
+        let pos = Position::from_fen("4k3/2p5/1p6/8/3Q3r/8/8/4K3 w - - 0 1").unwrap();
+        let tt_move = uci("e1f2");
+        let killer = uci("d4d5");
+        let moves = picked(&pos, tt_move, [killer, Move::NULL]);
+
+        assert_eq!(moves[0], tt_move);
+        assert_eq!(moves[1], uci("d4h4"));
+        assert_eq!(moves[2], killer);
+        assert_eq!(*moves.last().unwrap(), uci("d4b6"));
+    }
+
+    #[test]
+    fn illegal_tt_move_and_killers_are_skipped() {
+        let pos = Position::starting_position();
+        let moves = picked(&pos, uci("e2e5"), [uci("e7e5"), uci("b1d2")]);
+        assert_eq!(moves.len(), 20);
+        assert!(!moves.contains(&uci("e2e5")));
+    }
+}
@@ -1,26 +1,133 @@
-use std::time::Instant;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 
 use crate::evaluation::Evaluator;
+use crate::movegen::see::see_ge;
 use crate::movegen::{
-    Move, generate_legal_captures_in_place, generate_pseudo_legal_moves_in_place, is_in_check,
+    Move, generate_legal_captures, generate_legal_checks, generate_legal_moves,
+    generate_legal_moves_in_place, is_in_check,
 };
 use crate::position::{Cell, Color, PieceKind, Position};
+use crate::tablebase::{Tablebase, Wdl};
+use super::ordering::MoveOrdering;
+use super::picker::{MoveList, MovePicker};
+use super::time::{Clock, TimeManager};
 use super::tt::{Bound, TranspositionTable};
 
 const INF: i32 = 50000;
-const MATE: i32 = 30_000;
+pub const MATE: i32 = 30_000;
+//tablebase win, below every mate score
+const TB_WIN: i32 = MATE - 2000;
+//nodes are published to the shared counter in batches to keep threads off one cache line
+const NODE_BATCH: u64 = 1024;
+//clock_start_ms while an infinite/ponder search hasn't been released yet
+const CLOCK_NOT_STARTED: u64 = u64::MAX;
+
+//selective search parameters
+const RFP_MAX_DEPTH: i32 = 3;
+const RFP_MARGIN: i32 = 120;
+const NULL_MOVE_MIN_DEPTH: i32 = 3;
+const FUTILITY_MARGINS: [i32; 3] = [0, 200, 400];
+const LMR_MIN_DEPTH: i32 = 3;
+const LMR_MIN_MOVES: i32 = 3;
+//tt move is always searched first
+const TT_MOVE_BONUS: i32 = 1_000_000;
+//qsearch: a capture has to be able to get within this of alpha
+const DELTA_MARGIN: i32 = 200;
+//below every quiet move ordering score (history is at least -HISTORY_MAX)
+const BAD_CAPTURE_BASE: i32 = -20_000;
+//aspiration window half-width around the previous score, doubled on every fail
+const ASPIRATION_DELTA: i32 = 25;
+const ASPIRATION_MIN_DEPTH: u8 = 4;
+//beyond this the window is opened completely
+const ASPIRATION_MAX_DELTA: i32 = 1000;
 #[derive(Clone, Copy)]
 pub struct SearchLimits {
     pub max_depth: u8,
     pub max_nodes: Option<u64>,
     pub max_time_ms: Option<u64>,
+    //number of best root moves to report (analysis), 1 for normal play
+    pub multi_pv: usize,
+    //clock of the side to move; soft/hard budgets on top of max_time_ms
+    pub clock: Option<Clock>,
 }
 
+//switches for the selective search techniques, all enabled by default;
+//mainly there to A/B test them against each other
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SearchConfig {
+    pub null_move: bool,
+    pub lmr: bool,
+    pub pvs: bool,
+    pub check_extensions: bool,
+    pub futility: bool,
+    pub reverse_futility: bool,
+    //killers, history and countermoves for quiet moves
+    pub quiet_ordering: bool,
+    pub aspiration: bool,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            null_move: true,
+            lmr: true,
+            pvs: true,
+            check_extensions: true,
+            futility: true,
+            reverse_futility: true,
+            quiet_ordering: true,
+            aspiration: true,
+        }
+    }
+}
+
+impl SearchConfig {
+    //plain alpha-beta, everything off
+    pub fn plain() -> Self {
+        Self {
+            null_move: false,
+            lmr: false,
+            pvs: false,
+            check_extensions: false,
+            futility: false,
+            reverse_futility: false,
+            quiet_ordering: false,
+            aspiration: false,
+        }
+    }
+}
+
+//progress of the main thread after every completed iteration
+pub struct IterationInfo<'a> {
+    pub depth: u8,
+    //1-based index of the line, always 1 unless multi_pv > 1
+    pub multipv: usize,
+    pub score_cp: i32,
+    pub nodes: u64,
+    pub pv: &'a [Move],
+    pub elapsed_ms: u64,
+}
+
+pub type IterationCallback = Box<dyn FnMut(&IterationInfo) + Send>;
+
 pub struct SearchResult {
     pub best_move: Move,
     pub score_cp: i32,
     pub depth: u8,
     pub nodes: u64,
+    //principal variation of the last completed iteration, starts with best_move
+    pub pv: Vec<Move>,
+    //best multi_pv lines, best first; lines[0] matches best_move / score_cp / pv
+    pub lines: Vec<PvLine>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PvLine {
+    pub best_move: Move,
+    pub score_cp: i32,
+    pub pv: Vec<Move>,
 }
 
 pub struct Searcher<E: Evaluator> {
@@ -29,8 +136,44 @@ pub struct Searcher<E: Evaluator> {
     start: Instant,
     limits: SearchLimits,
     history: Vec<u64>,
+    //keys of the game before the root, oldest first (see search_with_history)
+    game_history: Vec<u64>,
+    //index of the root position in history
+    root_index: usize,
     move_buf: Vec<Move>,
-    tt: TranspositionTable,
+    //negamax move buffers and searched quiet moves per ply, reused across nodes
+    move_lists: Vec<MoveList>,
+    quiet_lists: Vec<Vec<Move>>,
+    //scored quiescence moves per ply, same idea
+    qsearch_lists: Vec<Vec<(Move, i32)>>,
+    tt: Arc<TranspositionTable>,
+    //triangular pv table: pv[ply] is the best line found from ply onwards
+    pv: Vec<Vec<Move>>,
+    //shared with front-ends (UCI "stop"), checked in should_stop
+    stop: Arc<AtomicBool>,
+    //shared with front-ends: while set, node and time limits are suspended and the
+    //search doesn't return before stop; clearing it (UCI "ponderhit") starts the clock
+    infinite: Arc<AtomicBool>,
+    //ms after start from which max_time_ms counts, shared by all threads
+    clock_start_ms: Arc<AtomicU64>,
+    //nodes of all threads of the current search
+    shared_nodes: Arc<AtomicU64>,
+    //set by the main thread when it is done, ends the helper threads
+    search_done: Arc<AtomicBool>,
+    threads: usize,
+    config: SearchConfig,
+    ordering: MoveOrdering,
+    //move played at each ply of the current line (NULL for a null move), for countermoves
+    ply_moves: Vec<Move>,
+    //front-end hook, only called by the main thread
+    on_iteration: Option<IterationCallback>,
+    //root moves already reported as earlier multipv lines
+    root_excluded: Vec<Move>,
+    //soft budget of a clock search, main thread only
+    time: Option<TimeManager>,
+    tablebase: Option<Arc<Tablebase>>,
+    //root moves left by the tablebase, empty: all of them
+    root_filter: Vec<Move>,
 }
 
 impl<E: Evaluator> Searcher<E> {
@@ -43,19 +186,136 @@ impl<E: Evaluator> Searcher<E> {
                 max_depth: 1,
                 max_nodes: None,
                 max_time_ms: None,
+                multi_pv: 1,
+                clock: None,
             },
             history: Vec::new(),
+            game_history: Vec::new(),
+            root_index: 0,
             move_buf: Vec::new(),
-            tt: TranspositionTable::new_mb(64),
+            move_lists: Vec::new(),
+            quiet_lists: Vec::new(),
+            qsearch_lists: Vec::new(),
+            tt: Arc::new(TranspositionTable::new_mb(64)),
+            pv: Vec::new(),
+            stop: Arc::new(AtomicBool::new(false)),
+            infinite: Arc::new(AtomicBool::new(false)),
+            clock_start_ms: Arc::new(AtomicU64::new(0)),
+            shared_nodes: Arc::new(AtomicU64::new(0)),
+            search_done: Arc::new(AtomicBool::new(false)),
+            threads: 1,
+            config: SearchConfig::default(),
+            ordering: MoveOrdering::new(),
+            ply_moves: Vec::new(),
+            on_iteration: None,
+            root_excluded: Vec::new(),
+            time: None,
+            tablebase: None,
+            root_filter: Vec::new(),
         }
     }
 
+    //handle for other threads to abort a running search, the caller is responsible
+    //for clearing it again before the next search
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.stop)
+    }
+
+    //handle to run a search in infinite/ponder mode, has to be set before the search
+    //starts; like the stop flag it is never cleared by the searcher
+    pub fn infinite_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.infinite)
+    }
+
+    //number of threads used by search_smp (main thread included)
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    //shared by all threads, None to search without tablebases
+    pub fn set_tablebase(&mut self, tablebase: Option<Arc<Tablebase>>) {
+        self.tablebase = tablebase;
+    }
+
+    pub fn config(&self) -> SearchConfig {
+        self.config
+    }
+
+    pub fn set_config(&mut self, config: SearchConfig) {
+        self.config = config;
+    }
+
+    //called after every completed iteration, e.g. for UCI "info" lines
+    pub fn set_on_iteration(&mut self, callback: IterationCallback) {
+        self.on_iteration = Some(callback);
+    }
+
+    pub fn clear_on_iteration(&mut self) {
+        self.on_iteration = None;
+    }
+
     pub fn search(&mut self, pos: &mut Position, limits: SearchLimits) -> SearchResult {
+        self.search_with_history(pos, limits, &[])
+    }
+
+    //prior_keys: zobrist keys of the positions the game went through before pos, oldest
+    //first (pos itself not included); keys before the last irreversible move are ignored
+    pub fn search_with_history(
+        &mut self,
+        pos: &mut Position,
+        limits: SearchLimits,
+        prior_keys: &[u64],
+    ) -> SearchResult {
+        self.set_game_history(pos, prior_keys);
+        self.begin(limits);
+        let mut result = self.iterate(pos, 1);
+        self.wait_while_infinite();
+        self.flush_nodes();
+        result.nodes = self.shared_nodes.load(Ordering::Relaxed);
+        result
+    }
+
+    fn set_game_history(&mut self, pos: &Position, prior_keys: &[u64]) {
+        let reversible = prior_keys.len().min(pos.half_move_clock as usize);
+        self.game_history.clear();
+        self.game_history.extend_from_slice(&prior_keys[prior_keys.len() - reversible..]);
+    }
+
+    fn begin(&mut self, limits: SearchLimits) {
         self.limits = limits;
+        //the hard budget is just another time limit, so helpers respect it too
+        self.time = limits.clock.map(|clock| TimeManager::new(&clock));
+        if let Some(time) = &self.time {
+            let hard = time.budget().hard_ms;
+            self.limits.max_time_ms = Some(self.limits.max_time_ms.map_or(hard, |ms| ms.min(hard)));
+        }
         self.nodes = 0;
         self.start = Instant::now();
+        let clock_start = if self.infinite.load(Ordering::Relaxed) {
+            CLOCK_NOT_STARTED
+        } else {
+            0
+        };
+        self.clock_start_ms.store(clock_start, Ordering::Relaxed);
+        self.shared_nodes.store(0, Ordering::Relaxed);
+        self.search_done.store(false, Ordering::Relaxed);
+        self.ordering.new_search();
+    }
+
+    //iterative deepening from first_depth up to limits.max_depth
+    fn iterate(&mut self, pos: &mut Position, first_depth: u8) -> SearchResult {
+        //the accumulator only lives for the search, pos goes back the way it came
+        let outer = pos.accumulator.take();
+        self.eval.attach(pos);
+        let result = self.iterate_attached(pos, first_depth);
+        pos.accumulator = outer;
+        result
+    }
 
+    fn iterate_attached(&mut self, pos: &mut Position, first_depth: u8) -> SearchResult {
         self.history.clear();
+        self.history.extend_from_slice(&self.game_history);
+        self.root_index = self.history.len();
         self.history.push(pos.zobrist);
 
         if pos.half_move_clock >= 100 {
@@ -64,6 +324,8 @@ impl<E: Evaluator> Searcher<E> {
                 score_cp: 0,
                 depth: 0,
                 nodes: 0,
+                pv: Vec::new(),
+                lines: Vec::new(),
             };
         }
 
@@ -77,36 +339,97 @@ impl<E: Evaluator> Searcher<E> {
             );
         }
 
-        let mut best_move = Move::NULL;
+        //multipv can't show more lines than there are legal moves
+        let mut root_moves = Vec::new();
+        generate_legal_moves_in_place(pos, &mut root_moves);
+        //in a tablebase position only the moves keeping the best outcome are searched
+        self.root_filter.clear();
+        if let Some(tb) = &self.tablebase
+            && let Some(moves) = tb.dtz_optimal_moves(pos, &root_moves)
+        {
+            root_moves.clone_from(&moves);
+            self.root_filter = moves;
+        }
+        let line_count = self.limits.multi_pv.clamp(1, root_moves.len().max(1));
+
+        let mut lines: Vec<PvLine> = Vec::new();
         let mut best_score = 0;
         let mut reached_depth = 0;
 
-        for d in 1..=self.limits.max_depth {
+        for d in first_depth..=self.limits.max_depth {
             if self.should_stop() {
                 break;
             }
-            let (mv, sc, complete) = self.root(pos, d as i32);
+
+            //line i is searched with the best moves of lines 0..i excluded at the root
+            let mut new_lines: Vec<PvLine> = Vec::with_capacity(line_count);
+            let mut complete = true;
+            let mut terminal_score = None;
+            self.root_excluded.clear();
+            for i in 0..line_count {
+                let prev_score = lines.get(i).map(|line| line.score_cp);
+                let (mv, sc, done) = match prev_score {
+                    Some(prev)
+                        if self.config.aspiration
+                            && d >= ASPIRATION_MIN_DEPTH
+                            && !Self::is_mate_score(prev) =>
+                    {
+                        self.aspiration(pos, d as i32, prev)
+                    }
+                    _ => self.root(pos, d as i32, -INF, INF),
+                };
+
+                if !done {
+                    complete = false;
+                    break;
+                }
+                //no legal move at all: mate or stalemate
+                if mv.is_null() {
+                    terminal_score = Some(sc);
+                    break;
+                }
+                new_lines.push(PvLine {
+                    best_move: mv,
+                    score_cp: sc,
+                    pv: self.pv[0].clone(),
+                });
+                self.root_excluded.push(mv);
+            }
+            self.root_excluded.clear();
 
             if !complete {
                 break;
             }
 
-            if mv.is_null() {
-                if best_move.is_null() {
+            if let Some(sc) = terminal_score {
+                if lines.is_empty() {
                     best_score = sc;
                     reached_depth = d;
                 }
                 break;
             }
 
-            best_move = mv;
-            best_score = sc;
+            //later lines can come out better than earlier ones (search instability)
+            new_lines.sort_by_key(|line| -line.score_cp);
+            lines = new_lines;
+            best_score = lines[0].score_cp;
             reached_depth = d;
+            self.report_lines(d, &lines);
 
             if self.should_stop() {
                 break;
             }
 
+            let elapsed = self.clock_elapsed_ms();
+            if let Some(time) = self.time.as_mut() {
+                time.on_iteration(lines[0].best_move, best_score);
+                //a forced move needs no thinking
+                let forced = root_moves.len() == 1;
+                if elapsed.is_some_and(|ms| forced || !time.should_continue(ms)) {
+                    break;
+                }
+            }
+
             #[cfg(debug_assertions)]
             {
                 debug_assert_eq!(
@@ -117,24 +440,79 @@ impl<E: Evaluator> Searcher<E> {
                 );
             }
         }
+        let (best_move, best_pv) = match lines.first() {
+            Some(line) => (line.best_move, line.pv.clone()),
+            None => (Move::NULL, Vec::new()),
+        };
         SearchResult {
             best_move,
             score_cp: best_score,
             depth: reached_depth,
-            nodes: self.nodes,
+            nodes: self.total_nodes(),
+            pv: best_pv,
+            lines,
         }
     }
 
-    fn root(&mut self, pos: &mut Position, depth: i32) -> (Move, i32, bool) {
-        const TT_BONUS: i32 = 1_000_000;
-        
-        let side_to_move = pos.player_to_move;
+    //root search in a narrow window around the last score, widened until the score fits
+    fn aspiration(&mut self, pos: &mut Position, depth: i32, prev_score: i32) -> (Move, i32, bool) {
+        let mut delta = ASPIRATION_DELTA;
+        let mut alpha = prev_score - delta;
+        let mut beta = prev_score + delta;
+
+        loop {
+            let (mv, sc, complete) = self.root(pos, depth, alpha, beta);
+            if !complete {
+                return (mv, sc, complete);
+            }
+
+            let failed_low = sc <= alpha && alpha > -INF;
+            let failed_high = sc >= beta && beta < INF;
+            if !failed_low && !failed_high {
+                return (mv, sc, complete);
+            }
+
+            delta *= 2;
+            if delta > ASPIRATION_MAX_DELTA {
+                alpha = -INF;
+                beta = INF;
+            } else if failed_low {
+                alpha = (sc - delta).max(-INF);
+            } else {
+                beta = (sc + delta).min(INF);
+            }
+        }
+    }
+
+    fn report_lines(&mut self, depth: u8, lines: &[PvLine]) {
+        let nodes = self.total_nodes();
+        let elapsed_ms = self.start.elapsed().as_millis() as u64;
+        if let Some(callback) = self.on_iteration.as_mut() {
+            for (i, line) in lines.iter().enumerate() {
+                callback(&IterationInfo {
+                    depth,
+                    multipv: i + 1,
+                    score_cp: line.score_cp,
+                    nodes,
+                    pv: &line.pv,
+                    elapsed_ms,
+                });
+            }
+        }
+    }
+
+    //window (alpha, beta) is (-INF, INF) unless called from aspiration
+    fn root(&mut self, pos: &mut Position, depth: i32, alpha: i32, beta: i32) -> (Move, i32, bool) {
         let root_key = pos.zobrist;
 
         let tt_best = self.tt.probe(root_key).map(|e| e.best).unwrap_or(Move::NULL);
+        self.clear_pv(0);
 
         self.move_buf.clear();
-        generate_pseudo_legal_moves_in_place(pos, &mut self.move_buf);
+        generate_legal_moves(pos, &mut self.move_buf);
+        if !self.root_filter.is_empty() {
+            self.move_buf.retain(|mv| self.root_filter.contains(mv));
+        }
         let mut complete = true;
 
         if self.move_buf.is_empty() {
@@ -143,22 +521,19 @@ impl<E: Evaluator> Searcher<E> {
             return (Move::NULL, sc, complete);
         }
 
-        // Build + sort ordered move list (captures/promos first via move_order_score)
+        // Build + sort ordered move list (tt move, captures/promos, then quiets by history)
         let mut scored_moves: Vec<(Move, i32)> = self
             .move_buf
             .iter()
-            .map(|&m| {
-                let tt_bonus = if m == tt_best { TT_BONUS } else { 0 };
-                (m, Self::move_order_score(pos, m) + tt_bonus)
-            })
+            .map(|&m| (m, self.ordering_score(pos, m, 0, tt_best)))
             .collect();
 
         scored_moves.sort_by_key(|&(_, s)| -s);
         // let scored_moves_clone = scored_moves.clone();
 
+        let orig_alpha = alpha;
         let mut best_mv = Move::NULL;
-        let mut alpha = -INF;
-        let beta = INF;
+        let mut alpha = alpha;
 
         let mut first = true;
         let mut any_legal = false;
@@ -168,30 +543,28 @@ impl<E: Evaluator> Searcher<E> {
                 complete = false;
                 break;
             }
-
-            let undo = pos.make_move_with_undo(mv);
-
-            //check legality
-            if is_in_check(pos, side_to_move) {
-                pos.undo_move(undo);
+            if self.root_excluded.contains(&mv) {
                 continue;
             }
+
+            let undo = pos.make_move_with_undo(mv);
             any_legal = true;
 
             self.history.push(pos.zobrist);
+            self.set_ply_move(0, mv);
 
             // - First move: full window
             // - Others: null-window search; if it improves alpha, re-search full window
-            let score = if first {
+            let score = if first || !self.config.pvs {
                 first = false;
-                -self.negamax(pos, depth - 1, 1, -beta, -alpha)
+                -self.negamax(pos, depth - 1, 1, -beta, -alpha, true)
             } else {
                 // Null-window probe: in negamax the child window is (-alpha-1, -alpha)
-                let mut s = -self.negamax(pos, depth - 1, 1, -alpha - 1, -alpha);
+                let mut s = -self.negamax(pos, depth - 1, 1, -alpha - 1, -alpha, true);
 
                 if s > alpha {
                     // Re-search with full window to get exact score
-                    s = -self.negamax(pos, depth - 1, 1, -beta, -alpha);
+                    s = -self.negamax(pos, depth - 1, 1, -beta, -alpha, true);
                 }
                 s
             };
@@ -199,9 +572,20 @@ impl<E: Evaluator> Searcher<E> {
             self.history.pop();
             pos.undo_move(undo);
 
+            //a child cut short by the limits returns a meaningless score
+            if self.should_stop() {
+                complete = false;
+                break;
+            }
+
             if score > alpha {
                 alpha = score;
                 best_mv = mv;
+                self.update_pv(0, mv);
+            }
+            //fail high, aspiration widens the window and searches again
+            if alpha >= beta {
+                break;
             }
         }
 
@@ -213,9 +597,17 @@ impl<E: Evaluator> Searcher<E> {
             return  (Move::NULL, sc, complete);
         }
 
-        //root is an exact result if complete
-        if complete {
-            self.tt.store(root_key, depth, Self::to_tt_score(alpha, 0), Bound::Exact, best_mv);   
+        //root is an exact result if complete and inside the window;
+        //with excluded moves it is not the score of the position
+        if complete && self.root_excluded.is_empty() {
+            let bound = if alpha <= orig_alpha {
+                Bound::Upper
+            } else if alpha >= beta {
+                Bound::Lower
+            } else {
+                Bound::Exact
+            };
+            self.tt.store(root_key, depth, Self::to_tt_score(alpha, 0), bound, best_mv);
         }
 
         (best_mv, alpha, complete)
@@ -228,8 +620,10 @@ impl<E: Evaluator> Searcher<E> {
         ply: i32,
         mut alpha: i32,
         beta: i32,
+        allow_null: bool,
     ) -> i32 {
-        self.nodes += 1;
+        self.count_node();
+        self.clear_pv(ply as usize);
         if self.should_stop() {
             return self.eval_stm(pos);
         }
@@ -241,8 +635,15 @@ impl<E: Evaluator> Searcher<E> {
         if self.is_repetition(pos.zobrist) {
             return 0;
         }
+
+        let side_to_move = pos.player_to_move;
+        let in_check = is_in_check(pos, side_to_move);
+        let mut depth = depth;
+        if in_check && self.config.check_extensions {
+            depth += 1;
+        }
         if depth <= 0 {
-            return self.quiescence(pos, ply, alpha, beta);
+            return self.quiescence(pos, ply, alpha, beta, true);
         }
 
         let key = pos.zobrist;
@@ -250,12 +651,16 @@ impl<E: Evaluator> Searcher<E> {
         let orig_beta = beta;
         let mut beta = beta;
 
+        //no cutoffs in pv nodes: they would cut the pv short, and with several
+        //threads writing the TT that happens on almost every iteration
+        let pv_node = beta - alpha > 1;
+
         //TT probe (bestmove + possible cutoff)
         let mut tt_best = Move::NULL;
         if let Some(entry) = self.tt.probe(key) {
             tt_best = entry.best;
 
-            if (entry.depth as i32) >= depth {
+            if !pv_node && (entry.depth as i32) >= depth {
                 let tt_score = Self::from_tt_score(entry.score, ply);
 
                 match entry.bound {
@@ -277,51 +682,166 @@ impl<E: Evaluator> Searcher<E> {
                 }
             }
         }
-        let side_to_move = pos.player_to_move;
 
-        self.move_buf.clear();
-        generate_pseudo_legal_moves_in_place(pos, &mut self.move_buf);
+        //tablebase: the tables ignore the fifty move counter, so the outcome is only
+        //sure right after a capture or pawn move
+        let mut min_score = -INF;
+        let mut max_score = INF;
+        if pos.half_move_clock == 0
+            && let Some(wdl) = self.tablebase.as_ref().and_then(|tb| tb.probe_wdl(pos))
+        {
+            let (score, bound) = match wdl {
+                Wdl::Win => (TB_WIN - ply, Bound::Lower),
+                Wdl::Loss => (-TB_WIN + ply, Bound::Upper),
+                //cursed wins and blessed losses are drawn by the fifty move rule
+                _ => (2 * wdl.signum(), Bound::Exact),
+            };
+            let cutoff = match bound {
+                Bound::Exact => true,
+                Bound::Lower => score >= beta,
+                Bound::Upper => score <= alpha,
+            };
+            if cutoff {
+                self.tt.store(key, depth, score, bound, Move::NULL);
+                return score;
+            }
+            //otherwise the search can only sharpen the bound
+            if bound == Bound::Lower {
+                min_score = score;
+                alpha = alpha.max(score);
+            } else {
+                max_score = score;
+            }
+        }
 
-        if self.move_buf.is_empty() {
-            let s = self.terminal_score(pos, ply);
+        //static eval is only needed by the pruning below, which is off in pv nodes and in check
+        let prunable = !pv_node && !in_check;
+        let static_eval = if prunable {
+            self.eval_stm(pos)
+        } else {
+            -INF
+        };
 
-            self.tt.store(key, depth, Self::to_tt_score(s, ply), Bound::Exact, Move::NULL);
-            return s;
+        //reverse futility: far above beta at low depth, assume it holds
+        if prunable
+            && self.config.reverse_futility
+            && depth <= RFP_MAX_DEPTH
+            && !Self::is_mate_score(beta)
+            && static_eval - RFP_MARGIN * depth >= beta
+        {
+            return static_eval;
         }
 
-        //self.move_buf.sort_by_key(|&m| -Self::move_order_score(pos, m));
+        //null move: if passing still fails high, a real move will too;
+        //not without pieces (zugzwang in pawn endings) and never twice in a row
+        if prunable
+            && allow_null
+            && self.config.null_move
+            && depth >= NULL_MOVE_MIN_DEPTH
+            && static_eval >= beta
+            && !Self::is_mate_score(beta)
+            && Self::has_non_pawn_material(pos, side_to_move)
+        {
+            let reduction = 2 + depth / 4;
+            let undo = pos.make_null_move();
+            self.history.push(pos.zobrist);
+            self.set_ply_move(ply as usize, Move::NULL);
 
-        let mut scored_moves: Vec<(Move, i32)> = self
-            .move_buf
-            .iter()
-            .map(|&m| {
-                let tt_bonus = if m == tt_best { 1_000_000 } else {0 };
-                (m, Self::move_order_score(pos, m) + tt_bonus)
-            }).collect();
+            let score = -self.negamax(pos, depth - 1 - reduction, ply + 1, -beta, -beta + 1, false);
 
-        scored_moves.sort_by_key(|&(_, score)| -score);
+            self.history.pop();
+            pos.undo_null_move(undo);
 
-        let mut any_legal = false;
+            if score >= beta {
+                //unproven mates from a null move search are not trusted
+                return if Self::is_mate_score(score) { beta } else { score };
+            }
+        }
+
+        //futility: quiet moves can't lift a hopeless static eval above alpha
+        let futile = prunable
+            && self.config.futility
+            && depth < FUTILITY_MARGINS.len() as i32
+            && !Self::is_mate_score(alpha)
+            && static_eval + FUTILITY_MARGINS[depth as usize] <= alpha;
+
+        //buffers of this ply, handed back before returning
+        let p = ply as usize;
+        if self.move_lists.len() <= p {
+            self.move_lists.resize_with(p + 1, MoveList::default);
+            self.quiet_lists.resize_with(p + 1, Vec::new);
+        }
+        let killers = if self.config.quiet_ordering {
+            self.ordering.killers(p)
+        } else {
+            [Move::NULL; 2]
+        };
+        let mut picker = MovePicker::new(std::mem::take(&mut self.move_lists[p]), tt_best, killers);
+        let mut tried_quiets = std::mem::take(&mut self.quiet_lists[p]);
+        tried_quiets.clear();
+
+        let mut moves_searched = 0;
         let mut best_mv = Move::NULL;
         let mut aborted = false;
 
-        for (mv, _) in scored_moves {
+        while let Some(mv) = picker.next(pos, |m| self.ordering_score(pos, m, p, Move::NULL)) {
             if self.should_stop() {
                 aborted = true;
                 break;
             }
+            let is_quiet = Self::is_quiet(pos, mv);
+
             let undo = pos.make_move_with_undo(mv);
+            let gives_check = is_in_check(pos, pos.player_to_move);
 
-            //check legality inline, instead of filtering legal before
-            if is_in_check(pos, side_to_move) {
+            if futile && moves_searched > 0 && is_quiet && !gives_check {
                 pos.undo_move(undo);
                 continue;
             }
-            any_legal = true;
 
             self.history.push(pos.zobrist);
+            self.set_ply_move(ply as usize, mv);
 
-            let score = -self.negamax(pos, depth - 1, ply + 1, -beta, -alpha);
+            let new_depth = depth - 1;
+            let score = if moves_searched == 0 {
+                -self.negamax(pos, new_depth, ply + 1, -beta, -alpha, true)
+            } else {
+                //late quiet moves are searched shallower first
+                let reduction = if self.config.lmr
+                    && depth >= LMR_MIN_DEPTH
+                    && moves_searched >= LMR_MIN_MOVES
+                    && is_quiet
+                    && !in_check
+                    && !gives_check
+                {
+                    if moves_searched >= 2 * LMR_MIN_MOVES && depth >= 2 * LMR_MIN_DEPTH {
+                        2
+                    } else {
+                        1
+                    }
+                } else {
+                    0
+                };
+
+                //alpha + 1 = "not refuted yet", forces the next stage
+                let mut s = alpha + 1;
+                if reduction > 0 {
+                    s = -self.negamax(pos, new_depth - reduction, ply + 1, -alpha - 1, -alpha, true);
+                }
+                let needs_full_window = if self.config.pvs {
+                    if s > alpha {
+                        s = -self.negamax(pos, new_depth, ply + 1, -alpha - 1, -alpha, true);
+                    }
+                    s > alpha && s < beta
+                } else {
+                    s > alpha
+                };
+                if needs_full_window {
+                    s = -self.negamax(pos, new_depth, ply + 1, -beta, -alpha, true);
+                }
+                s
+            };
+            moves_searched += 1;
 
             self.history.pop();
             pos.undo_move(undo);
@@ -329,148 +849,190 @@ impl<E: Evaluator> Searcher<E> {
             if score > alpha {
                 alpha = score;
                 best_mv = mv;
+                self.update_pv(ply as usize, mv);
+            }
+            if is_quiet {
+                tried_quiets.push(mv);
             }
             if alpha >= beta {
+                if is_quiet && self.config.quiet_ordering && !self.should_stop() {
+                    let prev = self.prev_move(ply as usize);
+                    self.ordering
+                        .update_on_cutoff(side_to_move, mv, ply as usize, prev, depth, &tried_quiets);
+                }
                 break;
             }
         }
+        self.move_lists[p] = picker.into_list();
+        self.quiet_lists[p] = tried_quiets;
 
-        if !any_legal {
+        if moves_searched == 0 && !aborted {
+            //futility never prunes the first legal move, so this is mate or stalemate
             let s = self.terminal_score(pos, ply);
             self.tt.store(key, depth, Self::to_tt_score(s, ply), Bound::Exact, Move::NULL);
             return s;
         }
         //TT store (only if not aborted by time/nodes)
+        //a result held at a tablebase bound is only that bound
+        let score = alpha.min(max_score);
         if !aborted {
-            let bound = if alpha <= orig_alpha {
+            let bound = if score <= orig_alpha || score == max_score {
                 Bound::Upper
-            } else if alpha >= orig_beta {
+            } else if score >= orig_beta || score == min_score {
                 Bound::Lower
             } else {
                 Bound::Exact
             };
-            self.tt.store(key, depth, Self::to_tt_score(alpha, ply), bound, best_mv);
+            self.tt.store(key, depth, Self::to_tt_score(score, ply), bound, best_mv);
         }
-        alpha
+        score
     }
 
-    fn quiescence(&mut self, pos: &mut Position, ply: i32, mut alpha: i32, beta: i32) -> i32 {
-        self.nodes += 1;
+    //checks: also search quiet checking moves, only at the first qsearch ply
+    fn quiescence(&mut self, pos: &mut Position, ply: i32, mut alpha: i32, beta: i32, checks: bool) -> i32 {
+        self.count_node();
+        //the pv ends where quiescence starts
+        self.clear_pv(ply as usize);
         if self.should_stop() {
             return self.eval_stm(pos);
         }
 
-        //if in check also allow evasion not only captures
-        let side_to_move = pos.player_to_move;
-        if is_in_check(pos, side_to_move) {
-            self.move_buf.clear();
-            generate_pseudo_legal_moves_in_place(pos, &mut self.move_buf);
+        //every entry is at least as deep as a qsearch node (depth 0)
+        let key = pos.zobrist;
+        let orig_alpha = alpha;
+        if let Some(entry) = self.tt.probe(key) {
+            let tt_score = Self::from_tt_score(entry.score, ply);
+            let usable = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => tt_score >= beta,
+                Bound::Upper => tt_score <= alpha,
+            };
+            if usable {
+                return tt_score;
+            }
+        }
 
+        //if in check also allow evasion not only captures
+        let in_check = is_in_check(pos, pos.player_to_move);
+        self.move_buf.clear();
+        if in_check {
+            generate_legal_moves(pos, &mut self.move_buf);
             if self.move_buf.is_empty() {
-                return -MATE + ply as i32;
+                return -MATE + ply;
             }
-
-            //self.move_buf.sort_by_key(|&m| -Self::move_order_score(pos, m));
-
-            let mut scored_moves: Vec<(Move, i32)> = self
-                .move_buf
-                .iter()
-                .map(|&m| (m, Self::move_order_score(pos, m)))
-                .collect();
-
-            scored_moves.sort_by_key(|&(_, score)| -score);
-
-            let mut any_legal = false;
-
-            for (mv, _) in scored_moves {
-                if self.should_stop() {
-                    break;
-                }
-
-                let undo = pos.make_move_with_undo(mv);
-
-                //legality check (did we leave the king in check?)
-                if is_in_check(pos, side_to_move) {
-                    pos.undo_move(undo);
-                    continue;
-                }
-                any_legal = true;
-
-                self.history.push(pos.zobrist);
-
-                let score = -self.quiescence(pos, ply + 1, -beta, -alpha);
-
-                self.history.pop();
-                pos.undo_move(undo);
-
-                if score > alpha {
-                    alpha = score;
-                }
-                if alpha >= beta {
-                    return beta;
-                }
-                
+        } else {
+            //stand-pat
+            let stand_pat = self.eval_stm(pos);
+            if stand_pat >= beta {
+                self.tt.store(key, 0, Self::to_tt_score(beta, ply), Bound::Lower, Move::NULL);
+                return beta;
+            }
+            if stand_pat > alpha {
+                alpha = stand_pat;
             }
 
-            if !any_legal {
-                return -MATE + ply;
+            generate_legal_captures(pos, &mut self.move_buf);
+            if checks {
+                generate_legal_checks(pos, &mut self.move_buf);
             }
-            return alpha;
+            //captures that lose material can't raise the stand-pat score (SEE), neither can
+            //ones that stay below alpha even with the captured piece and a margin (delta)
+            self.move_buf.retain(|&m| {
+                if m.is_promotion() {
+                    return true;
+                }
+                if !see_ge(pos, m, 0) {
+                    return false;
+                }
+                Self::is_quiet(pos, m) || stand_pat + Self::captured_value(pos, m) + DELTA_MARGIN > alpha
+            });
         }
 
-        //stand-pat
-        let stand_pat = self.eval_stm(pos);
-        if stand_pat >= beta {
-            return beta;
+        //move_buf is reused by the children, so the scored moves live in this ply's buffer
+        let p = ply as usize;
+        if self.qsearch_lists.len() <= p {
+            self.qsearch_lists.resize_with(p + 1, Vec::new);
         }
-        if stand_pat > alpha {
-            alpha = stand_pat;
-        }
-        self.move_buf.clear();
-        generate_pseudo_legal_moves_in_place(pos, &mut self.move_buf);
-        //self.move_buf.sort_by_key(|&m| -Self::move_order_score(pos, m));
-        let mut scored_moves: Vec<(Move, i32)> = self
-            .move_buf
-            .iter()
-            .filter(|&&m| {
-                m.is_promotion()
-                    || m.is_en_passant()
-                    || matches!(pos.board[m.to_sq()], Cell::Piece(_))
-            })
-            .map(|&m| (m, Self::move_order_score(pos, m)))
-            .collect();
-
+        let mut scored_moves = std::mem::take(&mut self.qsearch_lists[p]);
+        scored_moves.clear();
+        scored_moves.extend(self.move_buf.iter().map(|&m| (m, Self::move_order_score(pos, m))));
         scored_moves.sort_by_key(|&(_, score)| -score);
-        for (mv, _) in scored_moves {
+
+        let mut best_mv = Move::NULL;
+        //set when the node returns early, after the buffer is handed back
+        let mut early = None;
+        for &(mv, _) in &scored_moves {
             if self.should_stop() {
+                early = Some(alpha);
                 break;
             }
 
             let undo = pos.make_move_with_undo(mv);
-
-            //legality check
-            if is_in_check(pos, side_to_move) {
-                pos.undo_move(undo);
-                continue;
-            }
-
             self.history.push(pos.zobrist);
 
-            let score = -self.quiescence(pos, ply + 1, -beta, -alpha);
+            let score = -self.quiescence(pos, ply + 1, -beta, -alpha, false);
 
             self.history.pop();
             pos.undo_move(undo);
 
+            if self.should_stop() {
+                early = Some(alpha);
+                break;
+            }
             if score >= beta {
-                return beta;
+                self.tt.store(key, 0, Self::to_tt_score(beta, ply), Bound::Lower, mv);
+                early = Some(beta);
+                break;
             }
             if score > alpha {
                 alpha = score;
-            }   
+                best_mv = mv;
+            }
+        }
+        self.qsearch_lists[p] = scored_moves;
+        if let Some(score) = early {
+            return score;
         }
+
+        let bound = if alpha > orig_alpha { Bound::Exact } else { Bound::Upper };
+        self.tt.store(key, 0, Self::to_tt_score(alpha, ply), bound, best_mv);
         alpha
     }
 
+    //material a capture removes, for delta pruning
+    #[inline]
+    fn captured_value(pos: &Position, mv: Move) -> i32 {
+        match pos.board[mv.to_sq()] {
+            Cell::Piece(victim) => Self::piece_value(victim.kind),
+            _ if mv.is_en_passant() => Self::piece_value(PieceKind::Pawn),
+            _ => 0,
+        }
+    }
+
+    //zugzwang guard for null moves: only pawns (and the king) left
+    #[inline]
+    fn has_non_pawn_material(pos: &Position, color: Color) -> bool {
+        //piece_counter order: P, N, B, R, Q, K per color
+        let base = color.idx() * 6;
+        pos.piece_counter[base + 1..base + 5].iter().any(|&n| n > 0)
+    }
+
+    fn clear_pv(&mut self, ply: usize) {
+        if self.pv.len() <= ply + 1 {
+            self.pv.resize(ply + 2, Vec::new());
+        }
+        self.pv[ply].clear();
+    }
+
+    //pv[ply] = mv followed by the line of the child
+    fn update_pv(&mut self, ply: usize, mv: Move) {
+        let (head, tail) = self.pv.split_at_mut(ply + 1);
+        let line = &mut head[ply];
+        line.clear();
+        line.push(mv);
+        line.extend_from_slice(&tail[0]);
+    }
+
     fn terminal_score(&mut self, pos: &Position, ply: i32) -> i32 {
         let stm = pos.player_to_move;
         if is_in_check(pos, stm) {
@@ -514,26 +1076,130 @@ impl<E: Evaluator> Searcher<E> {
         }
     }
 
+    //a position that already occurred since the root (root included) is scored as a draw
+    //right away, the side that repeated it can do so again; one that only occurred in
+    //the game before the root has to be there twice, that is the threefold repetition
     #[inline]
     fn is_repetition(&self, key: u64) -> bool {
-        self.history.iter().rev().skip(1).any(|&k| k == key)
+        let last = self.history.len().saturating_sub(1);
+        let mut before_root = 0;
+        for (i, &k) in self.history[..last].iter().enumerate().rev() {
+            if k != key {
+                continue;
+            }
+            if i >= self.root_index {
+                return true;
+            }
+            before_root += 1;
+            if before_root == 2 {
+                return true;
+            }
+        }
+        false
+    }
+
+    #[inline]
+    fn count_node(&mut self) {
+        self.nodes += 1;
+        if self.nodes.is_multiple_of(NODE_BATCH) {
+            self.shared_nodes.fetch_add(NODE_BATCH, Ordering::Relaxed);
+        }
+    }
+
+    fn flush_nodes(&mut self) {
+        self.shared_nodes
+            .fetch_add(self.nodes % NODE_BATCH, Ordering::Relaxed);
+        self.nodes -= self.nodes % NODE_BATCH;
+    }
+
+    //published nodes of all threads plus the unpublished ones of this thread
+    #[inline]
+    fn total_nodes(&self) -> u64 {
+        self.shared_nodes.load(Ordering::Relaxed) + self.nodes % NODE_BATCH
     }
 
     fn should_stop(&self) -> bool {
+        if self.stop.load(Ordering::Relaxed) || self.search_done.load(Ordering::Relaxed) {
+            return true;
+        }
+        if self.infinite.load(Ordering::Relaxed) {
+            return false;
+        }
         if let Some(n) = self.limits.max_nodes {
-            if self.nodes >= n {
+            if self.total_nodes() >= n {
                 return true;
             }
         }
-        if let Some(ms) = self.limits.max_time_ms {
-            if self.start.elapsed().as_millis() as u64 >= ms {
-                return true;
-            }
+        match self.limits.max_time_ms {
+            Some(ms) => self.clock_elapsed_ms().is_some_and(|elapsed| elapsed >= ms),
+            None => false,
+        }
+    }
+
+    //time charged to the limits, None while an infinite/ponder search runs
+    fn clock_elapsed_ms(&self) -> Option<u64> {
+        if self.infinite.load(Ordering::Relaxed) {
+            return None;
+        }
+        let elapsed = self.start.elapsed().as_millis() as u64;
+        //the first check after a ponderhit starts the clock for all threads
+        let clock_start = match self.clock_start_ms.compare_exchange(
+            CLOCK_NOT_STARTED,
+            elapsed,
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+        ) {
+            Ok(_) => elapsed,
+            Err(start) => start,
+        };
+        Some(elapsed.saturating_sub(clock_start))
+    }
+
+    //an infinite search that ran out of depth still has to wait for stop or ponderhit
+    fn wait_while_infinite(&self) {
+        while self.infinite.load(Ordering::Relaxed) && !self.stop.load(Ordering::Relaxed) {
+            std::thread::sleep(Duration::from_millis(1));
         }
-        false
     }
 
     //ordering helpers
+
+    //full ordering key: tt move, then captures/promotions, then killers,
+    //countermove and history for quiet moves
+    fn ordering_score(&self, pos: &Position, mv: Move, ply: usize, tt_best: Move) -> i32 {
+        let tt_bonus = if mv == tt_best { TT_MOVE_BONUS } else { 0 };
+        let mut score = Self::move_order_score(pos, mv) + tt_bonus;
+        if self.config.quiet_ordering && Self::is_quiet(pos, mv) {
+            let prev = self.prev_move(ply);
+            score += self
+                .ordering
+                .quiet_score(pos.player_to_move, mv, ply, prev);
+        }
+        score
+    }
+
+    #[inline]
+    fn is_quiet(pos: &Position, mv: Move) -> bool {
+        !mv.is_promotion() && !mv.is_en_passant() && !matches!(pos.board[mv.to_sq()], Cell::Piece(_))
+    }
+
+    #[inline]
+    fn set_ply_move(&mut self, ply: usize, mv: Move) {
+        if self.ply_moves.len() <= ply {
+            self.ply_moves.resize(ply + 1, Move::NULL);
+        }
+        self.ply_moves[ply] = mv;
+    }
+
+    //move that led to the node at ply
+    #[inline]
+    fn prev_move(&self, ply: usize) -> Move {
+        match ply.checked_sub(1) {
+            Some(p) => self.ply_moves.get(p).copied().unwrap_or(Move::NULL),
+            None => Move::NULL,
+        }
+    }
+
     #[inline]
     fn piece_value(kind: PieceKind) -> i32 {
         match kind {
@@ -587,16 +1253,115 @@ impl<E: Evaluator> Searcher<E> {
                 s += 10000;
             }
         } else if let Cell::Piece(victim) = pos.board[mv.to_sq()] {
+            //captures losing material in the exchange come after the quiet moves
+            let base = if see_ge(pos, mv, 0) { 10000 } else { BAD_CAPTURE_BASE };
             if let Cell::Piece(att) = pos.board[mv.from_sq()] {
-                s += 10000 + Self::piece_value(victim.kind) - Self::piece_value(att.kind);
+                s += base + Self::piece_value(victim.kind) - Self::piece_value(att.kind);
             } else {
-                s += 10000 + Self::piece_value(victim.kind);
+                s += base + Self::piece_value(victim.kind);
             }
         }
         s
     }
 }
 
+//Lazy SMP: helper threads run the same iterative deepening on their own copy of
+//the position and only cooperate through the shared TT, the main thread decides
+impl<E: Evaluator + Clone + Send> Searcher<E> {
+    pub fn search_smp(&mut self, pos: &mut Position, limits: SearchLimits) -> SearchResult {
+        self.search_smp_with_history(pos, limits, &[])
+    }
+
+    //search_with_history on all threads
+    pub fn search_smp_with_history(
+        &mut self,
+        pos: &mut Position,
+        limits: SearchLimits,
+        prior_keys: &[u64],
+    ) -> SearchResult {
+        if self.threads <= 1 {
+            return self.search_with_history(pos, limits, prior_keys);
+        }
+        self.set_game_history(pos, prior_keys);
+        self.begin(limits);
+
+        let mut helpers: Vec<Searcher<E>> = (1..self.threads).map(|_| self.helper()).collect();
+        let (main_result, helper_results) = std::thread::scope(|s| {
+            let handles: Vec<_> = helpers
+                .iter_mut()
+                .enumerate()
+                .map(|(i, helper)| {
+                    let mut helper_pos = pos.clone();
+                    //every other helper skips depth 1 so the threads spread over the tree
+                    let first_depth = 1 + (i % 2) as u8;
+                    s.spawn(move || {
+                        let result = helper.iterate(&mut helper_pos, first_depth);
+                        helper.flush_nodes();
+                        result
+                    })
+                })
+                .collect();
+
+            let main_result = self.iterate(pos, 1);
+            self.wait_while_infinite();
+            self.search_done.store(true, Ordering::Relaxed);
+            self.flush_nodes();
+
+            let helper_results: Vec<SearchResult> = handles
+                .into_iter()
+                .map(|h| h.join().expect("search helper thread panicked"))
+                .collect();
+            (main_result, helper_results)
+        });
+
+        //a helper that completed a deeper iteration than the main thread wins
+        let main_depth = main_result.depth;
+        let mut best = main_result;
+        for result in helper_results {
+            if result.depth > best.depth && !result.best_move.is_null() {
+                best = result;
+            }
+        }
+        if best.depth > main_depth {
+            self.report_lines(best.depth, &best.lines);
+        }
+        best.nodes = self.shared_nodes.load(Ordering::Relaxed);
+        best
+    }
+
+    fn helper(&self) -> Self {
+        Self {
+            eval: self.eval.clone(),
+            nodes: 0,
+            start: self.start,
+            limits: self.limits,
+            history: Vec::new(),
+            game_history: self.game_history.clone(),
+            root_index: 0,
+            move_buf: Vec::new(),
+            move_lists: Vec::new(),
+            quiet_lists: Vec::new(),
+            qsearch_lists: Vec::new(),
+            tt: Arc::clone(&self.tt),
+            pv: Vec::new(),
+            stop: Arc::clone(&self.stop),
+            infinite: Arc::clone(&self.infinite),
+            clock_start_ms: Arc::clone(&self.clock_start_ms),
+            shared_nodes: Arc::clone(&self.shared_nodes),
+            search_done: Arc::clone(&self.search_done),
+            threads: 1,
+            config: self.config,
+            ordering: MoveOrdering::new(),
+            ply_moves: Vec::new(),
+            on_iteration: None,
+            root_excluded: Vec::new(),
+            time: None,
+            tablebase: self.tablebase.clone(),
+            root_filter: Vec::new(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -615,6 +1380,8 @@ mod tests {
             max_depth: 3,
             max_nodes: None,
             max_time_ms: None,
+            multi_pv: 1,
+            clock: None,
         };
 
         let result = searcher.search(&mut pos, limits);
@@ -638,6 +1405,8 @@ mod tests {
             max_depth: 2,
             max_nodes: None,
             max_time_ms: None,
+            multi_pv: 1,
+            clock: None,
         };
 
         let result = searcher.search(&mut pos, limits);
@@ -658,6 +1427,8 @@ mod tests {
             max_depth: 1,
             max_nodes: None,
             max_time_ms: None,
+            multi_pv: 1,
+            clock: None,
         };
 
         let result = searcher.search(&mut pos, limits);
@@ -679,6 +1450,8 @@ mod tests {
             max_depth: 3,
             max_nodes: None,
             max_time_ms: None,
+            multi_pv: 1,
+            clock: None,
         };
 
         let result = searcher.search(&mut pos, limits);
@@ -700,6 +1473,52 @@ mod tests {
         assert!(searcher.is_repetition(pos.zobrist));
     }
 
+    #[test]
+    fn test_repetition_before_root_needs_two_occurrences() {
+        let mut searcher = Searcher::new(ClassicalEval::new());
+        //game: 1 2 1 2, root 3, then 1 in the tree
+        searcher.history = vec![1, 2, 1, 2, 3, 1];
+        searcher.root_index = 4;
+        assert!(searcher.is_repetition(1));
+        searcher.history = vec![1, 2, 4, 2, 3, 1];
+        assert!(!searcher.is_repetition(1));
+        //twofold inside the tree, root included
+        searcher.history = vec![1, 2, 4, 2, 3, 5, 3];
+        assert!(searcher.is_repetition(3));
+    }
+
+    #[test]
+    fn test_game_history_lets_losing_side_claim_threefold() {
+        fn play(pos: &mut Position, uci: &str) {
+            let mut legal = Vec::new();
+            generate_legal_moves_in_place(pos, &mut legal);
+            let mv = legal.into_iter().find(|m| m.to_uci() == uci).unwrap();
+            pos.make_move(mv);
+        }
+
+        //white is a queen down, Kg1 repeats the position after it for the third time
+        let mut pos = Position::from_fen("k7/8/q7/8/8/8/8/7K w - - 0 1").unwrap();
+        let mut keys = Vec::new();
+        for uci in ["h1g1", "a6a5", "g1h1", "a5a6"].iter().cycle().take(8) {
+            keys.push(pos.zobrist);
+            play(&mut pos, uci);
+        }
+        let limits = SearchLimits { max_depth: 4, max_nodes: None, max_time_ms: None, multi_pv: 1, clock: None };
+
+        let mut searcher = Searcher::new(ClassicalEval::new());
+        let result = searcher.search_with_history(&mut pos.clone(), limits, &keys);
+        assert_eq!(result.best_move.to_uci(), "h1g1");
+        assert_eq!(result.score_cp, 0);
+
+        //without the history it is just a lost position
+        let mut searcher = Searcher::new(ClassicalEval::new());
+        assert!(searcher.search(&mut pos.clone(), limits).score_cp < -500);
+        //keys before the last irreversible move can't repeat
+        pos.half_move_clock = 0;
+        let mut searcher = Searcher::new(ClassicalEval::new());
+        assert!(searcher.search_with_history(&mut pos, limits, &keys).score_cp < -500);
+    }
+
     // Test 6: Respect Time Limit
     #[test]
     fn test_respects_time_limit() {
@@ -711,6 +1530,8 @@ mod tests {
             max_depth: 100,
             max_nodes: None,
             max_time_ms: Some(100),
+            multi_pv: 1,
+            clock: None,
         };
 
         let start = std::time::Instant::now();
@@ -733,6 +1554,8 @@ mod tests {
             max_depth: 100,
             max_nodes: Some(1000), // Only 1000 Nodes
             max_time_ms: None,
+            multi_pv: 1,
+            clock: None,
         };
 
         let result = searcher.search(&mut pos, limits);
@@ -788,6 +1611,109 @@ mod tests {
         assert!(promo_score > normal_score);
     }
 
+    #[test]
+    fn test_quiescence_finds_quiet_mating_check_at_first_ply() {
+        //Ra8# is neither a capture nor a promotion
+        let mut pos = Position::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let mut searcher = Searcher::new(ClassicalEval::new());
+        searcher.begin(SearchLimits { max_depth: 1, max_nodes: None, max_time_ms: None, multi_pv: 1, clock: None });
+
+        let with_checks = searcher.quiescence(&mut pos, 0, -INF, INF, true);
+        assert_eq!(with_checks, MATE - 1);
+
+        let mut searcher = Searcher::new(ClassicalEval::new());
+        searcher.begin(SearchLimits { max_depth: 1, max_nodes: None, max_time_ms: None, multi_pv: 1, clock: None });
+        let without_checks = searcher.quiescence(&mut pos, 0, -INF, INF, false);
+        assert!(without_checks < MATE - 100);
+    }
+
+    #[test]
+    fn test_quiescence_stores_depth_zero_entries() {
+        let mut pos = Position::from_fen("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1").unwrap();
+        let mut searcher = Searcher::new(ClassicalEval::new());
+        searcher.begin(SearchLimits { max_depth: 1, max_nodes: None, max_time_ms: None, multi_pv: 1, clock: None });
+
+        let score = searcher.quiescence(&mut pos, 0, -INF, INF, true);
+        let entry = searcher.tt.probe(pos.zobrist).expect("qsearch result stored");
+        assert_eq!(entry.depth, 0);
+        assert_eq!(entry.bound, Bound::Exact);
+        assert_eq!(Searcher::<ClassicalEval>::from_tt_score(entry.score, 0), score);
+        //the stored entry answers the next probe without searching
+        let nodes = searcher.nodes;
+        assert_eq!(searcher.quiescence(&mut pos, 0, -INF, INF, true), score);
+        assert_eq!(searcher.nodes, nodes + 1);
+    }
+
+    #[test]
+    fn test_move_ordering_puts_losing_captures_after_quiets() {
+        //Qxb6 loses the queen to the c7 pawn, Qxh4 wins a free rook
+        let pos = Position::from_fen("4k3/2p5/1p6/8/3Q3r/8/8/4K3 w - - 0 1").unwrap();
+        let score = |uci: &str| Searcher::<ClassicalEval>::move_order_score(&pos, Move::from_uci(uci).unwrap());
+
+        assert!(score("d4h4") > score("d4d5"));
+        assert!(score("d4b6") < score("d4d5"));
+        assert!(score("d4b6") < score("e1e2"));
+    }
+
+    #[test]
+    fn test_tablebase_scores_capture_into_table_as_win() {
+        use crate::tablebase::test_tables::kqk_tablebase;
+
+        //Qxd7 leaves kq vs k, which the table scores as a win
+        let mut pos = Position::from_fen("7k/3n4/8/8/8/3Q4/8/K7 w - - 0 1").unwrap();
+        let mut searcher = Searcher::new(ClassicalEval::new());
+        searcher.set_tablebase(Some(Arc::new(kqk_tablebase("search"))));
+        let limits = SearchLimits {
+            max_depth: 3,
+            max_nodes: None,
+            max_time_ms: None,
+            multi_pv: 1,
+            clock: None,
+        };
+
+        let result = searcher.search(&mut pos, limits);
+        assert_eq!(result.best_move, Move::from_uci("d3d7").unwrap());
+        assert_eq!(result.score_cp, TB_WIN - 1);
+    }
+
+    #[test]
+    fn test_tablebase_root_only_searches_dtz_optimal_moves() {
+        use crate::tablebase::test_tables::kqk_tablebase;
+
+        //several mates in one, the table keeps only those
+        let mut pos = Position::from_fen("7k/5K2/8/8/8/8/8/6Q1 w - - 0 1").unwrap();
+        let mut searcher = Searcher::new(ClassicalEval::new());
+        searcher.set_tablebase(Some(Arc::new(kqk_tablebase("search-root"))));
+        let limits = SearchLimits {
+            max_depth: 2,
+            max_nodes: None,
+            max_time_ms: None,
+            multi_pv: 8,
+            clock: None,
+        };
+
+        let result = searcher.search(&mut pos, limits);
+        assert_eq!(result.lines.len(), 4);
+        assert!(result.lines.iter().all(|line| line.score_cp == MATE - 1));
+    }
+
+    #[test]
+    fn test_tablebase_win_is_stored_as_lower_bound() {
+        use crate::tablebase::test_tables::kqk_tablebase;
+
+        //no mate within the horizon, so nothing beats the table score
+        let mut pos = Position::from_fen("7k/8/8/8/8/8/8/KQ6 w - - 0 1").unwrap();
+        let mut searcher = Searcher::new(ClassicalEval::new());
+        searcher.set_tablebase(Some(Arc::new(kqk_tablebase("search-bound"))));
+        searcher.begin(SearchLimits { max_depth: 2, max_nodes: None, max_time_ms: None, multi_pv: 1, clock: None });
+
+        let score = searcher.negamax(&mut pos, 2, 1, -INF, INF, true);
+        assert_eq!(score, TB_WIN - 1);
+        let entry = searcher.tt.probe(pos.zobrist).expect("tablebase node stored");
+        assert_eq!(entry.bound, Bound::Lower);
+        assert_eq!(Searcher::<ClassicalEval>::from_tt_score(entry.score, 1), score);
+    }
+
     // Test 10: Reached Depth ist correct with early Stop
     #[test]
     fn test_reached_depth_correct_on_early_stop() {
@@ -799,6 +1725,8 @@ mod tests {
             max_depth: 10,
             max_nodes: Some(100),
             max_time_ms: None,
+            multi_pv: 1,
+            clock: None,
         };
 
         let result = searcher.search(&mut pos, limits);
@@ -822,6 +1750,8 @@ mod tests {
             max_depth: 3,
             max_nodes: Some(5000),
             max_time_ms: None,
+            multi_pv: 1,
+            clock: None,
         };
 
         let result = searcher.search(&mut pos, limits);
@@ -862,6 +1792,8 @@ mod tests {
             max_depth: 1,
             max_nodes: None,
             max_time_ms: None,
+            multi_pv: 1,
+            clock: None,
         };
 
         let result = searcher.search(&mut pos, limits);
@@ -887,12 +1819,16 @@ mod tests {
     let mut pos_b = Position::from_fen(fen).unwrap();
 
     let mut s_no_tt = Searcher::new(ClassicalEval::new());
-    s_no_tt.tt = TranspositionTable::disabled();
+    s_no_tt.tt = Arc::new(TranspositionTable::disabled());
 
     let mut s_tt = Searcher::new(ClassicalEval::new());
-    s_tt.tt = TranspositionTable::new_mb(8);
+    s_tt.tt = Arc::new(TranspositionTable::new_mb(8));
+
+    //pruning and quiet ordering depend on what earlier searches left behind, so compare plain alpha-beta
+    s_no_tt.set_config(SearchConfig::plain());
+    s_tt.set_config(SearchConfig::plain());
 
-    let limits = SearchLimits { max_depth: 4, max_nodes: None, max_time_ms: None };
+    let limits = SearchLimits { max_depth: 4, max_nodes: None, max_time_ms: None, multi_pv: 1, clock: None };
 
     let r1 = s_no_tt.search(&mut pos_a, limits);
     let r2 = s_tt.search(&mut pos_b, limits);
@@ -901,6 +1837,288 @@ mod tests {
     assert_eq!(r1.score_cp, r2.score_cp);
     assert_eq!(r1.depth, r2.depth);
 }
+
+    #[test]
+    fn test_pv_starts_with_best_move_and_is_legal() {
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+        let mut pos = Position::from_fen(fen).unwrap();
+        let mut searcher = Searcher::new(ClassicalEval::new());
+
+        let limits = SearchLimits { max_depth: 4, max_nodes: None, max_time_ms: None, multi_pv: 1, clock: None };
+        let result = searcher.search(&mut pos, limits);
+
+        assert_eq!(result.pv.first(), Some(&result.best_move));
+        assert!(result.pv.len() >= 2);
+        assert!(result.pv.len() <= 4);
+
+        let mut legal = Vec::new();
+        for &mv in &result.pv {
+            generate_legal_moves_in_place(&mut pos, &mut legal);
+            assert!(legal.contains(&mv), "pv move {} is illegal", mv.to_uci());
+            pos.make_move(mv);
+        }
+    }
+
+    #[test]
+    fn test_smp_search_finds_mate_and_aggregates_nodes() {
+        let fen = "6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1";
+        let mut pos = Position::from_fen(fen).unwrap();
+        let mut searcher = Searcher::new(ClassicalEval::new());
+        searcher.set_threads(4);
+
+        let limits = SearchLimits { max_depth: 5, max_nodes: None, max_time_ms: None, multi_pv: 1, clock: None };
+        let result = searcher.search_smp(&mut pos, limits);
+
+        assert_eq!(result.best_move.to_uci(), "a1a8");
+        assert!(result.score_cp > 25000);
+        assert_eq!(result.depth, 5);
+        assert_eq!(pos.to_fen(), fen);
+        assert!(result.nodes > 0);
+    }
+
+    #[test]
+    fn test_smp_respects_shared_node_limit_and_stop_flag() {
+        let mut pos = Position::starting_position();
+        let mut searcher = Searcher::new(ClassicalEval::new());
+        searcher.set_threads(4);
+
+        let limits = SearchLimits { max_depth: 100, max_nodes: Some(20_000), max_time_ms: None, multi_pv: 1, clock: None };
+        let result = searcher.search_smp(&mut pos, limits);
+        //every thread may overshoot by less than one unpublished batch
+        assert!(result.nodes <= 20_000 + 4 * NODE_BATCH);
+        assert!(result.depth < 100);
+
+        searcher.stop_flag().store(true, Ordering::Relaxed);
+        let limits = SearchLimits { max_depth: 100, max_nodes: None, max_time_ms: None, multi_pv: 1, clock: None };
+        let stopped = searcher.search_smp(&mut pos, limits);
+        assert_eq!(stopped.depth, 0);
+    }
+
+    #[test]
+    fn test_every_search_config_finds_mate_in_two() {
+        let fen = "k7/8/2K5/8/8/8/8/7R w - - 0 1";
+        let plain = SearchConfig::plain();
+        let configs = [
+            SearchConfig::default(),
+            plain,
+            SearchConfig { null_move: true, ..plain },
+            SearchConfig { lmr: true, ..plain },
+            SearchConfig { pvs: true, ..plain },
+            SearchConfig { check_extensions: true, ..plain },
+            SearchConfig { futility: true, ..plain },
+            SearchConfig { reverse_futility: true, ..plain },
+            SearchConfig { quiet_ordering: true, ..plain },
+        ];
+
+        for config in configs {
+            let mut pos = Position::from_fen(fen).unwrap();
+            let mut searcher = Searcher::new(ClassicalEval::new());
+            searcher.set_config(config);
+
+            let limits = SearchLimits { max_depth: 4, max_nodes: None, max_time_ms: None, multi_pv: 1, clock: None };
+            let result = searcher.search(&mut pos, limits);
+            assert_eq!(result.score_cp, MATE - 3, "{config:?}");
+        }
+    }
+
+    #[test]
+    fn test_selective_search_needs_fewer_nodes_than_plain() {
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+        let limits = SearchLimits { max_depth: 4, max_nodes: None, max_time_ms: None, multi_pv: 1, clock: None };
+
+        let mut pos = Position::from_fen(fen).unwrap();
+        let mut plain = Searcher::new(ClassicalEval::new());
+        plain.set_config(SearchConfig::plain());
+        let plain_nodes = plain.search(&mut pos, limits).nodes;
+
+        let mut selective = Searcher::new(ClassicalEval::new());
+        let selective_nodes = selective.search(&mut pos, limits).nodes;
+
+        assert!(selective_nodes < plain_nodes, "{selective_nodes} >= {plain_nodes}");
+    }
+
+    #[test]
+    fn test_quiet_ordering_needs_fewer_nodes() {
+        let fen = "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4";
+        let limits = SearchLimits { max_depth: 5, max_nodes: None, max_time_ms: None, multi_pv: 1, clock: None };
+
+        let mut pos = Position::from_fen(fen).unwrap();
+        let mut unordered = Searcher::new(ClassicalEval::new());
+        unordered.set_config(SearchConfig { quiet_ordering: false, ..SearchConfig::default() });
+        let unordered_nodes = unordered.search(&mut pos, limits).nodes;
+
+        let mut ordered = Searcher::new(ClassicalEval::new());
+        let ordered_nodes = ordered.search(&mut pos, limits).nodes;
+
+        assert!(ordered_nodes < unordered_nodes, "{ordered_nodes} >= {unordered_nodes}");
+    }
+
+    #[test]
+    fn test_null_move_guard_ignores_pawns_and_king() {
+        let pawns_only = Position::from_fen("4k3/pppp4/8/8/8/8/PPPP4/4K3 w - - 0 1").unwrap();
+        assert!(!Searcher::<ClassicalEval>::has_non_pawn_material(&pawns_only, Color::White));
+        assert!(!Searcher::<ClassicalEval>::has_non_pawn_material(&pawns_only, Color::Black));
+
+        let with_knight = Position::from_fen("4k3/pppp4/8/8/8/8/PPPP4/1N2K3 w - - 0 1").unwrap();
+        assert!(Searcher::<ClassicalEval>::has_non_pawn_material(&with_knight, Color::White));
+        assert!(!Searcher::<ClassicalEval>::has_non_pawn_material(&with_knight, Color::Black));
+    }
+
+    #[test]
+    fn test_aspiration_recovers_from_wrong_guess() {
+        use crate::search::tt::TranspositionTable;
+
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+        let mut pos = Position::from_fen(fen).unwrap();
+
+        //plain alpha-beta without TT: the exact score doesn't depend on the window
+        let mut searcher = Searcher::new(ClassicalEval::new());
+        searcher.tt = Arc::new(TranspositionTable::disabled());
+        searcher.set_config(SearchConfig::plain());
+        searcher.limits = SearchLimits { max_depth: 3, max_nodes: None, max_time_ms: None, multi_pv: 1, clock: None };
+        searcher.history = vec![pos.zobrist];
+
+        let (_, full, _) = searcher.root(&mut pos, 3, -INF, INF);
+        for guess in [full - 600, full, full + 600] {
+            let (mv, score, complete) = searcher.aspiration(&mut pos, 3, guess);
+            assert!(complete);
+            assert!(!mv.is_null());
+            assert_eq!(score, full, "guess {guess}");
+        }
+    }
+
+    #[test]
+    fn test_iteration_callback_reports_every_depth() {
+        use std::sync::Mutex;
+
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&reports);
+
+        let mut pos = Position::starting_position();
+        let mut searcher = Searcher::new(ClassicalEval::new());
+        searcher.set_on_iteration(Box::new(move |info| {
+            sink.lock().unwrap().push((info.depth, info.nodes, info.pv.to_vec()));
+        }));
+
+        let limits = SearchLimits { max_depth: 5, max_nodes: None, max_time_ms: None, multi_pv: 1, clock: None };
+        let result = searcher.search(&mut pos, limits);
+
+        let reports = reports.lock().unwrap();
+        let depths: Vec<u8> = reports.iter().map(|r| r.0).collect();
+        assert_eq!(depths, vec![1, 2, 3, 4, 5]);
+        assert!(reports.windows(2).all(|w| w[0].1 <= w[1].1));
+        assert_eq!(reports.last().unwrap().2, result.pv);
+    }
+
+    #[test]
+    fn test_pv_of_mate_in_one_ends_in_mate() {
+        let fen = "6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1";
+        let mut pos = Position::from_fen(fen).unwrap();
+        let mut searcher = Searcher::new(ClassicalEval::new());
+
+        let limits = SearchLimits { max_depth: 3, max_nodes: None, max_time_ms: None, multi_pv: 1, clock: None };
+        let result = searcher.search(&mut pos, limits);
+
+        assert_eq!(result.pv.len(), 1);
+        assert_eq!(result.pv[0].to_uci(), "a1a8");
+    }
+
+    #[test]
+    fn test_multi_pv_returns_distinct_sorted_lines() {
+        let mut pos = Position::starting_position();
+        let mut searcher = Searcher::new(ClassicalEval::new());
+
+        let limits = SearchLimits { max_depth: 4, max_nodes: None, max_time_ms: None, multi_pv: 3, clock: None };
+        let result = searcher.search(&mut pos, limits);
+
+        assert_eq!(result.lines.len(), 3);
+        assert_eq!(result.lines[0].best_move, result.best_move);
+        assert_eq!(result.lines[0].score_cp, result.score_cp);
+        assert!(result.lines.windows(2).all(|w| w[0].score_cp >= w[1].score_cp));
+
+        let mut firsts: Vec<Move> = result.lines.iter().map(|l| l.best_move).collect();
+        firsts.sort_by_key(|m| (m.from_sq(), m.to_sq()));
+        firsts.dedup();
+        assert_eq!(firsts.len(), 3);
+        for line in &result.lines {
+            assert_eq!(line.pv.first(), Some(&line.best_move));
+        }
+    }
+
+    #[test]
+    fn test_multi_pv_is_clamped_to_legal_moves() {
+        //Kb1, Kb2, a3, a4
+        let mut pos = Position::from_fen("k7/8/8/8/8/8/P7/K7 w - - 0 1").unwrap();
+        let mut searcher = Searcher::new(ClassicalEval::new());
+
+        let limits = SearchLimits { max_depth: 3, max_nodes: None, max_time_ms: None, multi_pv: 10, clock: None };
+        let result = searcher.search(&mut pos, limits);
+
+        assert_eq!(result.lines.len(), 4);
+    }
+
+    #[test]
+    fn test_clock_search_stops_at_once_on_forced_move() {
+        //the rook covers a2 and b1, taking it is the only move
+        let mut pos = Position::from_fen("k7/8/8/8/8/8/1r6/K7 w - - 0 1").unwrap();
+        let mut searcher = Searcher::new(ClassicalEval::new());
+
+        let clock = Clock { time_left_ms: 600_000, increment_ms: 0, moves_to_go: Some(1) };
+        let limits = SearchLimits { max_depth: 64, max_nodes: None, max_time_ms: None, multi_pv: 1, clock: Some(clock) };
+        let t0 = Instant::now();
+        let result = searcher.search(&mut pos, limits);
+
+        assert_eq!(result.best_move.to_uci(), "a1b2");
+        assert!(t0.elapsed().as_millis() < 1000);
+    }
+
+    #[test]
+    fn test_infinite_search_ignores_limits_until_stopped() {
+        let mut pos = Position::starting_position();
+        let mut searcher = Searcher::new(ClassicalEval::new());
+        let stop = searcher.stop_flag();
+        searcher.infinite_flag().store(true, Ordering::Relaxed);
+
+        let stopper = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(150));
+            stop.store(true, Ordering::Relaxed);
+        });
+
+        //depth 2 is done long before the stop, the search has to wait for it anyway
+        let limits = SearchLimits { max_depth: 2, max_nodes: Some(10), max_time_ms: Some(10), multi_pv: 1, clock: None };
+        let t0 = Instant::now();
+        let result = searcher.search(&mut pos, limits);
+        stopper.join().unwrap();
+
+        assert!(t0.elapsed().as_millis() >= 150);
+        assert_eq!(result.depth, 2);
+        assert!(!result.best_move.is_null());
+    }
+
+    #[test]
+    fn test_ponderhit_turns_into_timed_search() {
+        let mut pos = Position::starting_position();
+        let mut searcher = Searcher::new(ClassicalEval::new());
+        let ponder = searcher.infinite_flag();
+        ponder.store(true, Ordering::Relaxed);
+
+        let ponder_hit = Arc::clone(&ponder);
+        let hitter = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(150));
+            ponder_hit.store(false, Ordering::Relaxed);
+        });
+
+        //the 100ms budget only starts counting at the ponderhit
+        let limits = SearchLimits { max_depth: 64, max_nodes: None, max_time_ms: Some(100), multi_pv: 1, clock: None };
+        let t0 = Instant::now();
+        let result = searcher.search(&mut pos, limits);
+        hitter.join().unwrap();
+
+        let elapsed = t0.elapsed().as_millis();
+        assert!(elapsed >= 250, "returned after {elapsed}ms");
+        assert!(elapsed < 1000, "returned after {elapsed}ms");
+        assert!(!result.best_move.is_null());
+    }
 }
 
 #[cfg(test)]
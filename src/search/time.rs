@@ -0,0 +1,190 @@
+//time management for games played on a clock
+//the soft budget is checked between iterations and scaled by how settled the search is,
+//the hard budget is a limit the search never exceeds
+
+use crate::movegen::Move;
+
+//safety margin so we never lose on time because of GUI/OS latency
+pub const MOVE_OVERHEAD_MS: u64 = 30;
+//assumed number of remaining moves when the GUI does not send movestogo
+pub const DEFAULT_MOVES_TO_GO: u64 = 30;
+//hard budget as a multiple of the soft one
+const HARD_FACTOR: u64 = 4;
+
+//soft budget scaling in percent
+const MIN_PERCENT: u64 = 50;
+const MAX_PERCENT: u64 = 250;
+//added to the instability whenever the best move changes, halved every iteration
+const BEST_MOVE_CHANGE_PERCENT: u64 = 100;
+//iterations with the same best move after which it counts as clearly best
+const STABLE_ITERATIONS: u32 = 4;
+
+//clock of the side to move
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Clock {
+    pub time_left_ms: u64,
+    pub increment_ms: u64,
+    //moves until the next time control, None for sudden death
+    pub moves_to_go: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeBudget {
+    pub soft_ms: u64,
+    pub hard_ms: u64,
+}
+
+impl TimeBudget {
+    pub fn from_clock(clock: &Clock) -> Self {
+        let usable = clock.time_left_ms.saturating_sub(MOVE_OVERHEAD_MS).max(1);
+        let moves_to_go = clock.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+        let base = usable / moves_to_go + clock.increment_ms * 3 / 4;
+
+        let soft_ms = base.min(usable).max(1);
+        let hard_ms = (soft_ms * HARD_FACTOR).min(usable);
+        Self { soft_ms, hard_ms }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TimeManager {
+    budget: TimeBudget,
+    prev_best: Move,
+    prev_score: Option<i32>,
+    //same best move for this many iterations in a row
+    stable_iterations: u32,
+    //recent best move changes, decays every iteration
+    instability: u64,
+    //last score drop in cp, 0 if the score didn't get worse
+    score_drop: u64,
+}
+
+impl TimeManager {
+    pub fn new(clock: &Clock) -> Self {
+        Self {
+            budget: TimeBudget::from_clock(clock),
+            prev_best: Move::NULL,
+            prev_score: None,
+            stable_iterations: 0,
+            instability: 0,
+            score_drop: 0,
+        }
+    }
+
+    pub fn budget(&self) -> TimeBudget {
+        self.budget
+    }
+
+    //result of a completed iteration
+    pub fn on_iteration(&mut self, best_move: Move, score_cp: i32) {
+        self.instability /= 2;
+        if best_move == self.prev_best {
+            self.stable_iterations += 1;
+        } else {
+            if !self.prev_best.is_null() {
+                self.instability += BEST_MOVE_CHANGE_PERCENT;
+            }
+            self.stable_iterations = 0;
+        }
+        self.score_drop = match self.prev_score {
+            Some(prev) if score_cp < prev => (prev - score_cp) as u64,
+            _ => 0,
+        };
+        self.prev_best = best_move;
+        self.prev_score = Some(score_cp);
+    }
+
+    //soft budget scaled by the last iterations, never above the hard budget
+    pub fn soft_limit_ms(&self) -> u64 {
+        let percent = if self.stable_iterations >= STABLE_ITERATIONS
+            && self.instability == 0
+            && self.score_drop == 0
+        {
+            //one move is clearly best
+            MIN_PERCENT
+        } else {
+            (100 + self.instability + self.score_drop.min(100)).min(MAX_PERCENT)
+        };
+        (self.budget.soft_ms * percent / 100).min(self.budget.hard_ms)
+    }
+
+    //start another iteration only while the scaled soft budget isn't used up
+    pub fn should_continue(&self, elapsed_ms: u64) -> bool {
+        elapsed_ms < self.soft_limit_ms()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clock(time_left_ms: u64, increment_ms: u64, moves_to_go: Option<u64>) -> Clock {
+        Clock { time_left_ms, increment_ms, moves_to_go }
+    }
+
+    fn mv(from: usize, to: usize) -> Move {
+        Move::new(from, to)
+    }
+
+    #[test]
+    fn budget_splits_time_over_remaining_moves() {
+        let budget = TimeBudget::from_clock(&clock(60_030, 0, Some(20)));
+        assert_eq!(budget.soft_ms, 3000);
+        assert_eq!(budget.hard_ms, 12_000);
+
+        //sudden death with increment
+        let budget = TimeBudget::from_clock(&clock(30_030, 1000, None));
+        assert_eq!(budget.soft_ms, 1000 + 750);
+    }
+
+    #[test]
+    fn budget_never_exceeds_remaining_time() {
+        let budget = TimeBudget::from_clock(&clock(1000, 0, Some(1)));
+        assert_eq!(budget.soft_ms, 1000 - MOVE_OVERHEAD_MS);
+        assert_eq!(budget.hard_ms, 1000 - MOVE_OVERHEAD_MS);
+
+        //big increment, almost no time left
+        let budget = TimeBudget::from_clock(&clock(200, 5000, None));
+        assert!(budget.hard_ms <= 200 - MOVE_OVERHEAD_MS);
+
+        let budget = TimeBudget::from_clock(&clock(10, 0, None));
+        assert_eq!(budget, TimeBudget { soft_ms: 1, hard_ms: 1 });
+    }
+
+    #[test]
+    fn changing_best_move_and_score_drop_extend_the_soft_limit() {
+        let mut tm = TimeManager::new(&clock(60_030, 0, Some(20)));
+        let base = tm.budget().soft_ms;
+
+        tm.on_iteration(mv(35, 55), 20);
+        assert_eq!(tm.soft_limit_ms(), base);
+
+        tm.on_iteration(mv(34, 54), 20);
+        assert!(tm.soft_limit_ms() > base);
+
+        let mut tm = TimeManager::new(&clock(60_030, 0, Some(20)));
+        tm.on_iteration(mv(35, 55), 20);
+        tm.on_iteration(mv(35, 55), -40);
+        assert_eq!(tm.soft_limit_ms(), base * 160 / 100);
+    }
+
+    #[test]
+    fn stable_best_move_stops_early() {
+        let mut tm = TimeManager::new(&clock(60_030, 0, Some(20)));
+        let base = tm.budget().soft_ms;
+        for _ in 0..=STABLE_ITERATIONS {
+            tm.on_iteration(mv(35, 55), 30);
+        }
+        assert_eq!(tm.soft_limit_ms(), base * MIN_PERCENT / 100);
+        assert!(tm.should_continue(base * MIN_PERCENT / 100 - 1));
+        assert!(!tm.should_continue(base * MIN_PERCENT / 100));
+    }
+
+    #[test]
+    fn soft_limit_stays_below_hard_budget() {
+        let mut tm = TimeManager::new(&clock(1000, 0, Some(1)));
+        tm.on_iteration(mv(35, 55), 300);
+        tm.on_iteration(mv(34, 54), -300);
+        assert_eq!(tm.soft_limit_ms(), tm.budget().hard_ms);
+    }
+}
@@ -1,4 +1,7 @@
-use crate::movegen::Move;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::board::mailbox120::{SQUARE64_TO_SQUARE120, SQUARE120_TO_SQUARE64};
+use crate::movegen::{Move, MoveType, PromotionPiece};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Bound {
@@ -28,16 +31,29 @@ impl Default for TTEntry {
     }
 }
 
+//one slot is two words: the packed entry and key ^ data, a torn write from
+//another thread fails the xor check and reads as a miss
+#[derive(Default)]
+struct Slot {
+    key_xor_data: AtomicU64,
+    data: AtomicU64,
+}
+
+//lock-free, shared by all search threads
 pub struct TranspositionTable {
-    entries: Vec<TTEntry>,
+    slots: Vec<Slot>,
     mask: usize,
 }
 
+//data layout: move 0..16 | depth 16..31 | bound 31..33 (0 = empty) | score 33..64 (signed)
+const DEPTH_SHIFT: u32 = 16;
+const BOUND_SHIFT: u32 = 31;
+const SCORE_SHIFT: u32 = 33;
 
 impl TranspositionTable {
     pub fn disabled() -> Self {
         Self {
-            entries: Vec::new(),
+            slots: Vec::new(),
             mask: 0,
         }
     }
@@ -48,14 +64,14 @@ impl TranspositionTable {
         }
 
         let bytes = size_mb.saturating_mul(1024 * 1024);
-        let entry_size = std::mem::size_of::<TTEntry>().max(1);
-        let approx = (bytes / entry_size).max(1);
+        let slot_size = std::mem::size_of::<Slot>().max(1);
+        let approx = (bytes / slot_size).max(1);
 
-        let entry_count_pow2 = approx.next_power_of_two().max(1024);
+        let slot_count_pow2 = approx.next_power_of_two().max(1024);
 
         Self {
-            entries: vec![TTEntry::default(); entry_count_pow2],
-            mask: entry_count_pow2 -1,
+            slots: (0..slot_count_pow2).map(|_| Slot::default()).collect(),
+            mask: slot_count_pow2 - 1,
         }
     }
 
@@ -64,40 +80,114 @@ impl TranspositionTable {
     }
 
     pub fn probe(&self, key: u64) -> Option<TTEntry> {
-        if self.entries.is_empty() {
+        if self.slots.is_empty() {
             return None;
         }
 
-        let slot_entry = self.entries[self.idx(key)];
-        if slot_entry.depth >= 0 && slot_entry.key == key {
-            Some(slot_entry)
-        } else {
-            None
-        }
+        let entry = self.load(self.idx(key))?;
+        if entry.key == key { Some(entry) } else { None }
     }
 
-    pub fn store(&mut self, key: u64, depth: i32, score: i32, bound: Bound, best: Move) {
-     if self.entries.is_empty() {
-        return;
-     }
-     let slot_idx = self.idx(key);
-     let existing_entry = self.entries[slot_idx];
+    pub fn store(&self, key: u64, depth: i32, score: i32, bound: Bound, best: Move) {
+        if self.slots.is_empty() {
+            return;
+        }
+        let slot_idx = self.idx(key);
+
+        let stored_depth = depth.clamp(0, i16::MAX as i32) as i16;
 
-     let stored_depth = depth.clamp(0, i16::MAX as i32) as i16;
+        //racy read, at worst a slightly worse replacement decision
+        let should_replace = match self.load(slot_idx) {
+            None => true,
+            Some(existing) => {
+                (existing.key == key && stored_depth >= existing.depth)
+                    || (existing.key != key && stored_depth > existing.depth)
+            }
+        };
 
-     let should_replace = existing_entry.depth < 0
-        || (existing_entry.key == key && stored_depth >= existing_entry.depth)
-        || (existing_entry.key != key && stored_depth > existing_entry.depth);
+        if should_replace {
+            let data = pack(stored_depth, score, bound, best);
+            let slot = &self.slots[slot_idx];
+            slot.key_xor_data.store(key ^ data, Ordering::Relaxed);
+            slot.data.store(data, Ordering::Relaxed);
+        }
+    }
 
-     if should_replace {
-        self.entries[slot_idx] = TTEntry {
+    //None if the slot is empty or was torn by a concurrent store
+    fn load(&self, slot_idx: usize) -> Option<TTEntry> {
+        let slot = &self.slots[slot_idx];
+        let data = slot.data.load(Ordering::Relaxed);
+        let key = slot.key_xor_data.load(Ordering::Relaxed) ^ data;
+
+        let bound = match (data >> BOUND_SHIFT) & 0b11 {
+            1 => Bound::Exact,
+            2 => Bound::Lower,
+            3 => Bound::Upper,
+            _ => return None,
+        };
+        Some(TTEntry {
             key,
-            depth: stored_depth,
-            score,
+            depth: ((data >> DEPTH_SHIFT) & 0x7FFF) as i16,
+            score: ((data as i64) >> SCORE_SHIFT) as i32,
             bound,
-            best,
-        };
-     }   
+            best: unpack_move(data as u16),
+        })
+    }
+}
+
+fn pack(depth: i16, score: i32, bound: Bound, best: Move) -> u64 {
+    let bound_bits: u64 = match bound {
+        Bound::Exact => 1,
+        Bound::Lower => 2,
+        Bound::Upper => 3,
+    };
+    pack_move(best) as u64
+        | (depth as u64) << DEPTH_SHIFT
+        | bound_bits << BOUND_SHIFT
+        | ((score as i64) << SCORE_SHIFT) as u64
+}
+
+//from64 0..6 | to64 6..12 | kind 12..16, 0 is the null move (a1a1 can't be a real move)
+fn pack_move(mv: Move) -> u16 {
+    if mv.is_null() {
+        return 0;
+    }
+    let kind = match (mv.move_type, mv.promotion) {
+        (MoveType::Promotion, Some(PromotionPiece::Knight)) => 4,
+        (MoveType::Promotion, Some(PromotionPiece::Bishop)) => 5,
+        (MoveType::Promotion, Some(PromotionPiece::Rook)) => 6,
+        (MoveType::Promotion, _) => 7,
+        (MoveType::DoublePawnPush, _) => 1,
+        (MoveType::EnPassant, _) => 2,
+        (MoveType::Castling, _) => 3,
+        (MoveType::Normal, _) => 0,
+    };
+    let from = SQUARE120_TO_SQUARE64[mv.from_sq()] as u16;
+    let to = SQUARE120_TO_SQUARE64[mv.to_sq()] as u16;
+    from | to << 6 | kind << 12
+}
+
+fn unpack_move(bits: u16) -> Move {
+    if bits == 0 {
+        return Move::NULL;
+    }
+    let from = SQUARE64_TO_SQUARE120[(bits & 63) as usize] as u8;
+    let to = SQUARE64_TO_SQUARE120[((bits >> 6) & 63) as usize] as u8;
+    let (move_type, promotion) = match bits >> 12 {
+        1 => (MoveType::DoublePawnPush, None),
+        2 => (MoveType::EnPassant, None),
+        3 => (MoveType::Castling, None),
+        4 => (MoveType::Promotion, Some(PromotionPiece::Knight)),
+        5 => (MoveType::Promotion, Some(PromotionPiece::Bishop)),
+        6 => (MoveType::Promotion, Some(PromotionPiece::Rook)),
+        7 => (MoveType::Promotion, Some(PromotionPiece::Queen)),
+        _ => (MoveType::Normal, None),
+    };
+    Move {
+        from,
+        to,
+        move_type,
+        promotion,
     }
 }
 
@@ -109,7 +199,7 @@ mod tests {
 
     #[test]
     fn disabled_tt_never_hits_and_never_stores() {
-        let mut tt = TranspositionTable::disabled();
+        let tt = TranspositionTable::disabled();
 
         assert!(tt.probe(123).is_none());
         tt.store(123, 5, 42, Bound::Exact, Move::NULL);
@@ -118,7 +208,7 @@ mod tests {
 
     #[test]
     fn store_then_probe_returns_entry() {
-        let mut tt = TranspositionTable::new_mb(1);
+        let tt = TranspositionTable::new_mb(1);
 
         let key = 0xDEADBEEF_u64;
         tt.store(key, 7, 123, Bound::Exact, Move::NULL);
@@ -134,7 +224,7 @@ mod tests {
     #[test]
     fn probe_miss_on_different_key_even_if_same_slot_possible() {
         //Can’t guarantee collision, but at least assert different key doesn't match.
-        let mut tt = TranspositionTable::new_mb(1);
+        let tt = TranspositionTable::new_mb(1);
 
         let key1 = 1_u64;
         let key2 = 2_u64;
@@ -149,7 +239,7 @@ mod tests {
 
     #[test]
     fn replacement_prefers_deeper_entry() {
-        let mut tt = TranspositionTable::new_mb(1);
+        let tt = TranspositionTable::new_mb(1);
         let key = 999_u64;
 
         tt.store(key, 4, 10, Bound::Upper, Move::NULL);
@@ -168,7 +258,7 @@ mod tests {
 
     #[test]
     fn depth_is_clamped_to_i16_max_and_non_negative() {
-        let mut tt = TranspositionTable::new_mb(1);
+        let tt = TranspositionTable::new_mb(1);
         let key = 42_u64;
 
         tt.store(key, -10, 1, Bound::Exact, Move::NULL);
@@ -179,6 +269,51 @@ mod tests {
         let e2 = tt.probe(key).unwrap();
         assert_eq!(e2.depth, i16::MAX);
     }
+
+    #[test]
+    fn moves_and_negative_scores_survive_packing() {
+        let tt = TranspositionTable::new_mb(1);
+        let moves = [
+            Move::new(35, 55),
+            Move { move_type: MoveType::DoublePawnPush, ..Move::new(35, 55) },
+            Move { move_type: MoveType::EnPassant, ..Move::new(65, 76) },
+            Move { move_type: MoveType::Castling, ..Move::new(25, 27) },
+            Move::new_promotion(81, 91, PromotionPiece::Queen),
+            Move::new_promotion(38, 27, PromotionPiece::Knight),
+            Move::new(98, 21),
+        ];
+
+        for (i, &mv) in moves.iter().enumerate() {
+            let key = 0x1234_5678_0000_0000 + i as u64;
+            tt.store(key, 3, -29_990, Bound::Lower, mv);
+            let e = tt.probe(key).unwrap();
+            assert_eq!(e.best, mv);
+            assert_eq!(e.score, -29_990);
+            assert_eq!(e.bound, Bound::Lower);
+        }
+    }
+
+    #[test]
+    fn concurrent_stores_never_return_foreign_entries() {
+        let tt = TranspositionTable::new_mb(1);
+        let slots = tt.mask as u64 + 1;
+
+        //all threads hammer the same few slots with different keys
+        std::thread::scope(|s| {
+            for t in 0..4u64 {
+                let tt = &tt;
+                s.spawn(move || {
+                    for i in 0..20_000u64 {
+                        let key = (i % 8) + slots * (t + 1);
+                        tt.store(key, (i % 50) as i32, key as i32, Bound::Exact, Move::NULL);
+                        if let Some(e) = tt.probe(key) {
+                            assert_eq!(e.score, key as i32);
+                        }
+                    }
+                });
+            }
+        });
+    }
 }
 
 
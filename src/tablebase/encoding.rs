@@ -0,0 +1,200 @@
+//square maps of the syzygy position index: symmetries are removed by mirroring the
+//leading piece into the a1-d1-d4 triangle (no pawns) or onto files a-d (pawns), the
+//remaining pieces are combined per group of equal pieces
+
+use std::sync::LazyLock;
+
+use crate::board::bitboard::{king_attacks, square_bb};
+
+//tables up to 7 pieces: at most 5 pieces in one group
+pub(super) const MAX_PIECES: usize = 7;
+const MAX_GROUP: usize = 6;
+
+pub(super) struct Encoding {
+    //binomial[k][n]: ways to put k equal pieces on n squares
+    pub binomial: [[u64; 64]; MAX_GROUP],
+    //a2..h7 -> 0..47, the leading pawn is the one with the highest value
+    pub map_pawns: [usize; 64],
+    //[leading pawns][square of the leading pawn]: first index of that square in its file
+    pub lead_pawn_idx: [[u64; 64]; MAX_GROUP],
+    //[leading pawns][file a..d]: number of placements of the leading pawns on that file
+    pub lead_pawns_size: [[u64; 4]; MAX_GROUP],
+    //squares below the a1-h8 diagonal -> 0..27
+    pub map_b1h1h7: [usize; 64],
+    //a1-d1-d4 triangle -> 0..9, squares on the diagonal last
+    pub map_a1d1d4: [usize; 64],
+    //[first king by map_a1d1d4][second king]: the 462 placements of the two kings
+    pub map_kk: [[u64; 64]; 10],
+}
+
+pub(super) static ENCODING: LazyLock<Box<Encoding>> = LazyLock::new(Encoding::new);
+
+#[inline]
+pub(super) fn file_of(sq: usize) -> usize {
+    sq & 7
+}
+
+#[inline]
+pub(super) fn rank_of(sq: usize) -> usize {
+    sq >> 3
+}
+
+//0 on the a1-h8 diagonal, negative below it
+#[inline]
+pub(super) fn off_diagonal(sq: usize) -> i32 {
+    rank_of(sq) as i32 - file_of(sq) as i32
+}
+
+impl Encoding {
+    fn new() -> Box<Self> {
+        let mut e = Box::new(Self {
+            binomial: [[0; 64]; MAX_GROUP],
+            map_pawns: [0; 64],
+            lead_pawn_idx: [[0; 64]; MAX_GROUP],
+            lead_pawns_size: [[0; 4]; MAX_GROUP],
+            map_b1h1h7: [0; 64],
+            map_a1d1d4: [0; 64],
+            map_kk: [[0; 64]; 10],
+        });
+
+        let mut code = 0;
+        for sq in 0..64 {
+            if off_diagonal(sq) < 0 {
+                e.map_b1h1h7[sq] = code;
+                code += 1;
+            }
+        }
+
+        //a1..d4 holds the whole triangle
+        let mut diagonal = Vec::new();
+        code = 0;
+        for sq in 0..=27 {
+            if file_of(sq) > 3 {
+                continue;
+            }
+            if off_diagonal(sq) < 0 {
+                e.map_a1d1d4[sq] = code;
+                code += 1;
+            } else if off_diagonal(sq) == 0 {
+                diagonal.push(sq);
+            }
+        }
+        for sq in diagonal {
+            e.map_a1d1d4[sq] = code;
+            code += 1;
+        }
+
+        //first king on the diagonal: the second one is mirrored below it,
+        //both on the diagonal come last
+        let mut both_on_diagonal = Vec::new();
+        let mut code = 0;
+        for idx in 0..10 {
+            //every square outside the triangle maps to 0 as well, b1 is the real one
+            for s1 in (0..=27).filter(|&s| e.map_a1d1d4[s] == idx && (idx > 0 || s == 1)) {
+                for s2 in 0..64 {
+                    if s1 == s2 || king_attacks(s1) & square_bb(s2) != 0 {
+                        continue;
+                    }
+                    if off_diagonal(s1) == 0 && off_diagonal(s2) > 0 {
+                        continue;
+                    }
+                    if off_diagonal(s1) == 0 && off_diagonal(s2) == 0 {
+                        both_on_diagonal.push((idx, s2));
+                    } else {
+                        e.map_kk[idx][s2] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (idx, s2) in both_on_diagonal {
+            e.map_kk[idx][s2] = code;
+            code += 1;
+        }
+
+        e.binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..MAX_GROUP.min(n + 1) {
+                e.binomial[k][n] = if k > 0 { e.binomial[k - 1][n - 1] } else { 0 }
+                    + if k < n { e.binomial[k][n - 1] } else { 0 };
+            }
+        }
+
+        //a pawn on rank 2 of the a file leaves 47 squares to the others, every rank
+        //higher takes away two more (the square below it on both edge files)
+        let mut available = 48;
+        for lead in 1..MAX_GROUP {
+            for file in 0..4 {
+                let mut idx = 0;
+                for rank in 1..7 {
+                    let sq = rank * 8 + file;
+                    if lead == 1 {
+                        available -= 1;
+                        e.map_pawns[sq] = available;
+                        available -= 1;
+                        e.map_pawns[sq ^ 7] = available;
+                    }
+                    e.lead_pawn_idx[lead][sq] = idx;
+                    idx += e.binomial[lead - 1][e.map_pawns[sq]];
+                }
+                e.lead_pawns_size[lead][file] = idx;
+            }
+        }
+        e
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kings_have_462_placements() {
+        let e = &ENCODING;
+        let mut codes: Vec<u64> = Vec::new();
+        for s1 in 0..=27 {
+            if file_of(s1) > 3 || off_diagonal(s1) > 0 {
+                continue;
+            }
+            let idx = e.map_a1d1d4[s1];
+            for s2 in 0..64 {
+                let mirrored = off_diagonal(s1) == 0 && off_diagonal(s2) > 0;
+                if s1 != s2 && king_attacks(s1) & square_bb(s2) == 0 && !mirrored {
+                    codes.push(e.map_kk[idx][s2]);
+                }
+            }
+        }
+        codes.sort_unstable();
+        assert_eq!(codes, (0..462).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn square_maps_are_dense() {
+        let e = &ENCODING;
+        let mut triangle: Vec<usize> =
+            (0..=27).filter(|&s| file_of(s) <= 3 && off_diagonal(s) <= 0).map(|s| e.map_a1d1d4[s]).collect();
+        triangle.sort_unstable();
+        assert_eq!(triangle, (0..10).collect::<Vec<_>>());
+        assert_eq!(e.map_a1d1d4[0], 6);
+        assert_eq!((e.map_b1h1h7[1], e.map_b1h1h7[63 - 8]), (0, 27));
+
+        let mut pawns: Vec<usize> = (8..56).map(|s| e.map_pawns[s]).collect();
+        pawns.sort_unstable();
+        assert_eq!(pawns, (0..48).collect::<Vec<_>>());
+        //a2 leads, h2 next
+        assert_eq!((e.map_pawns[8], e.map_pawns[15]), (47, 46));
+    }
+
+    #[test]
+    fn leading_pawn_indices_cover_the_file() {
+        let e = &ENCODING;
+        assert_eq!(e.binomial[2][5], 10);
+        assert_eq!(e.binomial[5][63], 7_028_847);
+        //a single leading pawn: one index per rank
+        assert_eq!(e.lead_pawns_size[1], [6; 4]);
+        //two leading pawns: the second one on any square mapped below the first
+        let a_file: u64 = (1..7).map(|rank| e.map_pawns[rank * 8] as u64).sum();
+        assert_eq!(e.lead_pawns_size[2][0], a_file);
+        assert_eq!(e.lead_pawn_idx[2][16], e.map_pawns[8] as u64);
+    }
+}
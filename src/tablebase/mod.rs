@@ -0,0 +1,769 @@
+//Syzygy endgame tablebases: win/draw/loss (.rtbw) and distance to zeroing (.rtbz)
+//probing; a table is read into memory the first time a position needs it
+
+mod encoding;
+mod table;
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::ops::Neg;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use crate::movegen::{Move, generate_legal_moves, has_legal_move, is_in_check};
+use crate::position::{Cell, PieceKind, Position};
+use table::{Material, Table, TableKind};
+
+//outcome with best play; cursed wins and blessed losses are the ones the fifty
+//move rule turns into draws
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Wdl {
+    Loss,
+    BlessedLoss,
+    Draw,
+    CursedWin,
+    Win,
+}
+
+impl Wdl {
+    pub fn signum(self) -> i32 {
+        match self {
+            Wdl::Loss | Wdl::BlessedLoss => -1,
+            Wdl::Draw => 0,
+            Wdl::CursedWin | Wdl::Win => 1,
+        }
+    }
+}
+
+impl Neg for Wdl {
+    type Output = Wdl;
+
+    fn neg(self) -> Wdl {
+        match self {
+            Wdl::Loss => Wdl::Win,
+            Wdl::BlessedLoss => Wdl::CursedWin,
+            Wdl::Draw => Wdl::Draw,
+            Wdl::CursedWin => Wdl::BlessedLoss,
+            Wdl::Win => Wdl::Loss,
+        }
+    }
+}
+
+//the files of one material combination
+struct Entry {
+    material: Material,
+    wdl_path: Option<PathBuf>,
+    dtz_path: Option<PathBuf>,
+    //None inside: missing or unreadable file
+    wdl: OnceLock<Option<Table>>,
+    dtz: OnceLock<Option<Table>>,
+}
+
+impl Entry {
+    fn table(&self, kind: TableKind) -> Option<&Table> {
+        let (cell, path) = match kind {
+            TableKind::Wdl => (&self.wdl, &self.wdl_path),
+            TableKind::Dtz => (&self.dtz, &self.dtz_path),
+        };
+        cell.get_or_init(|| Table::parse(fs::read(path.as_ref()?).ok()?, kind, &self.material))
+            .as_ref()
+    }
+}
+
+pub struct Tablebase {
+    //by piece counts [white, black] as in the file names
+    entries: HashMap<[[u8; 6]; 2], Entry>,
+    max_pieces: usize,
+}
+
+impl Tablebase {
+    //scans the directories (separated like PATH) for table files; files are only
+    //read once a probe needs them
+    pub fn open(paths: &str) -> io::Result<Self> {
+        let mut entries: HashMap<[[u8; 6]; 2], Entry> = HashMap::new();
+        for dir in std::env::split_paths(paths).filter(|dir| !dir.as_os_str().is_empty()) {
+            for file in fs::read_dir(&dir)? {
+                let path = file?.path();
+                let (Some(stem), Some(ext)) = (
+                    path.file_stem().and_then(|s| s.to_str()),
+                    path.extension().and_then(|s| s.to_str()),
+                ) else {
+                    continue;
+                };
+                let Some(material) = parse_material(stem) else {
+                    continue;
+                };
+                let entry = entries.entry(material.counts).or_insert_with(|| Entry {
+                    material,
+                    wdl_path: None,
+                    dtz_path: None,
+                    wdl: OnceLock::new(),
+                    dtz: OnceLock::new(),
+                });
+                match ext {
+                    "rtbw" => entry.wdl_path = Some(path),
+                    "rtbz" => entry.dtz_path = Some(path),
+                    _ => {}
+                }
+            }
+        }
+        //dtz alone is of no use, every probe starts with wdl
+        entries.retain(|_, entry| entry.wdl_path.is_some());
+        let max_pieces = entries.values().map(|e| e.material.piece_count()).max().unwrap_or(0);
+        Ok(Self { entries, max_pieces })
+    }
+
+    //number of material combinations with a wdl table
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    //pieces (kings included) of the largest table
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    //the tables know nothing about castling
+    pub fn covers(&self, pos: &Position) -> bool {
+        pos.castling_rights == 0 && piece_count(pos) <= self.max_pieces
+    }
+
+    //outcome for the side to move, not counting the fifty move counter of pos;
+    //None if pos isn't covered or a table is missing
+    pub fn probe_wdl(&self, pos: &mut Position) -> Option<Wdl> {
+        if !self.covers(pos) {
+            return None;
+        }
+        self.search(pos, false).map(|(wdl, _)| wdl)
+    }
+
+    //plies to the next capture or pawn move with best play, positive if the side to
+    //move wins, negative if it loses, 0 for a draw; like the tables themselves this
+    //can be one ply too long
+    pub fn probe_dtz(&self, pos: &mut Position) -> Option<i32> {
+        if !self.covers(pos) {
+            return None;
+        }
+        self.dtz(pos)
+    }
+
+    //the moves of moves keeping the best outcome: winning ones reaching a zeroing
+    //move soonest, all drawing ones, or losing ones holding out longest
+    pub fn dtz_optimal_moves(&self, pos: &mut Position, moves: &[Move]) -> Option<Vec<Move>> {
+        if !self.covers(pos) {
+            return None;
+        }
+        let mut ranked = Vec::with_capacity(moves.len());
+        for &mv in moves {
+            let undo = pos.make_move_with_undo(mv);
+            //dtz of the move, counted from the root
+            let dtz = if pos.half_move_clock == 0 {
+                self.search(pos, false).map(|(wdl, _)| dtz_before_zeroing(-wdl))
+            } else {
+                self.dtz(pos).map(|d| -d - d.signum())
+            };
+            let mates = dtz == Some(2) && is_in_check(pos, pos.player_to_move) && !has_legal_move(pos);
+            pos.undo_move(undo);
+
+            let dtz = if mates { 1 } else { dtz? };
+            let rank = match dtz.signum() {
+                1 => (2, -dtz),
+                0 => (1, 0),
+                _ => (0, -dtz),
+            };
+            ranked.push((mv, rank));
+        }
+        let best = ranked.iter().map(|&(_, rank)| rank).max()?;
+        Some(ranked.into_iter().filter(|&(_, rank)| rank == best).map(|(mv, _)| mv).collect())
+    }
+
+    //captures (and pawn moves for dtz) are searched before trusting the table: it
+    //doesn't know en passant and stores "don't care" where a capture wins;
+    //also returns whether the best move is one of those searched
+    fn search(&self, pos: &mut Position, pawn_moves: bool) -> Option<(Wdl, bool)> {
+        let mut moves = Vec::new();
+        generate_legal_moves(pos, &mut moves);
+
+        let mut best = Wdl::Loss;
+        let mut searched = 0;
+        for &mv in &moves {
+            let zeroing = is_capture(pos, mv) || (pawn_moves && is_pawn_move(pos, mv));
+            if !zeroing {
+                continue;
+            }
+            searched += 1;
+            let undo = pos.make_move_with_undo(mv);
+            let value = self.search(pos, false).map(|(wdl, _)| -wdl);
+            pos.undo_move(undo);
+
+            let value = value?;
+            if value > best {
+                best = value;
+                if value == Wdl::Win {
+                    return Some((value, true));
+                }
+            }
+        }
+
+        //every move searched: the table can't add anything
+        let all_searched = searched > 0 && searched == moves.len();
+        let value = if all_searched { best } else { self.probe_wdl_table(pos)? };
+        if best >= value {
+            return Some((best, best > Wdl::Draw || all_searched));
+        }
+        Some((value, false))
+    }
+
+    fn dtz(&self, pos: &mut Position) -> Option<i32> {
+        let (wdl, zeroing_best) = self.search(pos, true)?;
+        if wdl == Wdl::Draw {
+            return Some(0);
+        }
+        if zeroing_best {
+            return Some(dtz_before_zeroing(wdl));
+        }
+        if let Some(dtz) = self.probe_dtz_table(pos, wdl)? {
+            let cursed = matches!(wdl, Wdl::CursedWin | Wdl::BlessedLoss);
+            return Some((dtz + if cursed { 100 } else { 0 }) * wdl.signum());
+        }
+
+        //the table only has the other side to move: best child, one ply further
+        let mut moves = Vec::new();
+        generate_legal_moves(pos, &mut moves);
+        let mut best: Option<i32> = None;
+        for mv in moves {
+            let zeroing = is_capture(pos, mv) || is_pawn_move(pos, mv);
+            let undo = pos.make_move_with_undo(mv);
+            //a zeroing move restarts the count: take the dtz before it
+            let dtz = if zeroing {
+                self.search(pos, false).map(|(wdl, _)| -dtz_before_zeroing(wdl))
+            } else {
+                self.dtz(pos).map(|d| -d)
+            };
+            let mates = dtz == Some(1) && is_in_check(pos, pos.player_to_move) && !has_legal_move(pos);
+            pos.undo_move(undo);
+
+            let mut dtz = dtz?;
+            if mates {
+                best = Some(1);
+            }
+            if !zeroing {
+                dtz += dtz.signum();
+            }
+            if dtz.signum() == wdl.signum() && best.is_none_or(|b| dtz < b) {
+                best = Some(dtz);
+            }
+        }
+        //no legal move: mated
+        Some(best.unwrap_or(-1))
+    }
+
+    //the entry of the position and whether its colors are swapped relative to it
+    fn entry(&self, pos: &Position) -> Option<(&Entry, bool)> {
+        let [white, black] = material_counts(pos);
+        if let Some(entry) = self.entries.get(&[white, black]) {
+            return Some((entry, false));
+        }
+        self.entries.get(&[black, white]).map(|entry| (entry, true))
+    }
+
+    fn probe_wdl_table(&self, pos: &Position) -> Option<Wdl> {
+        if piece_count(pos) == 2 {
+            return Some(Wdl::Draw);
+        }
+        let (entry, flipped) = self.entry(pos)?;
+        let table = entry.table(TableKind::Wdl)?;
+        table.wdl(&table.lookup(pos, flipped)?)
+    }
+
+    //None inside: the table stores the other side to move
+    fn probe_dtz_table(&self, pos: &Position, wdl: Wdl) -> Option<Option<i32>> {
+        let (entry, flipped) = self.entry(pos)?;
+        let table = entry.table(TableKind::Dtz)?;
+        table.dtz(&table.lookup(pos, flipped)?, wdl)
+    }
+}
+
+//dtz of a position whose best move is zeroing, given its outcome
+fn dtz_before_zeroing(wdl: Wdl) -> i32 {
+    match wdl {
+        Wdl::Win => 1,
+        Wdl::CursedWin => 101,
+        Wdl::Draw => 0,
+        Wdl::BlessedLoss => -101,
+        Wdl::Loss => -1,
+    }
+}
+
+#[inline]
+fn piece_count(pos: &Position) -> usize {
+    pos.piece_counter.iter().map(|&n| n as usize).sum()
+}
+
+//piece_counter split by color, kinds in PieceKind order
+fn material_counts(pos: &Position) -> [[u8; 6]; 2] {
+    let mut counts = [[0; 6]; 2];
+    counts[0].copy_from_slice(&pos.piece_counter[..6]);
+    counts[1].copy_from_slice(&pos.piece_counter[6..]);
+    counts
+}
+
+#[inline]
+fn is_capture(pos: &Position, mv: Move) -> bool {
+    mv.is_en_passant() || matches!(pos.board[mv.to_sq()], Cell::Piece(_))
+}
+
+#[inline]
+fn is_pawn_move(pos: &Position, mv: Move) -> bool {
+    matches!(pos.board[mv.from_sq()], Cell::Piece(piece) if piece.kind == PieceKind::Pawn)
+}
+
+//"KRPvKP": white pieces, 'v', black pieces, one king each
+fn parse_material(name: &str) -> Option<Material> {
+    let (white, black) = name.split_once('v')?;
+    let mut counts = [[0u8; 6]; 2];
+    for (side, pieces) in [white, black].into_iter().enumerate() {
+        for c in pieces.chars() {
+            let kind = match c {
+                'P' => PieceKind::Pawn,
+                'N' => PieceKind::Knight,
+                'B' => PieceKind::Bishop,
+                'R' => PieceKind::Rook,
+                'Q' => PieceKind::Queen,
+                'K' => PieceKind::King,
+                _ => return None,
+            };
+            counts[side][kind.idx()] += 1;
+        }
+        if counts[side][PieceKind::King.idx()] != 1 {
+            return None;
+        }
+    }
+    let material = Material { counts };
+    (material.piece_count() <= encoding::MAX_PIECES).then_some(material)
+}
+
+//synthetic tables for tests of the probing and the search, real tables are too big
+//to live in the repository
+#[cfg(test)]
+pub(crate) mod test_tables {
+    use super::*;
+    use std::path::Path;
+
+    //a table of kq vs k (or its dtz) where every position of a side has the same value
+    pub(crate) fn single_value_table(magic: [u8; 4], values: &[(u8, u8)]) -> Vec<u8> {
+        let mut data = magic.to_vec();
+        //no pawns; leading group first; white queen, white king, black king for both
+        //sides; padding to an even offset
+        data.extend([0x01, 0x00, 0x55, 0x66, 0xee, 0x00]);
+        for &(flags, value) in values {
+            data.extend([0x80 | flags, value]);
+        }
+        data.resize(64, 0);
+        data
+    }
+
+    //temporary directory of table files, removed on drop; tables are only read when a
+    //probe needs them, so it has to outlive the probes
+    pub(crate) struct TableDir(PathBuf);
+
+    impl TableDir {
+        pub(crate) fn new(name: &str, files: &[(&str, Vec<u8>)]) -> Self {
+            let dir = std::env::temp_dir().join(format!("syzygy-{name}-{}", std::process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            for (file, data) in files {
+                fs::write(dir.join(file), data).unwrap();
+            }
+            TableDir(dir)
+        }
+
+        pub(crate) fn path(&self) -> &Path {
+            &self.0
+        }
+
+        pub(crate) fn open(&self) -> Tablebase {
+            Tablebase::open(self.0.to_str().unwrap()).unwrap()
+        }
+    }
+
+    impl Drop for TableDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    //kqk: white to move always wins in 3 moves, black to move always loses
+    pub(crate) fn kqk_tablebase(name: &str) -> Tablebase {
+        let wdl = single_value_table([0x71, 0xe8, 0x23, 0x5d], &[(0, 4), (0, 0)]);
+        let dtz = single_value_table([0xd7, 0x66, 0x0c, 0xa5], &[(0, 3)]);
+        let dir = TableDir::new(name, &[("KQvK.rtbw", wdl), ("KQvK.rtbz", dtz)]);
+        let tb = dir.open();
+        //read now, the directory goes with dir
+        for entry in tb.entries.values() {
+            entry.table(TableKind::Wdl).unwrap();
+            entry.table(TableKind::Dtz).unwrap();
+        }
+        tb
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::test_tables::{TableDir, kqk_tablebase};
+
+    fn pos(fen: &str) -> Position {
+        Position::from_fen(fen).unwrap()
+    }
+
+    #[test]
+    fn open_indexes_tables_by_material() {
+        let dir = TableDir::new(
+            "index",
+            &[
+                ("KRvK.rtbw", Vec::new()),
+                ("KRvK.rtbz", Vec::new()),
+                ("KQvKR.rtbw", Vec::new()),
+                //without its wdl table, or not a table at all
+                ("KPvK.rtbz", Vec::new()),
+                ("KXvK.rtbw", Vec::new()),
+                ("README.txt", Vec::new()),
+            ],
+        );
+        let tb = dir.open();
+        assert_eq!(tb.len(), 2);
+        assert_eq!(tb.max_pieces(), 4);
+
+        let (_, flipped) = tb.entry(&pos("8/8/8/8/8/8/8/K1k1r3 w - - 0 1")).unwrap();
+        assert!(flipped);
+        let (_, flipped) = tb.entry(&pos("4R3/8/8/8/8/8/8/K1k5 b - - 0 1")).unwrap();
+        assert!(!flipped);
+        assert!(tb.entry(&pos("4B3/8/8/8/8/8/8/K1k5 w - - 0 1")).is_none());
+
+        assert!(Tablebase::open(dir.path().join("missing").to_str().unwrap()).is_err());
+    }
+
+    #[test]
+    fn broken_files_and_uncovered_positions_fail_the_probe() {
+        let dir = TableDir::new("broken", &[("KRvK.rtbw", vec![0x71, 0xe8, 0x23, 0x5d, 0x01])]);
+        let tb = dir.open();
+        assert_eq!(tb.probe_wdl(&mut pos("4R3/8/8/8/8/8/8/K1k5 w - - 0 1")), None);
+        //castling rights, or more pieces than any table
+        assert_eq!(tb.probe_wdl(&mut pos("8/8/8/8/8/8/8/R3K1k1 w Q - 0 1")), None);
+        assert_eq!(tb.probe_wdl(&mut pos("3k4/8/8/8/8/8/8/KRR5 w - - 0 1")), None);
+        assert!(Tablebase::open("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn wdl_follows_the_side_to_move_and_swapped_colors() {
+        let tb = kqk_tablebase("wdl");
+        assert_eq!(tb.probe_wdl(&mut pos("7k/8/8/8/8/3Q4/8/K7 w - - 0 1")), Some(Wdl::Win));
+        assert_eq!(tb.probe_wdl(&mut pos("7k/8/8/8/8/3Q4/8/K7 b - - 0 1")), Some(Wdl::Loss));
+        //black has the queen
+        assert_eq!(tb.probe_wdl(&mut pos("7K/8/8/8/8/3q4/8/k7 w - - 0 1")), Some(Wdl::Loss));
+        assert_eq!(tb.probe_wdl(&mut pos("7K/8/8/8/8/3q4/8/k7 b - - 0 1")), Some(Wdl::Win));
+        //the hanging queen is taken, whatever the table says
+        assert_eq!(tb.probe_wdl(&mut pos("8/8/8/8/8/8/5Qk1/K7 b - - 0 1")), Some(Wdl::Draw));
+    }
+
+    #[test]
+    fn dtz_searches_one_ply_for_the_side_the_table_lacks() {
+        let tb = kqk_tablebase("dtz");
+        //stored in moves: 3 moves are 6 plies, plus one
+        assert_eq!(tb.probe_dtz(&mut pos("7k/8/8/8/8/3Q4/8/K7 w - - 0 1")), Some(7));
+        assert_eq!(tb.probe_dtz(&mut pos("7k/8/8/8/8/3Q4/8/K7 b - - 0 1")), Some(-8));
+        assert_eq!(tb.probe_dtz(&mut pos("8/8/8/8/8/8/5Qk1/K7 b - - 0 1")), Some(0));
+    }
+
+    #[test]
+    fn root_keeps_the_mating_moves() {
+        let tb = kqk_tablebase("root");
+        let mut p = pos("7k/5K2/8/8/8/8/8/6Q1 w - - 0 1");
+        let mut moves = Vec::new();
+        generate_legal_moves(&p, &mut moves);
+
+        let mates: Vec<Move> = moves
+            .iter()
+            .copied()
+            .filter(|&mv| {
+                let undo = p.make_move_with_undo(mv);
+                let mate = is_in_check(&p, p.player_to_move) && !has_legal_move(&p);
+                p.undo_move(undo);
+                mate
+            })
+            .collect();
+        assert!(!mates.is_empty());
+        assert_eq!(tb.dtz_optimal_moves(&mut p, &moves), Some(mates));
+    }
+}
+
+//the real 3-5 piece tables, which can't live in the repository: run with
+//SYZYGY_PATH=<dir> cargo test -- --ignored
+//the synthetic tables above have one value per side, so only these reach the
+//huffman decoding, the value maps and the piece index of real files
+#[cfg(test)]
+mod syzygy_tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn syzygy() -> Option<Tablebase> {
+        let Ok(path) = std::env::var("SYZYGY_PATH") else {
+            eprintln!("SYZYGY_PATH not set, skipped");
+            return None;
+        };
+        let tb = Tablebase::open(&path).unwrap();
+        assert!(tb.max_pieces() >= 5, "SYZYGY_PATH needs the 3-5 piece tables");
+        Some(tb)
+    }
+
+    fn wdl(tb: &Tablebase, fen: &str) -> Wdl {
+        let mut pos = Position::from_fen(fen).unwrap();
+        tb.probe_wdl(&mut pos).unwrap_or_else(|| panic!("no wdl for {fen}"))
+    }
+
+    fn dtz(tb: &Tablebase, fen: &str) -> i32 {
+        let mut pos = Position::from_fen(fen).unwrap();
+        tb.probe_dtz(&mut pos).unwrap_or_else(|| panic!("no dtz for {fen}"))
+    }
+
+    //a..h -> h..a
+    fn mirror_files(fen: &str) -> String {
+        let mut parts: Vec<String> = fen.split(' ').map(String::from).collect();
+        parts[0] = parts[0]
+            .split('/')
+            .map(|rank| expand(rank).chars().rev().collect::<String>())
+            .map(|rank| compress(&rank))
+            .collect::<Vec<_>>()
+            .join("/");
+        parts.join(" ")
+    }
+
+    //rank 1 <-> rank 8, only the same position without pawns
+    fn flip_ranks(fen: &str) -> String {
+        let mut parts: Vec<String> = fen.split(' ').map(String::from).collect();
+        parts[0] = parts[0].split('/').rev().collect::<Vec<_>>().join("/");
+        parts.join(" ")
+    }
+
+    //ranks flipped, colors swapped: the same position for the other side
+    fn swap_colors(fen: &str) -> String {
+        let mut parts: Vec<String> = flip_ranks(fen).split(' ').map(String::from).collect();
+        parts[0] = parts[0]
+            .chars()
+            .map(|c| if c.is_ascii_uppercase() { c.to_ascii_lowercase() } else { c.to_ascii_uppercase() })
+            .collect();
+        parts[1] = if parts[1] == "w" { "b" } else { "w" }.to_string();
+        parts.join(" ")
+    }
+
+    fn expand(rank: &str) -> String {
+        rank.chars()
+            .map(|c| match c.to_digit(10) {
+                Some(n) => ".".repeat(n as usize),
+                None => c.to_string(),
+            })
+            .collect()
+    }
+
+    fn compress(rank: &str) -> String {
+        let mut out = String::new();
+        let mut empty = 0;
+        for c in rank.chars() {
+            if c == '.' {
+                empty += 1;
+                continue;
+            }
+            if empty > 0 {
+                out.push_str(&empty.to_string());
+                empty = 0;
+            }
+            out.push(c);
+        }
+        if empty > 0 {
+            out.push_str(&empty.to_string());
+        }
+        out
+    }
+
+    //legal position with the pieces on random squares, pawns off the back ranks
+    fn random_fen(rng: &mut StdRng, pieces: &str) -> String {
+        loop {
+            let mut board = ['.'; 64];
+            for piece in pieces.chars() {
+                let sq = loop {
+                    let sq = rng.gen_range(0..64);
+                    let back_rank = !(8..56).contains(&sq);
+                    if board[sq] == '.' && !(back_rank && piece.eq_ignore_ascii_case(&'p')) {
+                        break sq;
+                    }
+                };
+                board[sq] = piece;
+            }
+            let ranks: Vec<String> = board.chunks(8).map(|rank| compress(&rank.iter().collect::<String>())).collect();
+            let side = if rng.r#gen() { "w" } else { "b" };
+            let fen = format!("{} {side} - - 0 1", ranks.join("/"));
+            let pos = Position::from_fen(&fen).unwrap();
+            //also rules out touching kings
+            if !is_in_check(&pos, pos.player_to_move.opposite()) {
+                return fen;
+            }
+        }
+    }
+
+    //the value of a position is the best of its moves, and it doesn't change
+    //with the board mirrored or the colors swapped
+    fn check_consistent(tb: &Tablebase, fen: &str, pawns: bool) {
+        let mut pos = Position::from_fen(fen).unwrap();
+        let value = wdl(tb, fen);
+        let mut moves = Vec::new();
+        generate_legal_moves(&pos, &mut moves);
+        let best = moves
+            .iter()
+            .map(|&mv| {
+                let undo = pos.make_move_with_undo(mv);
+                let child = tb.probe_wdl(&mut pos).map(|wdl| -wdl);
+                pos.undo_move(undo);
+                child.unwrap_or_else(|| panic!("no wdl after {} in {fen}", mv.to_uci()))
+            })
+            .max()
+            .unwrap_or(if is_in_check(&pos, pos.player_to_move) { Wdl::Loss } else { Wdl::Draw });
+        assert_eq!(value, best, "{fen}");
+        let distance = dtz(tb, fen);
+        assert_eq!(distance.signum(), value.signum(), "{fen}");
+
+        let mut same = vec![swap_colors(fen), mirror_files(fen)];
+        if !pawns {
+            same.push(flip_ranks(fen));
+        }
+        for other in same {
+            assert_eq!(wdl(tb, &other), value, "{fen} vs {other}");
+            assert_eq!(dtz(tb, &other), distance, "{fen} vs {other}");
+        }
+    }
+
+    #[test]
+    fn board_transforms() {
+        let lucena = "1K1k4/1P6/8/8/8/8/r7/2R5 w - - 0 1";
+        assert_eq!(mirror_files(lucena), "4k1K1/6P1/8/8/8/8/7r/5R2 w - - 0 1");
+        assert_eq!(flip_ranks(lucena), "2R5/r7/8/8/8/8/1P6/1K1k4 w - - 0 1");
+        assert_eq!(swap_colors(lucena), "2r5/R7/8/8/8/8/1p6/1k1K4 b - - 0 1");
+
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..20 {
+            let fen = random_fen(&mut rng, "KPkp");
+            assert_eq!(swap_colors(&swap_colors(&fen)), fen);
+            let ranks: Vec<&str> = fen.split(' ').next().unwrap().split('/').collect();
+            assert!(![ranks[0], ranks[7]].iter().any(|rank| rank.contains(['P', 'p'])), "{fen}");
+        }
+    }
+
+    #[test]
+    #[ignore = "needs the 3-5 piece syzygy tables in SYZYGY_PATH"]
+    fn krvk_and_kbnvk_known_values() {
+        let Some(tb) = syzygy() else { return };
+        //ra8 mates at once, and is the only move that does
+        let mate = "6k1/8/6K1/8/8/8/8/R7 w - - 0 1";
+        assert_eq!(wdl(&tb, mate), Wdl::Win);
+        assert_eq!(dtz(&tb, mate), 1);
+        let mut pos = Position::from_fen(mate).unwrap();
+        let mut moves = Vec::new();
+        generate_legal_moves(&pos, &mut moves);
+        let best = tb.dtz_optimal_moves(&mut pos, &moves).unwrap();
+        assert_eq!(best.iter().map(|mv| mv.to_uci()).collect::<Vec<_>>(), ["a1a8"]);
+        //the same with black's rook
+        assert_eq!(dtz(&tb, "r7/8/8/8/8/6k1/8/6K1 b - - 0 1"), 1);
+
+        assert_eq!(wdl(&tb, "8/8/8/4k3/8/8/8/R3K3 b - - 0 1"), Wdl::Loss);
+        assert!(dtz(&tb, "8/8/8/4k3/8/8/8/R3K3 b - - 0 1") < 0);
+        //stalemate, and the rook taken
+        assert_eq!(wdl(&tb, "k1K5/7R/8/8/8/8/8/8 b - - 0 1"), Wdl::Draw);
+        assert_eq!(dtz(&tb, "k1K5/7R/8/8/8/8/8/8 b - - 0 1"), 0);
+        assert_eq!(wdl(&tb, "8/8/8/8/8/2k5/1R6/7K b - - 0 1"), Wdl::Draw);
+
+        //bishop and knight mate within the fifty moves from anywhere
+        let kbnk = "8/8/8/4k3/8/8/8/KBN5 w - - 0 1";
+        assert_eq!(wdl(&tb, kbnk), Wdl::Win);
+        assert!((1..100).contains(&dtz(&tb, kbnk)));
+        assert_eq!(wdl(&tb, &swap_colors(kbnk)), Wdl::Win);
+        assert_eq!(wdl(&tb, "B6K/8/8/8/8/2k5/1N6/8 b - - 0 1"), Wdl::Draw);
+    }
+
+    #[test]
+    #[ignore = "needs the 3-5 piece syzygy tables in SYZYGY_PATH"]
+    fn kpvk_opposition_and_rook_pawn() {
+        let Some(tb) = syzygy() else { return };
+        //black has the opposition with white to move, and loses it to move
+        assert_eq!(wdl(&tb, "8/4k3/8/4K3/4P3/8/8/8 w - - 0 1"), Wdl::Draw);
+        assert_eq!(dtz(&tb, "8/4k3/8/4K3/4P3/8/8/8 w - - 0 1"), 0);
+        assert_eq!(wdl(&tb, "8/4k3/8/4K3/4P3/8/8/8 b - - 0 1"), Wdl::Loss);
+        //white king on a key square
+        assert_eq!(wdl(&tb, "4k3/8/4K3/4P3/8/8/8/8 w - - 0 1"), Wdl::Win);
+        assert_eq!(wdl(&tb, "4k3/8/4K3/4P3/8/8/8/8 b - - 0 1"), Wdl::Loss);
+        //the defending king in the corner holds the rook pawn
+        assert_eq!(wdl(&tb, "k7/8/8/8/8/8/P7/K7 w - - 0 1"), Wdl::Draw);
+        assert_eq!(wdl(&tb, "k7/8/8/8/8/8/P7/K7 b - - 0 1"), Wdl::Draw);
+        //promotion is zeroing, the pawn is taken on the seventh
+        assert_eq!(dtz(&tb, "8/4P3/8/8/8/8/k7/4K3 w - - 0 1"), 1);
+        assert_eq!(wdl(&tb, "8/3kP3/8/8/8/8/8/K7 b - - 0 1"), Wdl::Draw);
+    }
+
+    #[test]
+    #[ignore = "needs the 3-5 piece syzygy tables in SYZYGY_PATH"]
+    fn five_piece_pawn_tables_and_en_passant() {
+        let Some(tb) = syzygy() else { return };
+        //lucena: the bridge wins
+        let lucena = "1K1k4/1P6/8/8/8/8/r7/2R5 w - - 0 1";
+        assert_eq!(wdl(&tb, lucena), Wdl::Win);
+        assert!(dtz(&tb, lucena) > 0);
+        assert_eq!(wdl(&tb, &mirror_files(lucena)), Wdl::Win);
+        assert_eq!(wdl(&tb, &swap_colors(lucena)), Wdl::Win);
+
+        //black's only move is dxe3 e.p., and kxe3 follows: without en passant it
+        //would be stalemate
+        let ep = "k7/8/1Q6/8/3pP3/3K4/8/8 b - e3 0 1";
+        assert_eq!(wdl(&tb, ep), Wdl::Loss);
+        assert_eq!(dtz(&tb, ep), -1);
+        assert_eq!(wdl(&tb, "k7/8/1Q6/8/3pP3/3K4/8/8 b - - 0 1"), Wdl::Draw);
+    }
+
+    #[test]
+    #[ignore = "needs the 3-5 piece syzygy tables in SYZYGY_PATH"]
+    fn symmetric_material_uses_the_side_to_move() {
+        let Some(tb) = syzygy() else { return };
+        //krvkr and kpvkp are stored for one color only: rb8 mates, and so does rb1
+        //for black in the swapped position
+        let mate = "7k/8/6K1/8/8/8/r7/1R6 w - - 0 1";
+        assert_eq!(wdl(&tb, mate), Wdl::Win);
+        assert_eq!(dtz(&tb, mate), 1);
+        assert_eq!(wdl(&tb, "1r6/R7/8/8/8/6k1/8/7K b - - 0 1"), Wdl::Win);
+        assert_eq!(dtz(&tb, "1r6/R7/8/8/8/6k1/8/7K b - - 0 1"), 1);
+
+        let mut rng = StdRng::seed_from_u64(17);
+        for _ in 0..200 {
+            check_consistent(&tb, &random_fen(&mut rng, "KRkr"), false);
+            check_consistent(&tb, &random_fen(&mut rng, "KPkp"), true);
+        }
+    }
+
+    #[test]
+    #[ignore = "needs the 3-5 piece syzygy tables in SYZYGY_PATH"]
+    fn random_positions_agree_with_their_moves_and_mirrors() {
+        let Some(tb) = syzygy() else { return };
+        let mut rng = StdRng::seed_from_u64(29);
+        for (pieces, pawns) in [("KRk", false), ("KPk", true), ("KBNk", false), ("KQkr", false), ("KPPk", true)] {
+            for _ in 0..200 {
+                check_consistent(&tb, &random_fen(&mut rng, pieces), pawns);
+            }
+        }
+        //the colors of the table file swapped
+        for _ in 0..100 {
+            check_consistent(&tb, &random_fen(&mut rng, "Kkpp"), true);
+        }
+    }
+}
@@ -0,0 +1,674 @@
+//one .rtbw/.rtbz file: header parsing, position index and decompression of single
+//values; files are trusted to be well formed only as far as never reading out of
+//bounds, anything inconsistent makes the lookup fail
+
+use crate::board::bitboard::{Bitboard, squares};
+use crate::board::mailbox120::SQUARE64_TO_SQUARE120;
+use crate::position::{Cell, Color, PieceKind, Position};
+
+use super::Wdl;
+use super::encoding::{ENCODING, MAX_PIECES, file_of, off_diagonal, rank_of};
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xe8, 0x23, 0x5d];
+const DTZ_MAGIC: [u8; 4] = [0xd7, 0x66, 0x0c, 0xa5];
+
+//flags of one table part
+const FLAG_STM: u8 = 1;
+const FLAG_MAPPED: u8 = 2;
+const FLAG_WIN_PLIES: u8 = 4;
+const FLAG_LOSS_PLIES: u8 = 8;
+const FLAG_WIDE: u8 = 16;
+const FLAG_SINGLE_VALUE: u8 = 128;
+
+//bit 1 of the file flags
+const FILE_HAS_PAWNS: u8 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum TableKind {
+    Wdl,
+    Dtz,
+}
+
+//material of a table: counts[color][kind], white is the left side of the file name
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) struct Material {
+    pub counts: [[u8; 6]; 2],
+}
+
+impl Material {
+    pub fn piece_count(&self) -> usize {
+        self.counts.iter().flatten().map(|&n| n as usize).sum()
+    }
+
+    pub fn has_pawns(&self) -> bool {
+        self.counts[0][0] + self.counts[1][0] > 0
+    }
+
+    pub fn is_symmetric(&self) -> bool {
+        self.counts[0] == self.counts[1]
+    }
+}
+
+//one part of a table: one side to move and, with pawns, one file of the leading pawn
+#[derive(Clone, Debug, Default)]
+struct PairsData {
+    flags: u8,
+    //piece codes (kind + 1, +8 for black) in the order of the encoding
+    pieces: [u8; MAX_PIECES],
+    //pieces per group, zero terminated
+    group_len: [usize; MAX_PIECES + 1],
+    //factor of each group in the index, group_idx[groups] is the size of the part
+    group_idx: [u64; MAX_PIECES + 1],
+    block_size: usize,
+    span: u64,
+    sparse_index: usize,
+    sparse_index_size: usize,
+    block_lengths: usize,
+    block_lengths_size: usize,
+    blocks: usize,
+    blocks_data: usize,
+    //the value itself for single value parts
+    min_sym_len: u8,
+    lowest_sym: usize,
+    //lowest code of every symbol length, left aligned
+    base64: Vec<u64>,
+    //number of values - 1 each symbol expands to
+    symlen: Vec<u8>,
+    btree: usize,
+    //dtz: start of the value map for each wdl outcome
+    map_idx: [usize; 4],
+}
+
+pub(super) struct Table {
+    data: Vec<u8>,
+    kind: TableKind,
+    symmetric: bool,
+    has_pawns: bool,
+    has_unique_pieces: bool,
+    //pawns of the leading color, pawns of the other color
+    pawn_count: [usize; 2],
+    piece_count: usize,
+    //[side to move][file]: one side for dtz and symmetric tables, one file without pawns
+    parts: Vec<Vec<PairsData>>,
+    //dtz value maps
+    map: usize,
+}
+
+//position index into a table part
+pub(super) struct Lookup {
+    side: usize,
+    file: usize,
+    idx: u64,
+}
+
+impl Table {
+    pub fn parse(data: Vec<u8>, kind: TableKind, material: &Material) -> Option<Self> {
+        let magic = match kind {
+            TableKind::Wdl => WDL_MAGIC,
+            TableKind::Dtz => DTZ_MAGIC,
+        };
+        if data.get(..4)? != magic {
+            return None;
+        }
+
+        let has_pawns = material.has_pawns();
+        let [white_pawns, black_pawns] = [material.counts[0][0] as usize, material.counts[1][0] as usize];
+        //the leading color is the one with fewer pawns, if both have some
+        let white_leads = black_pawns == 0 || (white_pawns > 0 && black_pawns >= white_pawns);
+        let pawn_count = if white_leads {
+            [white_pawns, black_pawns]
+        } else {
+            [black_pawns, white_pawns]
+        };
+        let has_unique_pieces = material.counts.iter().any(|side| side[..5].contains(&1));
+        let symmetric = material.is_symmetric();
+        let sides = if kind == TableKind::Wdl && !symmetric { 2 } else { 1 };
+        let files = if has_pawns { 4 } else { 1 };
+
+        let mut table = Self {
+            data,
+            kind,
+            symmetric,
+            has_pawns,
+            has_unique_pieces,
+            pawn_count,
+            piece_count: material.piece_count(),
+            parts: vec![vec![PairsData::default(); files]; sides],
+            map: 0,
+        };
+        if table.piece_count > MAX_PIECES || (byte(&table.data, 4)? & FILE_HAS_PAWNS != 0) != has_pawns {
+            return None;
+        }
+        table.parse_parts(sides, files)?;
+        Some(table)
+    }
+
+    fn parse_parts(&mut self, sides: usize, files: usize) -> Option<()> {
+        let data = std::mem::take(&mut self.data);
+        let both_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let mut at = 5;
+
+        for file in 0..files {
+            let first = byte(&data, at)?;
+            let second = if both_pawns { byte(&data, at + 1)? } else { 0xff };
+            let order = [[first & 0xf, second & 0xf], [first >> 4, second >> 4]];
+            at += 1 + both_pawns as usize;
+            for k in 0..self.piece_count {
+                let b = byte(&data, at)?;
+                for side in 0..sides {
+                    self.parts[side][file].pieces[k] = if side == 0 { b & 0xf } else { b >> 4 };
+                }
+                at += 1;
+            }
+            for (side, &order) in order.iter().enumerate().take(sides) {
+                self.set_groups(side, file, order)?;
+            }
+        }
+        at += at & 1;
+
+        for file in 0..files {
+            for side in 0..sides {
+                at = self.parts[side][file].set_sizes(&data, at)?;
+            }
+        }
+
+        if self.kind == TableKind::Dtz {
+            self.map = at;
+            for part in &mut self.parts[0] {
+                if part.flags & FLAG_MAPPED == 0 {
+                    continue;
+                }
+                //four maps, one per outcome, each prefixed by its length
+                if part.flags & FLAG_WIDE != 0 {
+                    at += at & 1;
+                    for i in 0..4 {
+                        part.map_idx[i] = (at - self.map) / 2 + 1;
+                        at += 2 * u16_le(&data, at)? as usize + 2;
+                    }
+                } else {
+                    for i in 0..4 {
+                        part.map_idx[i] = at - self.map + 1;
+                        at += byte(&data, at)? as usize + 1;
+                    }
+                }
+            }
+            at += at & 1;
+        }
+
+        for file in 0..files {
+            for side in 0..sides {
+                let part = &mut self.parts[side][file];
+                part.sparse_index = at;
+                at += part.sparse_index_size * 6;
+            }
+        }
+        for file in 0..files {
+            for side in 0..sides {
+                let part = &mut self.parts[side][file];
+                part.block_lengths = at;
+                at += part.block_lengths_size * 2;
+            }
+        }
+        for file in 0..files {
+            for side in 0..sides {
+                let part = &mut self.parts[side][file];
+                at = (at + 0x3f) & !0x3f;
+                part.blocks_data = at;
+                at += part.blocks * part.block_size;
+            }
+        }
+
+        if at > data.len() {
+            return None;
+        }
+        self.data = data;
+        Some(())
+    }
+
+    //splits pieces[] into groups and works out the factor of every group in the index;
+    //order[0] is the position of the leading group, order[1] the one of the other
+    //color's pawns (0xf if there are none)
+    fn set_groups(&mut self, side: usize, file: usize, order: [u8; 2]) -> Option<()> {
+        let e = &ENCODING;
+        let piece_count = self.piece_count;
+        let has_pawns = self.has_pawns;
+        let both_pawns = has_pawns && self.pawn_count[1] > 0;
+        let leading_size = if self.has_unique_pieces { 31332 } else { 462 };
+        let part = &mut self.parts[side][file];
+
+        //pieces of the leading group: the leading pawns, 3 unique pieces or the two kings
+        let mut first_len: i32 = if has_pawns {
+            0
+        } else if self.has_unique_pieces {
+            3
+        } else {
+            2
+        };
+        let mut n = 0;
+        part.group_len[0] = 1;
+        for i in 1..piece_count {
+            first_len -= 1;
+            if first_len > 0 || part.pieces[i] == part.pieces[i - 1] {
+                part.group_len[n] += 1;
+            } else {
+                n += 1;
+                part.group_len[n] = 1;
+            }
+        }
+        n += 1;
+        part.group_len[n] = 0;
+
+        let mut next = if both_pawns { 2 } else { 1 };
+        let mut free_squares = 64 - part.group_len[0] - if both_pawns { part.group_len[1] } else { 0 };
+        let mut idx: u64 = 1;
+        let mut k = 0;
+        while next < n || k == order[0] || k == order[1] {
+            if k == order[0] {
+                part.group_idx[0] = idx;
+                idx *= if has_pawns {
+                    *e.lead_pawns_size.get(part.group_len[0])?.get(file)?
+                } else {
+                    leading_size
+                };
+            } else if k == order[1] {
+                part.group_idx[1] = idx;
+                idx *= e.binomial.get(part.group_len[1])?[48 - part.group_len[0]];
+            } else {
+                part.group_idx[next] = idx;
+                idx *= *e.binomial.get(part.group_len[next])?.get(free_squares)?;
+                free_squares = free_squares.checked_sub(part.group_len[next])?;
+                next += 1;
+            }
+            k += 1;
+            if k > 0xf {
+                return None;
+            }
+        }
+        part.group_idx[n] = idx;
+        Some(())
+    }
+
+    //index of the position; flipped: the colors of the position are swapped to match
+    //the table (the stronger side is always white in the files)
+    pub fn lookup(&self, pos: &Position, flipped: bool) -> Option<Lookup> {
+        let e = &ENCODING;
+        let black_to_move = pos.player_to_move == Color::Black;
+        //symmetric tables only store white to move
+        let mirror = flipped || (self.symmetric && black_to_move);
+        let flip_color = if mirror { 8 } else { 0 };
+        let flip_squares = if mirror { 56 } else { 0 };
+        let side = (mirror ^ black_to_move) as usize;
+
+        let mut sqs = [0usize; MAX_PIECES];
+        let mut pieces = [0u8; MAX_PIECES];
+        let mut size = 0;
+        let mut lead_pawns: Bitboard = 0;
+        let mut file = 0;
+
+        //with pawns the table is split by the file of the leading pawn, which is
+        //also first in pieces[] of every part
+        if self.has_pawns {
+            let lead = self.parts[0][0].pieces[0] ^ flip_color;
+            let color = if lead & 8 == 0 { Color::White } else { Color::Black };
+            lead_pawns = pos.bitboards.piece(color, PieceKind::Pawn);
+            for sq in squares(lead_pawns) {
+                *sqs.get_mut(size)? = sq ^ flip_squares;
+                size += 1;
+            }
+            let leading = (0..size).max_by_key(|&i| e.map_pawns[sqs[i]])?;
+            sqs.swap(0, leading);
+            file = file_of(sqs[0]).min(file_of(sqs[0] ^ 7));
+        }
+        let lead_count = size;
+
+        for sq in squares(pos.bitboards.occupied() & !lead_pawns) {
+            let Cell::Piece(piece) = pos.board[SQUARE64_TO_SQUARE120[sq]] else {
+                return None;
+            };
+            *sqs.get_mut(size)? = sq ^ flip_squares;
+            pieces[size] = (piece.kind.idx() as u8 + 1 + 8 * piece.color.idx() as u8) ^ flip_color;
+            size += 1;
+        }
+        if size != self.piece_count {
+            return None;
+        }
+
+        let part = &self.parts[side % self.parts.len()][file];
+        //same piece order as the table
+        for i in lead_count..size.saturating_sub(1) {
+            if let Some(j) = (i + 1..size).find(|&j| pieces[j] == part.pieces[i]) {
+                pieces.swap(i, j);
+                sqs.swap(i, j);
+            }
+        }
+
+        //leading piece onto files a-d
+        if file_of(sqs[0]) > 3 {
+            for sq in &mut sqs[..size] {
+                *sq ^= 7;
+            }
+        }
+
+        let mut idx;
+        if self.has_pawns {
+            idx = e.lead_pawn_idx[lead_count][sqs[0]];
+            sqs[1..lead_count].sort_by_key(|&sq| e.map_pawns[sq]);
+            for (i, &sq) in sqs.iter().enumerate().take(lead_count).skip(1) {
+                idx += e.binomial[i][e.map_pawns[sq]];
+            }
+        } else {
+            //leading piece onto ranks 1-4
+            if rank_of(sqs[0]) > 3 {
+                for sq in &mut sqs[..size] {
+                    *sq ^= 56;
+                }
+            }
+            //first piece of the leading group off the a1-h8 diagonal goes below it
+            for i in 0..part.group_len[0] {
+                let off = off_diagonal(sqs[i]);
+                if off == 0 {
+                    continue;
+                }
+                if off > 0 {
+                    for sq in &mut sqs[i..size] {
+                        *sq = ((*sq >> 3) | (*sq << 3)) & 63;
+                    }
+                }
+                break;
+            }
+            idx = if self.has_unique_pieces {
+                Self::unique_pieces_index(&sqs)
+            } else {
+                e.map_kk[e.map_a1d1d4[sqs[0]]][sqs[1]]
+            };
+        }
+        idx *= part.group_idx[0];
+
+        //remaining groups by ascending square, squares taken by earlier groups skipped
+        let mut start = part.group_len[0];
+        let mut remaining_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let mut next = 1;
+        while part.group_len[next] != 0 {
+            let len = part.group_len[next];
+            let group = start..start + len;
+            sqs.get_mut(group.clone())?.sort_unstable();
+            let mut n = 0;
+            for i in 0..len {
+                let sq = sqs[start + i];
+                let below = sqs[..start].iter().filter(|&&s| s < sq).count();
+                let skipped = below + if remaining_pawns { 8 } else { 0 };
+                n += e.binomial[i + 1][sq.checked_sub(skipped)?];
+            }
+            remaining_pawns = false;
+            idx += n * part.group_idx[next];
+            start += len;
+            next += 1;
+        }
+
+        Some(Lookup { side, file, idx })
+    }
+
+    //three unique pieces, the first in the a1-d1-d4 triangle and the first one off
+    //the diagonal below it
+    fn unique_pieces_index(sqs: &[usize; MAX_PIECES]) -> u64 {
+        let e = &ENCODING;
+        let [s0, s1, s2] = [sqs[0], sqs[1], sqs[2]];
+        let adjust1 = (s1 > s0) as usize;
+        let adjust2 = (s2 > s0) as usize + (s2 > s1) as usize;
+        let idx = if off_diagonal(s0) != 0 {
+            (e.map_a1d1d4[s0] * 63 + s1 - adjust1) * 62 + s2 - adjust2
+        } else if off_diagonal(s1) != 0 {
+            (6 * 63 + rank_of(s0) * 28 + e.map_b1h1h7[s1]) * 62 + s2 - adjust2
+        } else if off_diagonal(s2) != 0 {
+            6 * 63 * 62 + 4 * 28 * 62 + rank_of(s0) * 7 * 28 + (rank_of(s1) - adjust1) * 28 + e.map_b1h1h7[s2]
+        } else {
+            6 * 63 * 62
+                + 4 * 28 * 62
+                + 4 * 7 * 28
+                + rank_of(s0) * 7 * 6
+                + (rank_of(s1) - adjust1) * 6
+                + rank_of(s2)
+                - adjust2
+        };
+        idx as u64
+    }
+
+    pub fn wdl(&self, lookup: &Lookup) -> Option<Wdl> {
+        let part = &self.parts[lookup.side % self.parts.len()][lookup.file];
+        Wdl::from_stored(self.decompress(part, lookup.idx)?)
+    }
+
+    //dtz in plies from the stored value, None if the table only has the other side
+    //to move (the caller then searches one ply); wdl is the known outcome
+    pub fn dtz(&self, lookup: &Lookup, wdl: Wdl) -> Option<Option<i32>> {
+        let part = &self.parts[0][lookup.file];
+        let stored_side = (part.flags & FLAG_STM) as usize;
+        //without pawns a symmetric table serves both sides
+        let both_sides = self.symmetric && !self.has_pawns;
+        if stored_side != lookup.side && !both_sides {
+            return Some(None);
+        }
+
+        let mut value = self.decompress(part, lookup.idx)? as usize;
+        if part.flags & FLAG_MAPPED != 0 {
+            let map = part.map_idx[match wdl {
+                Wdl::Loss => 1,
+                Wdl::BlessedLoss => 3,
+                Wdl::CursedWin => 2,
+                Wdl::Win | Wdl::Draw => 0,
+            }];
+            value = if part.flags & FLAG_WIDE != 0 {
+                u16_le(&self.data, self.map + 2 * (map + value))? as usize
+            } else {
+                byte(&self.data, self.map + map + value)? as usize
+            };
+        }
+
+        //stored in moves unless the table says plies
+        let in_moves = match wdl {
+            Wdl::Win => part.flags & FLAG_WIN_PLIES == 0,
+            Wdl::Loss => part.flags & FLAG_LOSS_PLIES == 0,
+            Wdl::CursedWin | Wdl::BlessedLoss => true,
+            Wdl::Draw => false,
+        };
+        if in_moves {
+            value *= 2;
+        }
+        Some(Some(value as i32 + 1))
+    }
+
+    //value number idx of a part: find its block through the sparse index, walk the
+    //huffman codes of the block to the symbol holding it, then expand that symbol
+    fn decompress(&self, part: &PairsData, idx: u64) -> Option<u16> {
+        if part.flags & FLAG_SINGLE_VALUE != 0 {
+            return Some(part.min_sym_len as u16);
+        }
+        let data = &self.data[..];
+
+        //sparse entry k describes value k * span + span / 2
+        let k = (idx / part.span) as usize;
+        if k >= part.sparse_index_size {
+            return None;
+        }
+        let entry = part.sparse_index + 6 * k;
+        let mut block = u32_le(data, entry)? as usize;
+        let mut offset = u16_le(data, entry + 4)? as i64 + (idx % part.span) as i64 - (part.span / 2) as i64;
+
+        let block_length = |block: usize| -> Option<i64> {
+            if block >= part.block_lengths_size {
+                return None;
+            }
+            Some(u16_le(data, part.block_lengths + 2 * block)? as i64)
+        };
+        while offset < 0 {
+            block = block.checked_sub(1)?;
+            offset += block_length(block)? + 1;
+        }
+        while offset > block_length(block)? {
+            offset -= block_length(block)? + 1;
+            block += 1;
+        }
+        if block >= part.blocks {
+            return None;
+        }
+
+        let min_len = part.min_sym_len as usize;
+        let mut ptr = part.blocks_data + block * part.block_size;
+        let mut buf = u64_be(data, ptr)?;
+        ptr += 8;
+        let mut buf_size = 64;
+        let mut sym;
+        loop {
+            //codes are canonical: longer codes have smaller values
+            let mut len = 0;
+            while buf < *part.base64.get(len)? {
+                len += 1;
+            }
+            let shift = 64usize.checked_sub(len + min_len).filter(|&s| s < 64)?;
+            sym = (((buf - part.base64[len]) >> shift) as u16).wrapping_add(u16_le(data, part.lowest_sym + 2 * len)?);
+            let count = *part.symlen.get(sym as usize)? as i64 + 1;
+            if offset < count {
+                break;
+            }
+            offset -= count;
+            let bits = len + min_len;
+            buf = buf.checked_shl(bits as u32).unwrap_or(0);
+            buf_size -= bits as i64;
+            if buf_size <= 32 {
+                buf_size += 32;
+                buf |= (u32_be(data, ptr)? as u64) << (64 - buf_size);
+                ptr += 4;
+            }
+        }
+
+        //symbols are pairs of symbols, the value is a leaf
+        while part.symlen[sym as usize] != 0 {
+            let (left, right) = pair(data, part.btree, sym)?;
+            let left_count = *part.symlen.get(left as usize)? as i64 + 1;
+            if offset < left_count {
+                sym = left;
+            } else {
+                offset -= left_count;
+                sym = right;
+            }
+            part.symlen.get(sym as usize)?;
+        }
+        Some(pair(data, part.btree, sym)?.0)
+    }
+}
+
+impl PairsData {
+    //compression parameters and the huffman code of one part, returns the offset after them
+    fn set_sizes(&mut self, data: &[u8], mut at: usize) -> Option<usize> {
+        self.flags = byte(data, at)?;
+        at += 1;
+        if self.flags & FLAG_SINGLE_VALUE != 0 {
+            self.min_sym_len = byte(data, at)?;
+            return Some(at + 1);
+        }
+
+        let groups = self.group_len.iter().position(|&len| len == 0)?;
+        let size = self.group_idx[groups];
+        self.block_size = 1usize.checked_shl(byte(data, at)? as u32)?;
+        self.span = 1u64.checked_shl(byte(data, at + 1)? as u32)?;
+        self.sparse_index_size = size.div_ceil(self.span) as usize;
+        let padding = byte(data, at + 2)? as usize;
+        self.blocks = u32_le(data, at + 3)? as usize;
+        //padded so the sparse index never points past the end
+        self.block_lengths_size = self.blocks + padding;
+        let max_sym_len = byte(data, at + 7)?;
+        self.min_sym_len = byte(data, at + 8)?;
+        at += 9;
+        self.lowest_sym = at;
+        if max_sym_len < self.min_sym_len || self.min_sym_len == 0 {
+            return None;
+        }
+
+        let lengths = (max_sym_len - self.min_sym_len) as usize + 1;
+        self.base64 = vec![0; lengths];
+        for i in (0..lengths - 1).rev() {
+            let lowest = u16_le(data, at + 2 * i)? as u64;
+            let lowest_next = u16_le(data, at + 2 * i + 2)? as u64;
+            self.base64[i] = (self.base64[i + 1] + lowest).checked_sub(lowest_next)? / 2;
+        }
+        for (i, base) in self.base64.iter_mut().enumerate() {
+            let shift = 64usize.checked_sub(i + self.min_sym_len as usize)?;
+            *base = base.checked_shl(shift as u32).unwrap_or(0);
+        }
+        at += 2 * lengths;
+
+        let symbols = u16_le(data, at)? as usize;
+        at += 2;
+        self.btree = at;
+        self.symlen = vec![0; symbols];
+        let mut visited = vec![false; symbols];
+        for sym in 0..symbols {
+            if !visited[sym] {
+                self.symlen[sym] = self.set_symlen(data, sym, &mut visited)?;
+            }
+        }
+        Some(at + 3 * symbols + (symbols & 1))
+    }
+
+    fn set_symlen(&mut self, data: &[u8], sym: usize, visited: &mut [bool]) -> Option<u8> {
+        visited[sym] = true;
+        let (left, right) = pair(data, self.btree, sym as u16)?;
+        if right == 0xfff {
+            return Some(0);
+        }
+        let [left, right] = [left as usize, right as usize];
+        for child in [left, right] {
+            if *visited.get(child)? {
+                continue;
+            }
+            self.symlen[child] = self.set_symlen(data, child, visited)?;
+        }
+        Some(self.symlen[left].wrapping_add(self.symlen[right]).wrapping_add(1))
+    }
+}
+
+impl Wdl {
+    fn from_stored(value: u16) -> Option<Self> {
+        Some(match value {
+            0 => Wdl::Loss,
+            1 => Wdl::BlessedLoss,
+            2 => Wdl::Draw,
+            3 => Wdl::CursedWin,
+            4 => Wdl::Win,
+            _ => return None,
+        })
+    }
+}
+
+//symbol sym of the pair tree: 12 bits left, 12 bits right (0xfff for a leaf, whose
+//value is the left one)
+fn pair(data: &[u8], btree: usize, sym: u16) -> Option<(u16, u16)> {
+    let at = btree + 3 * sym as usize;
+    let [a, b, c] = [byte(data, at)?, byte(data, at + 1)?, byte(data, at + 2)?].map(u16::from);
+    Some((((b & 0xf) << 8) | a, (c << 4) | (b >> 4)))
+}
+
+#[inline]
+fn byte(data: &[u8], at: usize) -> Option<u8> {
+    data.get(at).copied()
+}
+
+#[inline]
+fn u16_le(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+#[inline]
+fn u32_le(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+#[inline]
+fn u32_be(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+#[inline]
+fn u64_be(data: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(data.get(at..at + 8)?.try_into().ok()?))
+}
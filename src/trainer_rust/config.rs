@@ -1,10 +1,296 @@
-//define constants here
+//run settings for the trainer, from a json config and/or command line flags
+//flags override the config file, fields missing from the file keep their defaults
 
-pub const CHUNK_PATH: &str = "D:\\127\\uni\\chessdata\\chunks\\chunk_1.jsonl"; //not needed anymore  was an alternative for dataset loading
-pub const MODEL_PATH: &str = "src/trainer_rust/models/mlp_checkpoint_2.json";
-pub const POSITIONS_PATH: &str = "D:/127/uni/chessdata/lichess_db_eval.jsonl";
-pub const OPTIMIZER_SAVE_PATH: &str = "src/trainer_rust/models/optimizer_checkpoint_2.json";
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
-pub const MODEL_PATH_2: &str = "src/trainer_rust/models/mlp_checkpoint_3.json";
-pub const OPTIMIZER_SAVE_PATH_2: &str = "src/trainer_rust/models/optimizer_checkpoint_3.json";
-//file lines : 342059879
+use anyhow::{Context, bail};
+use serde::{Deserialize, Serialize};
+
+//the engine loads src/trainer_rust/models/mlp_checkpoint_3.json by default
+pub const DEFAULT_CHECKPOINT_DIR: &str = "src/trainer_rust/models";
+pub const DEFAULT_NAME: &str = "mlp_checkpoint_3";
+
+pub const USAGE: &str = "usage: train [--config run.json] [--dataset evals.jsonl]... [--positions N]
+             [--valid-split F] [--hidden H1,H2] [--epochs N] [--batch-size N] [--lr F]
+             [--schedule constant|plateau[:FACTOR[:THRESHOLD]]|step:EVERY[:FACTOR]]
+             [--checkpoint-dir DIR] [--name NAME] [--resume] [--fresh-optimizer] [--seed N]";
+
+//halving when the valid loss stalls by less than 1e-4 is what the runs so far used
+const DEFAULT_FACTOR: f64 = 0.5;
+const DEFAULT_THRESHOLD: f32 = 1e-4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum LrSchedule {
+    Constant,
+    //scale by factor when the valid loss did not beat the best by threshold
+    Plateau { factor: f64, threshold: f32 },
+    //scale by factor after every `every` epochs
+    Step { every: usize, factor: f64 },
+}
+
+impl LrSchedule {
+    //lr for the epoch after `epoch`
+    pub fn next(&self, lr: f64, epoch: usize, valid_loss: f32, best_valid_loss: f32) -> f64 {
+        match *self {
+            LrSchedule::Constant => lr,
+            LrSchedule::Plateau { factor, threshold } => {
+                if valid_loss > best_valid_loss - threshold { lr * factor } else { lr }
+            }
+            LrSchedule::Step { every, factor } => {
+                if (epoch + 1).is_multiple_of(every) { lr * factor } else { lr }
+            }
+        }
+    }
+}
+
+impl Default for LrSchedule {
+    fn default() -> Self {
+        LrSchedule::Plateau { factor: DEFAULT_FACTOR, threshold: DEFAULT_THRESHOLD }
+    }
+}
+
+impl FromStr for LrSchedule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let mut parts = s.split(':');
+        let kind = parts.next().unwrap_or_default();
+        let args: Vec<&str> = parts.collect();
+        let schedule = match (kind, args.as_slice()) {
+            ("constant", []) => LrSchedule::Constant,
+            ("plateau", rest) if rest.len() <= 2 => LrSchedule::Plateau {
+                factor: rest.first().map_or(Ok(DEFAULT_FACTOR), |f| f.parse())?,
+                threshold: rest.get(1).map_or(Ok(DEFAULT_THRESHOLD), |t| t.parse())?,
+            },
+            ("step", [every, rest @ ..]) if rest.len() <= 1 => LrSchedule::Step {
+                every: every.parse()?,
+                factor: rest.first().map_or(Ok(DEFAULT_FACTOR), |f| f.parse())?,
+            },
+            _ => bail!("unknown lr schedule {s:?}"),
+        };
+        Ok(schedule)
+    }
+}
+
+impl fmt::Display for LrSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LrSchedule::Constant => write!(f, "constant"),
+            LrSchedule::Plateau { factor, threshold } => write!(f, "plateau:{factor}:{threshold}"),
+            LrSchedule::Step { every, factor } => write!(f, "step:{every}:{factor}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrainConfig {
+    //lichess eval jsonl files, positions are sampled from all of them by line count
+    pub datasets: Vec<PathBuf>,
+    pub positions: usize,
+    //share of the sampled positions held back for validation
+    pub valid_split: f32,
+    //hidden layer sizes of the 781 -> h1 -> h2 -> 1 MLP
+    pub hidden: [usize; 2],
+    pub epochs: usize,
+    pub batch_size: usize,
+    pub lr: f64,
+    pub schedule: LrSchedule,
+    pub checkpoint_dir: PathBuf,
+    //file stem of everything written to checkpoint_dir
+    pub name: String,
+    //continue from the model (and optimizer) saved under checkpoint_dir/name
+    pub resume: bool,
+    //with resume: keep the model but start a new optimizer
+    pub fresh_optimizer: bool,
+    //sampling and shuffling seed, random when not set
+    pub seed: Option<u64>,
+}
+
+impl Default for TrainConfig {
+    fn default() -> Self {
+        TrainConfig {
+            datasets: Vec::new(),
+            positions: 6_500_000,
+            valid_split: 0.2,
+            hidden: [256, 64],
+            epochs: 20,
+            batch_size: 32,
+            lr: 2e-6,
+            schedule: LrSchedule::default(),
+            checkpoint_dir: PathBuf::from(DEFAULT_CHECKPOINT_DIR),
+            name: DEFAULT_NAME.to_string(),
+            resume: false,
+            fresh_optimizer: false,
+            seed: None,
+        }
+    }
+}
+
+impl TrainConfig {
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).with_context(|| format!("cannot read {}", path.display()))?;
+        serde_json::from_str(&text).with_context(|| format!("invalid config {}", path.display()))
+    }
+
+    //the command line without the program name, --config is read before the other flags
+    pub fn from_args(args: &[String]) -> anyhow::Result<Self> {
+        let mut config = match args.iter().position(|arg| arg == "--config") {
+            Some(i) => match args.get(i + 1) {
+                Some(path) => Self::load(path)?,
+                None => bail!("--config needs a value"),
+            },
+            None => Self::default(),
+        };
+
+        let mut datasets = Vec::new();
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            match flag.as_str() {
+                "--resume" => config.resume = true,
+                "--fresh-optimizer" => config.fresh_optimizer = true,
+                _ => {
+                    let Some(value) = args.next() else {
+                        bail!("{flag} needs a value");
+                    };
+                    config.set(flag, value, &mut datasets).with_context(|| format!("{flag} {value}"))?;
+                }
+            }
+        }
+        //datasets on the command line replace the ones from the config
+        if !datasets.is_empty() {
+            config.datasets = datasets;
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn set(&mut self, flag: &str, value: &str, datasets: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+        match flag {
+            "--config" => {}
+            "--dataset" => datasets.push(PathBuf::from(value)),
+            "--positions" => self.positions = value.parse()?,
+            "--valid-split" => self.valid_split = value.parse()?,
+            "--hidden" => {
+                let Some((h1, h2)) = value.split_once(',') else {
+                    bail!("expected two sizes like 256,64");
+                };
+                self.hidden = [h1.trim().parse()?, h2.trim().parse()?];
+            }
+            "--epochs" => self.epochs = value.parse()?,
+            "--batch-size" => self.batch_size = value.parse()?,
+            "--lr" => self.lr = value.parse()?,
+            "--schedule" => self.schedule = value.parse()?,
+            "--checkpoint-dir" => self.checkpoint_dir = PathBuf::from(value),
+            "--name" => self.name = value.to_string(),
+            "--seed" => self.seed = Some(value.parse()?),
+            _ => bail!("unknown flag"),
+        }
+        Ok(())
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.datasets.is_empty() {
+            bail!("no dataset given");
+        }
+        if !(self.valid_split > 0.0 && self.valid_split < 1.0) {
+            bail!("valid split must be between 0 and 1, got {}", self.valid_split);
+        }
+        let valid = self.valid_size();
+        if valid == 0 || valid == self.positions {
+            bail!("{} positions leave no train or no valid positions", self.positions);
+        }
+        if self.hidden.contains(&0) || self.epochs == 0 || self.batch_size == 0 {
+            bail!("hidden sizes, epochs and batch size must be positive");
+        }
+        if self.lr.is_nan() || self.lr <= 0.0 {
+            bail!("learning rate must be positive, got {}", self.lr);
+        }
+        if let LrSchedule::Step { every: 0, .. } = self.schedule {
+            bail!("step schedule needs a positive epoch count");
+        }
+        if self.name.is_empty() {
+            bail!("empty checkpoint name");
+        }
+        Ok(())
+    }
+
+    pub fn valid_size(&self) -> usize {
+        (self.positions as f64 * self.valid_split as f64).round() as usize
+    }
+
+    pub fn model_path(&self) -> PathBuf {
+        self.checkpoint_dir.join(format!("{}.json", self.name))
+    }
+
+    //binary copy of the model for the engine (nn_model::format)
+    pub fn network_path(&self) -> PathBuf {
+        self.checkpoint_dir.join(format!("{}.nn", self.name))
+    }
+
+    pub fn optimizer_path(&self) -> PathBuf {
+        self.checkpoint_dir.join(format!("{}_optimizer.json", self.name))
+    }
+
+    //one metrics log per run, named by its start time
+    pub fn metrics_path(&self, started: u64) -> PathBuf {
+        self.checkpoint_dir.join(format!("{}_run_{started}.json", self.name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn flags_override_the_config_file() {
+        let path = std::env::temp_dir().join(format!("train-config-{}.json", std::process::id()));
+        fs::write(&path, r#"{"datasets": ["a.jsonl"], "epochs": 3, "schedule": {"kind": "constant"}}"#).unwrap();
+        let line = format!("--config {} --epochs 5 --hidden 512,32 --resume --seed 9", path.display());
+        let config = TrainConfig::from_args(&args(&line));
+        fs::remove_file(&path).unwrap();
+
+        let config = config.unwrap();
+        assert_eq!(config.datasets, vec![PathBuf::from("a.jsonl")]);
+        assert_eq!(config.epochs, 5);
+        assert_eq!(config.hidden, [512, 32]);
+        assert_eq!(config.schedule, LrSchedule::Constant);
+        assert!(config.resume && !config.fresh_optimizer);
+        assert_eq!(config.seed, Some(9));
+        //untouched fields keep their defaults
+        assert_eq!(config.batch_size, 32);
+        assert_eq!(config.model_path(), Path::new(DEFAULT_CHECKPOINT_DIR).join("mlp_checkpoint_3.json"));
+
+        assert!(TrainConfig::from_args(&args("--epochs 5")).is_err());
+        assert!(TrainConfig::from_args(&args("--dataset a.jsonl --valid-split 1.5")).is_err());
+        assert!(TrainConfig::from_args(&args("--dataset a.jsonl --batch-size")).is_err());
+        assert!(TrainConfig::from_args(&args("--dataset a.jsonl --warmup 3")).is_err());
+    }
+
+    #[test]
+    fn lr_schedules() {
+        let plateau: LrSchedule = "plateau".parse().unwrap();
+        assert_eq!(plateau, LrSchedule::default());
+        assert_eq!(plateau.next(1.0, 0, 0.5, 0.4), 0.5);
+        assert_eq!(plateau.next(1.0, 0, 0.3, 0.4), 1.0);
+
+        let step: LrSchedule = "step:2:0.1".parse().unwrap();
+        assert_eq!(step.next(1.0, 0, 0.3, 0.4), 1.0);
+        assert_eq!(step.next(1.0, 1, 0.3, 0.4), 0.1);
+
+        for schedule in [LrSchedule::Constant, plateau, step] {
+            assert_eq!(schedule.to_string().parse::<LrSchedule>().unwrap(), schedule);
+        }
+        assert!("step".parse::<LrSchedule>().is_err());
+        assert!("cosine".parse::<LrSchedule>().is_err());
+    }
+}
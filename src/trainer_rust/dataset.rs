@@ -1,18 +1,21 @@
 //here we will handle the dataset loading
 
+use crate::evaluation::accumulator::DEFAULT_OUTPUT_SCALE;
 use crate::trainer_rust::decode_fen::decode_data;
+use anyhow::{Context, bail};
 use burn::data::dataloader::batcher::Batcher;
 use burn::data::dataloader::{DataLoader, DataLoaderBuilder};
 use burn::data::dataset::Dataset;
 use burn::tensor::Tensor;
 use burn::tensor::backend::Backend;
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
 use rand::seq::index::sample;
-use rand::thread_rng;
 use serde_json::Value;
 use std::collections::HashSet;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 #[derive(Clone)]
@@ -78,40 +81,104 @@ impl<B: Backend> Batcher<B, ([f32; 781], f32), ChessBatch<B>> for ChessBatcher {
 //we also need to create a Dataloader? which will load the data into the training run
 pub fn create_dataloader<B: Backend>(
     dataset: Arc<ChessDataset>,
+    batch_size: usize,
+    seed: u64,
 ) -> Arc<dyn DataLoader<B, ChessBatch<B>>> {
     //here we can change the batch size the shuffle etc (shuffle parameter is a seed for shuffling)
     return DataLoaderBuilder::new(ChessBatcher)
-        .batch_size(32)
-        .shuffle(597657)
+        .batch_size(batch_size)
+        .shuffle(seed)
         .build(dataset);
 }
 //also create the same thing but for the validation dataset without shuffling
 pub fn create_valid_dataloader<B: Backend>(
     dataset: ChessDataset,
+    batch_size: usize,
 ) -> Arc<dyn DataLoader<B, ChessBatch<B>>> {
     return DataLoaderBuilder::new(ChessBatcher)
-        .batch_size(32)
+        .batch_size(batch_size)
         .build(dataset);
 }
 
-//the function which gets called in trainer_main which will load the data from the main 342m dataset
-pub fn load_dataset(path: &str, batch_size: usize) -> ChessDataset {
-    let dataset_size = 342_059_879;
-    let batch_size = batch_size;
-    let mut rng = thread_rng();
-    let indices = sample(&mut rng, dataset_size, batch_size);
+//the function which gets called in trainer_main which samples positions from the eval files
+//each file gives its share of the positions by line count
+pub fn load_dataset(paths: &[PathBuf], positions: usize, rng: &mut StdRng) -> anyhow::Result<ChessDataset> {
+    let mut lines = Vec::with_capacity(paths.len());
+    for path in paths {
+        lines.push(count_lines(path).with_context(|| format!("cannot read {}", path.display()))?);
+    }
+    let total: usize = lines.iter().sum();
+    if positions > total {
+        bail!("{positions} positions asked but the datasets only have {total}");
+    }
+
+    let mut positions_x = Vec::with_capacity(positions);
+    let mut evals_y = Vec::with_capacity(positions);
+    let mut taken = 0;
+    for (i, (path, &file_lines)) in paths.iter().zip(&lines).enumerate() {
+        //the last file takes the rounding remainder
+        let share = if i + 1 == paths.len() {
+            positions - taken
+        } else {
+            (positions as u128 * file_lines as u128 / total as u128) as usize
+        };
+        taken += share;
+        sample_file(path, file_lines, share, rng, &mut positions_x, &mut evals_y)
+            .with_context(|| format!("cannot load {}", path.display()))?;
+    }
+
+    //here we zip together the evals and position because we want them to stay together when we shuffle the vectors for randomly distributed positions across our vector
+    let mut samples_vec: Vec<([f32; 781], f32)> =
+        positions_x.into_iter().zip(evals_y.into_iter()).collect();
+
+    samples_vec.shuffle(rng);
+
+    let (positions_x_rndm, evals_y_rndm) = samples_vec.into_iter().unzip();
+
+    //we return our ChessDataset which has vec for positons and vec for the corresponding evals
+    return Ok(ChessDataset {
+        positions: positions_x_rndm,
+        evals: evals_y_rndm,
+    });
+}
+
+//the lichess file is too big to know its size by heart (342m lines when it was written)
+fn count_lines(path: &Path) -> io::Result<usize> {
+    let mut reader = BufReader::with_capacity(1 << 20, File::open(path)?);
+    let mut lines = 0;
+    let mut last = b'\n';
+    loop {
+        let buf = reader.fill_buf()?;
+        let Some(&end) = buf.last() else {
+            break;
+        };
+        lines += buf.iter().filter(|&&b| b == b'\n').count();
+        last = end;
+        let len = buf.len();
+        reader.consume(len);
+    }
+    //a last line without a newline still counts
+    Ok(lines + usize::from(last != b'\n'))
+}
+
+fn sample_file(
+    path: &Path,
+    file_lines: usize,
+    count: usize,
+    rng: &mut StdRng,
+    positions_x: &mut Vec<[f32; 781]>,
+    evals_y: &mut Vec<f32>,
+) -> anyhow::Result<()> {
+    let indices = sample(rng, file_lines, count);
 
     let hash_indices: HashSet<usize> = indices.iter().collect();
 
     let mut progress_counter: usize = 0;
     //first we open the file and store it in the variable
-    let file = File::open(path).unwrap();
+    let file = File::open(path)?;
     //then we give the variable into the reader
     let reader = BufReader::new(file);
 
-    let mut positions_x = Vec::with_capacity(batch_size);
-    let mut evals_y = Vec::with_capacity(batch_size);
-
     //here we take each position from the dataset that is from the vector of random indices and append it and its eval to the corresponding vectors , so we get data from all over the dataset to avoid feeding it too similar data and hopefully  get more generalization
     for (index, line) in reader.lines().enumerate() {
         if !hash_indices.contains(&index) {
@@ -123,60 +190,79 @@ pub fn load_dataset(path: &str, batch_size: usize) -> ChessDataset {
             println!("loading position : {}", progress_counter);
         }
         //get each positions(1 pos per line) from the jsonl
-        let line = line.unwrap();
-        let data: serde_json::Value = serde_json::from_str(&line).unwrap(); //load the json here
-
-        //at best_eval as_array returns option so unwrap for that and max_by_key also returns option
-        let fen = data.get("fen").and_then(Value::as_str).unwrap();
-        //then we read out the evals from the data for each positons  and take the best one
-        let best_eval = data
-            .get("evals")
-            .and_then(Value::as_array)
-            .unwrap()
-            .iter()
-            .max_by_key(|x| x.get("depth").and_then(Value::as_i64).unwrap_or(0))
-            .unwrap();
-        let best_pv = &best_eval
-            .get("pvs")
-            .and_then(Value::as_array)
-            .and_then(|pvs| pvs.get(0))
-            .unwrap();
-        let label: f32;
-
-        if best_pv.get("cp").is_some() {
-            let scale = 600.0;
-            label = ((best_pv.get("cp").and_then(Value::as_f64).unwrap() as f32) / scale).tanh();
-        } else {
-            //if there is no cp in the pvs then there has to be a mate detected in which case we give it a more extreme evaluation
-            //get mate labels as 1.5 or -1.5 so its outside the normal -1.0 - 1.0 range from tanh
-
-            let mate = best_pv.get("mate").and_then(Value::as_i64).unwrap() as i32;
+        let line = line?;
+        let data: serde_json::Value =
+            serde_json::from_str(&line).with_context(|| format!("line {}", index + 1))?; //load the json here
+        let Some((fen, label)) = labelled_position(&data) else {
+            bail!("line {}: no fen or no evaluation", index + 1);
+        };
 
-            if mate > 0 {
-                label = 1.5;
-            } else {
-                label = -1.5;
-            }
-        }
         //decode the fen into our neuron format then pass it into the vector of positions and also push the eal into the eval vec
         positions_x.push(decode_data(fen));
         evals_y.push(label);
 
-        if positions_x.len() == batch_size {
+        if progress_counter == count {
             break;
         }
     }
-    //here we zip together the evals and position because we want them to stay together when we shuffle the vectors for randomly distributed positions across our vector
-    let mut samples_vec: Vec<([f32; 781], f32)> =
-        positions_x.into_iter().zip(evals_y.into_iter()).collect();
+    Ok(())
+}
 
-    samples_vec.shuffle(&mut rng);
+//the fen and the label of one lichess eval line
+fn labelled_position(data: &Value) -> Option<(&str, f32)> {
+    let fen = data.get("fen").and_then(Value::as_str)?;
+    //then we read out the evals from the data for each positons  and take the best one
+    let best_eval = data
+        .get("evals")
+        .and_then(Value::as_array)?
+        .iter()
+        .max_by_key(|x| x.get("depth").and_then(Value::as_i64).unwrap_or(0))?;
+    let best_pv = best_eval
+        .get("pvs")
+        .and_then(Value::as_array)
+        .and_then(|pvs| pvs.first())?;
 
-    let (positions_x_rndm, evals_y_rndm) = samples_vec.into_iter().unzip();
+    if let Some(cp) = best_pv.get("cp").and_then(Value::as_f64) {
+        return Some((fen, (cp as f32 / DEFAULT_OUTPUT_SCALE).tanh()));
+    }
+    //if there is no cp in the pvs then there has to be a mate detected in which case we give it a more extreme evaluation
+    //get mate labels as 1.5 or -1.5 so its outside the normal -1.0 - 1.0 range from tanh
+    let mate = best_pv.get("mate").and_then(Value::as_i64)?;
+    Some((fen, if mate > 0 { 1.5 } else { -1.5 }))
+}
 
-    //we return our ChessDataset which has vec for positons and vec for the corresponding evals
-    return ChessDataset {
-        positions: positions_x_rndm,
-        evals: evals_y_rndm,
-    };
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use std::fs;
+
+    #[test]
+    fn samples_labelled_positions_from_every_file() {
+        let dir = std::env::temp_dir().join(format!("train-dataset-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -";
+        //the deepest eval wins, a mate is outside the tanh range
+        let line = |evals: &str| format!(r#"{{"fen": "{start}", "evals": [{evals}]}}"#);
+        let first = dir.join("a.jsonl");
+        let second = dir.join("b.jsonl");
+        fs::write(
+            &first,
+            line(r#"{"depth": 10, "pvs": [{"cp": 600}]}, {"depth": 30, "pvs": [{"cp": 0}]}"#) + "\n",
+        )
+        .unwrap();
+        //no newline after the last line
+        fs::write(&second, line(r#"{"depth": 20, "pvs": [{"mate": -3}]}"#)).unwrap();
+
+        let paths = [first, second];
+        let data = load_dataset(&paths, 2, &mut StdRng::seed_from_u64(5)).unwrap();
+        let too_many = load_dataset(&paths, 3, &mut StdRng::seed_from_u64(5));
+        fs::remove_dir_all(&dir).unwrap();
+
+        let mut evals = data.evals.clone();
+        evals.sort_by(f32::total_cmp);
+        assert_eq!(evals, vec![-1.5, 0.0]);
+        assert!(data.positions.iter().all(|x| *x == decode_data(start)));
+        assert!(too_many.is_err());
+    }
 }
@@ -0,0 +1,120 @@
+//json log of one training run: the settings it ran with and the losses of every epoch
+//rewritten after each epoch so an interrupted run still leaves a complete file
+
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::evaluation::accumulator::DEFAULT_OUTPUT_SCALE;
+use crate::trainer_rust::config::TrainConfig;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EpochMetrics {
+    pub epoch: usize,
+    pub lr: f64,
+    pub train_loss: f32,
+    pub valid_loss: f32,
+    //rms error in centipawns, the labels are tanh(cp / 600)
+    pub train_cp: f32,
+    pub valid_cp: f32,
+    //best valid loss so far, the checkpoint was written
+    pub saved: bool,
+    pub seconds: f64,
+}
+
+impl EpochMetrics {
+    pub fn loss_to_cp(loss: f32) -> f32 {
+        loss.sqrt() * DEFAULT_OUTPUT_SCALE
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunLog {
+    //unix seconds
+    pub started: u64,
+    //with the seed that was actually used
+    pub config: TrainConfig,
+    pub train_positions: usize,
+    pub valid_positions: usize,
+    pub epochs: Vec<EpochMetrics>,
+}
+
+pub struct MetricsLog {
+    path: PathBuf,
+    log: RunLog,
+}
+
+impl MetricsLog {
+    pub fn start(config: &TrainConfig, train_positions: usize, valid_positions: usize) -> anyhow::Result<Self> {
+        let started = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        let path = config.metrics_path(started);
+        let log = RunLog { started, config: config.clone(), train_positions, valid_positions, epochs: Vec::new() };
+        let metrics = MetricsLog { path, log };
+        metrics.write()?;
+        Ok(metrics)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn log(&self) -> &RunLog {
+        &self.log
+    }
+
+    pub fn push(&mut self, epoch: EpochMetrics) -> anyhow::Result<()> {
+        self.log.epochs.push(epoch);
+        self.write()
+    }
+
+    fn write(&self) -> anyhow::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("cannot create {}", dir.display()))?;
+        }
+        let file = File::create(&self.path).with_context(|| format!("cannot write {}", self.path.display()))?;
+        serde_json::to_writer_pretty(BufWriter::new(file), &self.log)
+            .with_context(|| format!("cannot write {}", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_is_readable_after_every_epoch() {
+        let dir = std::env::temp_dir().join(format!("train-metrics-{}", std::process::id()));
+        let config = TrainConfig {
+            datasets: vec![PathBuf::from("a.jsonl")],
+            checkpoint_dir: dir.clone(),
+            seed: Some(3),
+            ..TrainConfig::default()
+        };
+        let mut metrics = MetricsLog::start(&config, 80, 20).unwrap();
+        let read = |path: &Path| -> RunLog { serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap() };
+        assert!(read(metrics.path()).epochs.is_empty());
+
+        let epoch = EpochMetrics {
+            epoch: 0,
+            lr: config.lr,
+            train_loss: 0.04,
+            valid_loss: 0.05,
+            train_cp: EpochMetrics::loss_to_cp(0.04),
+            valid_cp: EpochMetrics::loss_to_cp(0.05),
+            saved: true,
+            seconds: 1.5,
+        };
+        metrics.push(epoch.clone()).unwrap();
+        let log = read(metrics.path());
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(&log, metrics.log());
+        assert_eq!(log.config, config);
+        assert_eq!(log.epochs, vec![epoch]);
+        assert!((log.epochs[0].train_cp - 120.0).abs() < 1e-3);
+    }
+}
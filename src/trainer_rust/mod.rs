@@ -6,6 +6,8 @@ pub mod dataset;
 #[cfg(feature = "trainer")]
 pub mod decode_fen;
 #[cfg(feature = "trainer")]
+pub mod metrics;
+#[cfg(feature = "trainer")]
 pub mod train;
 #[cfg(feature = "trainer")]
 pub mod trainer_main;
@@ -5,10 +5,12 @@
 //then we need to iterate over the epochs and do the optmizer reset run the model calculate the loss and gradients then update with the optimizer in the loop for the batch
 //then after give back the loss for each epoch
 
-use crate::trainer_rust::config::{MODEL_PATH_2, OPTIMIZER_SAVE_PATH_2};
-use crate::trainer_rust::dataset::{ChessBatch, ChessBatcher, ChessDataset};
 use crate::nn_model::mlp_structure::MLP;
-use burn::data::dataloader::{DataLoader, DataLoaderBuilder};
+use crate::trainer_rust::config::TrainConfig;
+use crate::trainer_rust::dataset::{ChessBatch, ChessDataset, create_dataloader};
+use crate::trainer_rust::metrics::{EpochMetrics, MetricsLog};
+use anyhow::Context;
+use burn::data::dataloader::DataLoader;
 use burn::module::AutodiffModule;
 use burn::module::Module;
 use burn::nn::loss::MseLoss;
@@ -21,54 +23,60 @@ use burn::record::FullPrecisionSettings;
 use burn::record::PrettyJsonFileRecorder;
 use burn::record::Recorder;
 use burn::tensor::backend::AutodiffBackend;
+use std::fs;
 use std::io::{self, Write};
-use std::path::Path;
 use std::sync::Arc;
+use std::time::Instant;
 
 pub fn train<B: AutodiffBackend>(
     mut model: MLP<B>,
     dataset: Arc<ChessDataset>,
     val_loader: Arc<dyn DataLoader<B, ChessBatch<B>>>,
+    config: &TrainConfig,
+    metrics: &mut MetricsLog,
     device: &B::Device,
-) -> MLP<B> {
+) -> anyhow::Result<MLP<B>> {
     //initialize variable for later checking if val loss decreased
     let mut best_val_loss: f32 = 1000.0;
     //initialize optimizer
     let optimizer_config = AdamConfig::new();
     let mut optimizer = optimizer_config.init();
-    //load optimizer state if it exists
-    if Path::new(&OPTIMIZER_SAVE_PATH_2).exists() {
-        let device = device; // get backend device
+    let optimizer_path = config.optimizer_path();
+    //load optimizer state when resuming and it exists
+    if config.resume && !config.fresh_optimizer && optimizer_path.exists() {
         let recorder: PrettyJsonFileRecorder<FullPrecisionSettings> = PrettyJsonFileRecorder::new();
 
         //load the  tje optimizer state in the record
         let optimizer_record = recorder
             .load::<<OptimizerAdaptor<Adam, MLP<B>, B> as Optimizer<MLP<B>, B>>::Record>(
-                OPTIMIZER_SAVE_PATH_2.into(),
+                optimizer_path.clone(),
                 device,
             )
-            .expect("Failed to load optimizer record");
+            .with_context(|| format!("cannot load optimizer {}", optimizer_path.display()))?;
 
         // then we load the optimizer from the record
         optimizer = optimizer.load_record(optimizer_record);
 
         println!("Loaded optimizer from checkpoint.");
     }
+    fs::create_dir_all(&config.checkpoint_dir)
+        .with_context(|| format!("cannot create {}", config.checkpoint_dir.display()))?;
 
     //initialie loss function and define learning rate of optimizer
     let loss_function = MseLoss::new();
-    let mut lr = 2e-6;
+    let mut lr = config.lr;
+    let seed = config.seed.unwrap_or_default();
+    let batches_per_epoch = dataset.positions.len().div_ceil(config.batch_size);
 
-    //loop over epochs (wont go 20 epochs but str c when overfitting)
-    for epoch in 0..20 {
+    //loop over epochs (wont go all epochs but str c when overfitting)
+    for epoch in 0..config.epochs {
+        let started = Instant::now();
         let mut epoch_loss: f32 = 0.0;
         let mut batch_num = 0;
         let mut valid_batches = 0;
         //initialize the data loader and make randomly shuffled minibatches
-        let loader = DataLoaderBuilder::new(ChessBatcher)
-            .batch_size(32)
-            .shuffle(epoch as u64) //give epoch as seed to every epoch data gets shuffled again for more randomness and generalizing
-            .build(dataset.clone());
+        //seed + epoch so every epoch data gets shuffled again for more randomness and generalizing
+        let loader = create_dataloader::<B>(dataset.clone(), config.batch_size, seed.wrapping_add(epoch as u64));
 
         //do the forward pass and loss , optimizer for each minibatch
         for batch in loader.iter() {
@@ -80,7 +88,6 @@ pub fn train<B: AutodiffBackend>(
             let grad = loss_tensor.backward();
             let grads = GradientsParams::from_grads(grad, &model);
             batch_num += 1;
-            //change lr to ~5e-4 when new training runs arent are jumping too high then to 1e-4 and so on if overfitting or bigger batches?
             model = optimizer.step(lr, model, grads);
 
             //progress of the run
@@ -88,9 +95,9 @@ pub fn train<B: AutodiffBackend>(
                 print!(
                     "\r\x1b[2KBatch {} - {:.2}% ",
                     batch_num,
-                    (batch_num as f32 / 140_000.0) * 100.0
+                    (batch_num as f32 / batches_per_epoch as f32) * 100.0
                 );
-                io::stdout().flush().unwrap();
+                io::stdout().flush()?;
             }
             epoch_loss += loss_value;
         }
@@ -111,43 +118,57 @@ pub fn train<B: AutodiffBackend>(
         //calculate the loss and translate it into cp by scaling back and/or using atanh though that could be worse
         let average_epoch_loss = epoch_loss / batch_num as f32;
         let average_valid_loss = valid_loss / valid_batches as f32;
-        //let real_loss = average_epoch_loss.sqrt();
         println!(
             "Train - Epoch: {}     Loss: {}    cp: {}",
             epoch,
             average_epoch_loss,
-            average_epoch_loss.sqrt() * 600.0
+            EpochMetrics::loss_to_cp(average_epoch_loss)
         );
         println!(
             "Valid - Epoch: {}     Loss: {}    cp: {}, weird cp: {}",
             epoch,
             average_valid_loss,
-            average_valid_loss.sqrt() * 600.0,
+            EpochMetrics::loss_to_cp(average_valid_loss),
             average_valid_loss.sqrt().atanh() * 600.0
         );
 
-        //if valid loss plateaus we decrease lr to counter overfitting
-        if average_valid_loss > best_val_loss - 1e-4 {
-            lr = lr * 0.5;
-        }
+        let epoch_lr = lr;
+        //e.g. decrease lr when valid loss plateaus to counter overfitting
+        lr = config.schedule.next(lr, epoch, average_valid_loss, best_val_loss);
 
         //save a model if it preforms better doesnt save overfitted models
-        if average_valid_loss < best_val_loss {
+        let saved = average_valid_loss < best_val_loss;
+        if saved {
             best_val_loss = average_valid_loss;
             let best_model = model.clone(); // keep the best
             let recorder: PrettyJsonFileRecorder<FullPrecisionSettings> =
                 PrettyJsonFileRecorder::new();
 
+            //before save_file, which takes the model
+            best_model
+                .save_network(config.network_path())
+                .context("cannot save network file")?;
             best_model
-                .save_file(MODEL_PATH_2, &recorder)
-                .expect("Error in saving model");
+                .save_file(config.model_path(), &recorder)
+                .context("cannot save model")?;
 
             let optimizer_record = optimizer.to_record();
             recorder
-                .record(optimizer_record, OPTIMIZER_SAVE_PATH_2.into()) // Path can be whatever you want
-                .expect("Failed to save optimizer");
+                .record(optimizer_record, optimizer_path.clone())
+                .context("cannot save optimizer")?;
             println!("Still Fine!");
         }
+
+        metrics.push(EpochMetrics {
+            epoch,
+            lr: epoch_lr,
+            train_loss: average_epoch_loss,
+            valid_loss: average_valid_loss,
+            train_cp: EpochMetrics::loss_to_cp(average_epoch_loss),
+            valid_cp: EpochMetrics::loss_to_cp(average_valid_loss),
+            saved,
+            seconds: started.elapsed().as_secs_f64(),
+        })?;
     }
-    return model;
+    return Ok(model);
 }
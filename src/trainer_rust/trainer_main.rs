@@ -1,48 +1,61 @@
 //here we call the functions of the other files in the directory
+use anyhow::Context;
 use burn::backend::{Autodiff, NdArray}; //both wgpu and ndarray loaded trained on both will remove in final version after done with training
 use burn::module::Module;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
 use std::sync::Arc;
 
-use crate::trainer_rust::config::{MODEL_PATH_2, POSITIONS_PATH};
-use crate::trainer_rust::dataset::{ChessDataset, create_valid_dataloader, load_dataset};
 use crate::nn_model::mlp_structure::MLP;
+use crate::trainer_rust::config::TrainConfig;
+use crate::trainer_rust::dataset::{ChessDataset, create_valid_dataloader, load_dataset};
+use crate::trainer_rust::metrics::MetricsLog;
 use crate::trainer_rust::train::train;
 use burn::record::FullPrecisionSettings;
 use burn::record::PrettyJsonFileRecorder;
 
-pub fn main() {
+pub fn run(config: &TrainConfig) -> anyhow::Result<()> {
     //define backend and device to train on and recorder
     type B = Autodiff<NdArray<f32>>;
 
     let device = Default::default();
 
-    let recorder: PrettyJsonFileRecorder<FullPrecisionSettings> = PrettyJsonFileRecorder::new();
+    //the seed goes into the metrics log so a run can be repeated
+    let seed = config.seed.unwrap_or_else(rand::random);
+    let config = TrainConfig { seed: Some(seed), ..config.clone() };
+    let mut rng = StdRng::seed_from_u64(seed);
 
     println!("Loading Dataset");
     //load the traning data and valid data
-    let train_size = 5_300_000;
-    //let combined_datsets = load_dataset(positions_path, 5_500_000); //used most but possible to get more positoins in 1 run
-    let combined_datsets = load_dataset(POSITIONS_PATH, 6_500_000);
-    let dataset = Arc::new(ChessDataset {
-        positions: combined_datsets.positions[..train_size].to_vec(),
-        evals: combined_datsets.evals[..train_size].to_vec(),
-    });
+    let mut positions = load_dataset(&config.datasets, config.positions, &mut rng)?;
+    let train_size = config.positions - config.valid_size();
     let val_dataset = ChessDataset {
-        positions: combined_datsets.positions[train_size..].to_vec(),
-        evals: combined_datsets.evals[train_size..].to_vec(),
+        positions: positions.positions.split_off(train_size),
+        evals: positions.evals.split_off(train_size),
     };
-    //let dataloader = create_dataloader::<B>(Arc::clone(&dataset));
-    let val_dataloader = create_valid_dataloader::<B>(val_dataset);
+    let (train_len, valid_len) = (positions.positions.len(), val_dataset.positions.len());
+    let dataset = Arc::new(positions);
+    let val_dataloader = create_valid_dataloader::<B>(val_dataset, config.batch_size);
     println!("Starting training!");
-    //initalize model and load state
-    //if there is a trained modelavailable we use th line below the model initialization if not then we comment it out
+
+    //initalize model and load state when resuming
     //try doing 781 256 128 32 and if thats better try removing one hiddenlayer and do maybe 781 256 32 or 256 64 because for nnue better to use less hidden layers?
-    let mut model = MLP::<B>::new(781, 256, 64, &device);
-    model = model.load_file(MODEL_PATH_2, &recorder, &device).unwrap();
+    let [hidden1, hidden2] = config.hidden;
+    let mut model = MLP::<B>::new(781, hidden1, hidden2, &device);
+    if config.resume {
+        let recorder: PrettyJsonFileRecorder<FullPrecisionSettings> = PrettyJsonFileRecorder::new();
+        let path = config.model_path();
+        model = model
+            .load_file(path.clone(), &recorder, &device)
+            .with_context(|| format!("cannot resume from {}", path.display()))?;
+    }
 
-    let _trained_model = train::<B>(model, dataset, val_dataloader, &device);
+    let mut metrics = MetricsLog::start(&config, train_len, valid_len)?;
+    println!("Logging to {}", metrics.path().display());
+    let _trained_model = train::<B>(model, dataset, val_dataloader, &config, &mut metrics, &device)?;
 
     println!("Finished!");
+    Ok(())
 }
 
 //first call load_dataset for loading the training data